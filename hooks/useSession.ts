@@ -0,0 +1,25 @@
+import { useEffect, useState } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { getCurrentSession, SessionInfo } from "../lib/memo-api";
+
+// The signed-in user, shared by every window through "session:changed" events
+export const useSession = () => {
+  const [session, setSession] = useState<SessionInfo | null>(null);
+  const [loading, setLoading] = useState(true);
+
+  useEffect(() => {
+    getCurrentSession()
+      .then(setSession)
+      .catch((error) => console.error("Failed to load session:", error))
+      .finally(() => setLoading(false));
+
+    const unlistenPromise = listen<SessionInfo | null>("session:changed", (event) => {
+      setSession(event.payload);
+    });
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten());
+    };
+  }, []);
+
+  return { session, loading };
+};
@@ -0,0 +1,353 @@
+import { invoke } from "@tauri-apps/api/core"
+
+export interface SessionInfo {
+  user_id: string | null
+  email: string
+  username: string | null
+  logged_in_at: string
+  expires_at: string | null
+}
+
+// Payloads of "auth:expiring" (a few minutes ahead) and "auth:expired" (session already ended)
+export interface AuthExpiring {
+  email: string
+  expires_at: string
+  seconds_left: number
+}
+
+export interface AuthExpired {
+  email: string
+  reason: "expired" | "rejected"
+}
+
+// The backend token stays in Rust; commands use the session started by login
+export async function login(email: string, password: string): Promise<SessionInfo> {
+  return invoke<SessionInfo>("login_command", { email, password })
+}
+
+export async function logout(): Promise<void> {
+  return invoke<void>("logout_command")
+}
+
+export async function getCurrentSession(): Promise<SessionInfo | null> {
+  return invoke<SessionInfo | null>("current_session_command")
+}
+
+export interface Account {
+  id: string
+  email: string
+  username: string | null
+  user_id: string | null
+  added_at: string
+  last_used_at: string
+  active: boolean
+  signed_in: boolean
+}
+
+// Each account has its own library, caches and settings. Switching, or logging in as
+// another account, restarts the app into that account's profile
+export async function listAccounts(): Promise<Account[]> {
+  return invoke<Account[]>("list_accounts_command")
+}
+
+export async function switchAccount(id: string): Promise<void> {
+  return invoke<void>("switch_account_command", { id })
+}
+
+export interface MemoQuery {
+  cursor?: string | null
+  page_size?: number
+  sort_by?: "created_at" | "title" | "duration"
+  sort_dir?: "asc" | "desc"
+  tags?: string[]
+  created_from?: string
+  created_to?: string
+  has_transcript?: boolean
+}
+
+export interface MemoPage<T> {
+  items: T[]
+  next_cursor: string | null
+}
+
+// Walks every page of get_memos_command for views that need the whole library
+export async function fetchAllMemos<T>(query: MemoQuery = {}): Promise<T[]> {
+  const items: T[] = []
+  let cursor: string | null = null
+  do {
+    const page: MemoPage<T> = await invoke<MemoPage<T>>("get_memos_command", {
+      query: { page_size: 200, ...query, cursor },
+    })
+    items.push(...page.items)
+    cursor = page.next_cursor
+  } while (cursor)
+  return items
+}
+
+export interface SyncReport {
+  pulled: number
+  pushed: number
+  deleted_local: number
+  deleted_remote: number
+  conflicts: {
+    memo_id: string
+    title: string
+    policy: "local_wins" | "remote_wins" | "newest_wins" | "keep_both"
+    resolution: "kept_local" | "kept_remote" | "kept_both"
+  }[]
+}
+
+// Reconciles the local database with the backend; emits memo:updated when anything changed
+export async function syncMemos(): Promise<SyncReport> {
+  return invoke<SyncReport>("sync_memos_command")
+}
+
+export interface PendingOperation {
+  id: number
+  operation: "create" | "update" | "delete"
+  memo_id: string
+  title: string | null
+  audio_size: number | null
+  attempts: number
+  failed: boolean
+  last_error: string | null
+  created_at: string
+}
+
+export interface ReplayReport {
+  replayed: number
+  failed: number
+  remaining: number
+}
+
+// Memo writes queued while the backend was unreachable
+export async function listPendingOperations(): Promise<PendingOperation[]> {
+  return invoke<PendingOperation[]>("list_pending_operations_command")
+}
+
+// Replays the queue; pass an id to also retry an operation the backend rejected
+export async function retryPendingOperations(id?: number): Promise<ReplayReport> {
+  return invoke<ReplayReport>("retry_pending_operations_command", { id: id ?? null })
+}
+
+export async function discardPendingOperation(id: number): Promise<void> {
+  return invoke<void>("discard_pending_operation_command", { id })
+}
+
+export interface SearchHit<T> {
+  memo: T
+  score: number
+  title_html: string
+  snippet_html: string
+}
+
+export interface SearchResults<T> {
+  hits: SearchHit<T>[]
+  total: number
+}
+
+// Full-text search in the local database; "quoted words" match as a phrase, word* as a prefix
+export async function searchMemos<T>(text: string, limit = 200, offset = 0): Promise<SearchResults<T>> {
+  return invoke<SearchResults<T>>("search_memos_command", { query: { text, limit, offset } })
+}
+
+export interface TagCount {
+  tag: string
+  count: number
+}
+
+// Tag operations run on the whole library and return how many memos changed
+export async function listTags(): Promise<TagCount[]> {
+  return invoke<TagCount[]>("list_tags_command")
+}
+
+export async function renameTag(from: string, to: string): Promise<number> {
+  return invoke<number>("rename_tag_command", { from, to })
+}
+
+export async function mergeTags(sources: string[], target: string): Promise<number> {
+  return invoke<number>("merge_tags_command", { sources, target })
+}
+
+export async function deleteTag(tag: string): Promise<number> {
+  return invoke<number>("delete_tag_command", { tag })
+}
+
+export async function normalizeTags(): Promise<number> {
+  return invoke<number>("normalize_tags_command")
+}
+
+export interface TrashedMemo<T> {
+  memo: T
+  deleted_at: string
+  purge_at: string | null
+}
+
+// Deleted memos stay in the trash until it is emptied or they pass the retention period
+export async function listTrash<T>(): Promise<TrashedMemo<T>[]> {
+  return invoke<TrashedMemo<T>[]>("list_trash_command")
+}
+
+export async function restoreMemo(id: string): Promise<void> {
+  return invoke<void>("restore_memo_command", { id })
+}
+
+export async function emptyTrash(): Promise<number> {
+  return invoke<number>("empty_trash_command")
+}
+
+export type MemoField = "title" | "transcript" | "translate" | "summary" | "tags"
+
+export interface Revision {
+  id: number
+  memo_id: string
+  created_at: string
+  source: "original" | "created" | "edit" | "sync" | "revert" | "import"
+  changed_fields: MemoField[]
+  title: string
+  transcript: string | null
+  translate: string | null
+  summary: string | null
+  tags: string[]
+}
+
+export interface RevisionDiff {
+  from: number
+  to: number
+  fields: { field: MemoField; changes: { kind: "equal" | "insert" | "delete"; text: string }[] }[]
+}
+
+// Every save records a revision; reverting adds a new one rather than dropping later ones
+export async function listRevisions(id: string): Promise<Revision[]> {
+  return invoke<Revision[]>("list_revisions_command", { id })
+}
+
+export async function diffRevisions(id: string, from: number, to: number): Promise<RevisionDiff> {
+  return invoke<RevisionDiff>("diff_revisions_command", { id, from, to })
+}
+
+export async function revertMemo(id: string, revision: number): Promise<Revision> {
+  return invoke<Revision>("revert_memo_command", { id, revision })
+}
+
+export interface VaultStatus {
+  encrypted: boolean
+  locked: boolean
+}
+
+// Passphrase encryption of the local library; a "vault:status" event follows every change
+export async function getVaultStatus(): Promise<VaultStatus> {
+  return invoke<VaultStatus>("vault_status_command")
+}
+
+export async function setPassphrase(passphrase: string): Promise<void> {
+  return invoke<void>("set_passphrase_command", { passphrase })
+}
+
+export async function changePassphrase(currentPassphrase: string, newPassphrase: string): Promise<void> {
+  return invoke<void>("change_passphrase_command", { currentPassphrase, newPassphrase })
+}
+
+export async function removePassphrase(passphrase: string): Promise<void> {
+  return invoke<void>("remove_passphrase_command", { passphrase })
+}
+
+export async function lockLibrary(): Promise<void> {
+  return invoke<void>("lock_library_command")
+}
+
+export async function unlockLibrary(passphrase: string): Promise<void> {
+  return invoke<void>("unlock_library_command", { passphrase })
+}
+
+export interface ExportReport {
+  memos: number
+  recordings: number
+  bytes: number
+}
+
+export interface ImportOptions {
+  mode?: "merge" | "replace"
+  restore_settings?: boolean
+}
+
+export interface ImportReport {
+  imported: number
+  replaced: number
+  duplicates: { id: string; title: string; reason: "id" | "remote_id" | "audio" }[]
+  settings_restored: boolean
+}
+
+// Backup archives (.zip) of the local library; "backup:progress" events report { operation, done, total }
+export async function exportLibrary(path: string): Promise<ExportReport> {
+  return invoke<ExportReport>("export_library_command", { path })
+}
+
+export async function importLibrary(path: string, options?: ImportOptions): Promise<ImportReport> {
+  return invoke<ImportReport>("import_library_command", { path, options })
+}
+
+export interface MemoUsage {
+  id: string
+  title: string
+  created_at: string
+  audio_bytes: number
+  transcript_bytes: number
+  in_trash: boolean
+}
+
+export interface QuotaStatus {
+  used_bytes: number
+  soft_limit_bytes: number | null
+  hard_limit_bytes: number | null
+  state: "ok" | "warning" | "full"
+}
+
+export interface StorageStats {
+  memo_count: number
+  audio_bytes: number
+  stored_audio_bytes: number
+  transcript_bytes: number
+  trash_bytes: number
+  synced_audio_bytes: number
+  database_bytes: number
+  cache_bytes: number
+  pending_upload_bytes: number
+  memos: MemoUsage[]
+  largest: MemoUsage[]
+  oldest: MemoUsage[]
+  quota: QuotaStatus
+}
+
+// Recording fails with kind "quota_exceeded" at the hard limit; "storage:quota" events carry a QuotaStatus
+export async function getStorageStats(): Promise<StorageStats> {
+  return invoke<StorageStats>("storage_stats_command")
+}
+
+export type IntegrityIssueKind =
+  | "missing_recording"
+  | "checksum_mismatch"
+  | "undecodable"
+  | "implausible_duration"
+  | "wrong_metadata"
+  | "inline_recording"
+  | "dangling_reference"
+  | "refcount_drift"
+  | "orphan_recording"
+  | "orphan_file"
+  | "orphan_rows"
+
+export interface IntegrityReport {
+  memos_checked: number
+  recordings_checked: number
+  issues: { kind: IntegrityIssueKind; memo_id: string | null; hash: string | null; detail: string; repaired: boolean }[]
+}
+
+// verifyLibrary only reports; repairLibrary fixes what it can and marks those issues as repaired
+export async function verifyLibrary(): Promise<IntegrityReport> {
+  return invoke<IntegrityReport>("verify_library_command")
+}
+
+export async function repairLibrary(): Promise<IntegrityReport> {
+  return invoke<IntegrityReport>("repair_library_command")
+}
@@ -1,3 +1,5 @@
 fn main() {
+  // Migrations are embedded with `sqlx::migrate!`; rebuild when they change.
+  println!("cargo:rerun-if-changed=migrations");
   tauri_build::build()
 }
@@ -0,0 +1,33 @@
+-- Local copy of the backend's `users` / `voice_memos1` tables, adapted for SQLite.
+-- Passwords stay on the server; tags move from a comma separated column to
+-- their own table so they can be filtered on.
+
+CREATE TABLE users (
+    user_id TEXT PRIMARY KEY,
+    username TEXT NOT NULL,
+    email TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE voice_memos (
+    id TEXT PRIMARY KEY,
+    user_id TEXT REFERENCES users(user_id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    date TEXT NOT NULL,
+    duration TEXT NOT NULL,
+    audio_blob BLOB NOT NULL,
+    transcription TEXT,
+    translate TEXT,
+    summary TEXT
+);
+
+CREATE INDEX voice_memos_date ON voice_memos(date);
+
+CREATE TABLE memo_tags (
+    memo_id TEXT NOT NULL REFERENCES voice_memos(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (memo_id, position)
+);
+
+CREATE INDEX memo_tags_tag ON memo_tags(tag);
@@ -0,0 +1,7 @@
+-- Recording and edit metadata kept alongside each memo.
+
+ALTER TABLE voice_memos ADD COLUMN audio_mime TEXT NOT NULL DEFAULT 'application/octet-stream';
+ALTER TABLE voice_memos ADD COLUMN audio_size INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE voice_memos ADD COLUMN updated_at TEXT;
+
+UPDATE voice_memos SET audio_size = length(audio_blob), updated_at = date;
@@ -0,0 +1,17 @@
+-- Bookkeeping for syncing the local database with the backend.
+-- `version` counts local edits, `dirty` marks edits not pushed yet and
+-- `remote_hash` fingerprints the backend copy as of the last sync.
+
+ALTER TABLE voice_memos ADD COLUMN remote_id TEXT;
+ALTER TABLE voice_memos ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE voice_memos ADD COLUMN dirty INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE voice_memos ADD COLUMN remote_hash TEXT;
+ALTER TABLE voice_memos ADD COLUMN synced_at TEXT;
+
+CREATE UNIQUE INDEX voice_memos_remote_id ON voice_memos(remote_id);
+
+-- Backend memos deleted here whose deletion has not been pushed yet.
+CREATE TABLE sync_tombstones (
+    remote_id TEXT PRIMARY KEY,
+    deleted_at TEXT NOT NULL
+);
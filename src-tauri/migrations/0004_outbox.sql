@@ -0,0 +1,17 @@
+-- Memo writes that could not reach the backend, replayed in `id` order.
+-- `payload` holds the operation's JSON arguments; recordings go in `audio`.
+-- `failed` marks operations the backend rejected, which wait for the user.
+
+CREATE TABLE outbox (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    operation TEXT NOT NULL,
+    memo_id TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    audio BLOB,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    failed INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX outbox_memo_id ON outbox(memo_id);
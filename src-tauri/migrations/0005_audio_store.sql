@@ -0,0 +1,35 @@
+-- Recordings move out of `voice_memos.audio_blob` into files named by their
+-- SHA-256 (see `audio_store.rs`). `audio_blob` can't be dropped while it is
+-- NOT NULL, so it is left empty once a memo points at a file.
+-- `refcount` is maintained by the triggers below; files whose count drops to
+-- zero are removed by the garbage collector.
+
+CREATE TABLE audio_blobs (
+    hash TEXT PRIMARY KEY,
+    size INTEGER NOT NULL,
+    refcount INTEGER NOT NULL DEFAULT 0,
+    touched_at TEXT NOT NULL
+);
+
+ALTER TABLE voice_memos ADD COLUMN audio_hash TEXT REFERENCES audio_blobs(hash);
+
+CREATE INDEX voice_memos_audio_hash ON voice_memos(audio_hash);
+
+CREATE TRIGGER audio_blobs_ref_insert AFTER INSERT ON voice_memos
+WHEN NEW.audio_hash IS NOT NULL
+BEGIN
+    UPDATE audio_blobs SET refcount = refcount + 1 WHERE hash = NEW.audio_hash;
+END;
+
+CREATE TRIGGER audio_blobs_ref_delete AFTER DELETE ON voice_memos
+WHEN OLD.audio_hash IS NOT NULL
+BEGIN
+    UPDATE audio_blobs SET refcount = refcount - 1 WHERE hash = OLD.audio_hash;
+END;
+
+CREATE TRIGGER audio_blobs_ref_update AFTER UPDATE OF audio_hash ON voice_memos
+WHEN OLD.audio_hash IS NOT NEW.audio_hash
+BEGIN
+    UPDATE audio_blobs SET refcount = refcount - 1 WHERE hash = OLD.audio_hash;
+    UPDATE audio_blobs SET refcount = refcount + 1 WHERE hash = NEW.audio_hash;
+END;
@@ -0,0 +1,33 @@
+-- Full-text index over the searchable memo fields, kept in step with
+-- `voice_memos` by triggers so every save, sync or delete updates it.
+-- `memo_id` is stored rather than relying on rowids, which VACUUM may renumber.
+
+CREATE VIRTUAL TABLE memo_search USING fts5(
+    memo_id UNINDEXED,
+    title,
+    transcript,
+    summary,
+    translate,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO memo_search (memo_id, title, transcript, summary, translate)
+SELECT id, name, transcription, summary, translate FROM voice_memos;
+
+CREATE TRIGGER memo_search_insert AFTER INSERT ON voice_memos
+BEGIN
+    INSERT INTO memo_search (memo_id, title, transcript, summary, translate)
+    VALUES (NEW.id, NEW.name, NEW.transcription, NEW.summary, NEW.translate);
+END;
+
+CREATE TRIGGER memo_search_update AFTER UPDATE OF name, transcription, summary, translate ON voice_memos
+BEGIN
+    DELETE FROM memo_search WHERE memo_id = OLD.id;
+    INSERT INTO memo_search (memo_id, title, transcript, summary, translate)
+    VALUES (NEW.id, NEW.name, NEW.transcription, NEW.summary, NEW.translate);
+END;
+
+CREATE TRIGGER memo_search_delete AFTER DELETE ON voice_memos
+BEGIN
+    DELETE FROM memo_search WHERE memo_id = OLD.id;
+END;
@@ -0,0 +1,6 @@
+-- Deleted memos go to the trash first: `deleted_at` is set and the row is
+-- hidden from listings until it is restored or purged.
+
+ALTER TABLE voice_memos ADD COLUMN deleted_at TEXT;
+
+CREATE INDEX voice_memos_deleted_at ON voice_memos(deleted_at);
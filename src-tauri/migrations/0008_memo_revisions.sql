@@ -0,0 +1,17 @@
+-- A snapshot of a memo's editable fields after every save, so earlier text
+-- can be compared and restored. `changed_fields` and `tags` are JSON arrays.
+
+CREATE TABLE memo_revisions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    memo_id TEXT NOT NULL REFERENCES voice_memos(id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL,
+    source TEXT NOT NULL,
+    changed_fields TEXT NOT NULL,
+    title TEXT NOT NULL,
+    transcript TEXT,
+    translate TEXT,
+    summary TEXT,
+    tags TEXT NOT NULL
+);
+
+CREATE INDEX memo_revisions_memo_id ON memo_revisions(memo_id, id);
@@ -0,0 +1,27 @@
+-- Durations are saved as "MM:SS" or "H:MM:SS" text, which does not sort by
+-- length ("1:05:00" < "59:00"). Listings sort on this parsed copy instead.
+-- It accepts exactly what `memo_ops::duration_secs` does, one to three
+-- colon-separated runs of digits, at most 12 characters so the result
+-- cannot overflow, and is 0 for anything else.
+
+ALTER TABLE voice_memos ADD COLUMN duration_secs INTEGER GENERATED ALWAYS AS (
+    CASE
+        WHEN duration = '' OR length(duration) > 12
+            OR duration GLOB '*[^0-9:]*'
+            OR duration LIKE ':%'
+            OR duration LIKE '%:'
+            OR instr(duration, '::') > 0
+            OR length(duration) - length(replace(duration, ':', '')) > 2
+        THEN 0
+        WHEN instr(duration, ':') = 0 THEN CAST(duration AS INTEGER)
+        WHEN instr(substr(duration, instr(duration, ':') + 1), ':') = 0 THEN
+            CAST(substr(duration, 1, instr(duration, ':') - 1) AS INTEGER) * 60
+            + CAST(substr(duration, instr(duration, ':') + 1) AS INTEGER)
+        ELSE
+            CAST(substr(duration, 1, instr(duration, ':') - 1) AS INTEGER) * 3600
+            + CAST(substr(substr(duration, instr(duration, ':') + 1), 1,
+                instr(substr(duration, instr(duration, ':') + 1), ':') - 1) AS INTEGER) * 60
+            + CAST(substr(substr(duration, instr(duration, ':') + 1),
+                instr(substr(duration, instr(duration, ':') + 1), ':') + 1) AS INTEGER)
+    END
+) VIRTUAL;
@@ -0,0 +1,322 @@
+use crate::config::CONFIG_FILE_NAME;
+use crate::db::{DATABASE_FILE, ENCRYPTED_DATABASE_FILE};
+use crate::error::{AppError, AppResult};
+use crate::idempotency::PENDING_FILE_NAME;
+use crate::local_store::timestamp;
+use crate::session::{SessionInfo, SessionState, PROFILE_ID_FILE, SESSION_FILE, SESSION_KEY_FILE};
+use crate::vault::VAULT_FILE;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Registry of the accounts used on this device, in the app data directory.
+const ACCOUNTS_FILE: &str = "accounts.json";
+/// Directory, in the data, cache and config directories, holding one
+/// subdirectory per account.
+const PROFILES_DIR: &str = "accounts";
+/// Profile in use while no account has logged in yet. It stays empty: every
+/// command that stores memos needs a session.
+const SIGNED_OUT_PROFILE: &str = "signed-out";
+/// Data from before accounts were kept apart, handed to the first account
+/// that logs in.
+const LEGACY_PROFILE: &str = "legacy";
+/// What the cache directory held before accounts.
+const LEGACY_CACHE_ENTRIES: &[&str] = &["audio"];
+
+/// What the data directory held before accounts. Anything else there, such
+/// as the webview's own data, is not ours to move.
+fn legacy_data_entries() -> Vec<String> {
+    let mut names: Vec<String> = [
+        VAULT_FILE,
+        "audio",
+        "uploads",
+        PENDING_FILE_NAME,
+        SESSION_FILE,
+        SESSION_KEY_FILE,
+        PROFILE_ID_FILE,
+        CONFIG_FILE_NAME,
+    ]
+    .iter()
+    .map(|name| name.to_string())
+    .collect();
+    for db in [DATABASE_FILE, ENCRYPTED_DATABASE_FILE] {
+        for suffix in ["", "-wal", "-shm", "-journal"] {
+            names.push(format!("{}{}", db, suffix));
+        }
+    }
+    names
+}
+
+/// An account that has logged in on this device.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Account {
+    /// Names the account's profile directories; derived from the backend
+    /// user id, or the email for tokens without one.
+    pub id: String,
+    pub email: String,
+    pub username: Option<String>,
+    pub user_id: Option<String>,
+    pub added_at: String,
+    pub last_used_at: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct AccountSummary {
+    #[serde(flatten)]
+    pub account: Account,
+    /// The account whose library is open.
+    pub active: bool,
+    /// Whether a session is stored, so switching does not ask for the password.
+    pub signed_in: bool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct AccountsFile {
+    active: Option<String>,
+    accounts: Vec<Account>,
+    /// The account that took over the data from before accounts.
+    #[serde(default)]
+    legacy_owner: Option<String>,
+}
+
+/// Where one account keeps its library, caches and settings.
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub data_dir: PathBuf,
+    pub cache_dir: PathBuf,
+    /// Per-account settings directory; `None` when the platform has no
+    /// config directory.
+    pub config_dir: Option<PathBuf>,
+    shared_config_dir: Option<PathBuf>,
+}
+
+impl Profile {
+    /// Settings are read from the account's own `config.json`, falling back
+    /// to the one shared by all accounts.
+    pub fn settings_dir(&self) -> Option<&Path> {
+        match &self.config_dir {
+            Some(dir) if dir.join(CONFIG_FILE_NAME).exists() => Some(dir),
+            _ => self.shared_config_dir.as_deref(),
+        }
+    }
+
+    /// The `config.json` settings are currently read from.
+    pub fn settings_file(&self) -> AppResult<PathBuf> {
+        self.settings_dir()
+            .map(|dir| dir.join(CONFIG_FILE_NAME))
+            .ok_or_else(|| AppError::internal("No config directory"))
+    }
+
+    /// The account's own `config.json`, where restored settings go.
+    pub fn own_settings_file(&self) -> AppResult<PathBuf> {
+        self.config_dir
+            .as_ref()
+            .map(|dir| dir.join(CONFIG_FILE_NAME))
+            .ok_or_else(|| AppError::internal("No config directory"))
+    }
+}
+
+/// The accounts known on this device and which one is open. Each account
+/// has its own profile directories, chosen when the app starts; switching
+/// accounts restarts the app into the other profile, so no open database,
+/// store or cache is ever shared between accounts.
+pub struct AccountRegistry {
+    data_root: PathBuf,
+    cache_root: PathBuf,
+    config_root: Option<PathBuf>,
+    file: Mutex<AccountsFile>,
+}
+
+impl AccountRegistry {
+    /// Reads the registry, first moving data from before accounts into a
+    /// profile of its own. Entries that cannot be moved are left where they
+    /// are and tried again on the next start.
+    pub fn load(data_root: PathBuf, cache_root: PathBuf, config_root: Option<PathBuf>) -> AppResult<Self> {
+        std::fs::create_dir_all(&data_root)?;
+        let path = data_root.join(ACCOUNTS_FILE);
+        let registry = Self {
+            file: Mutex::new(AccountsFile::default()),
+            data_root,
+            cache_root,
+            config_root,
+        };
+        let first_start = match std::fs::read(&path) {
+            Ok(raw) => {
+                *registry.file.lock().unwrap() = serde_json::from_slice(&raw)?;
+                false
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => true,
+            Err(e) => return Err(e.into()),
+        };
+        registry.move_legacy();
+        if first_start {
+            registry.adopt_legacy()?;
+        }
+        Ok(registry)
+    }
+
+    /// Moves what is left of the data from before accounts into the legacy
+    /// profile, or into the profile of the account that took it over.
+    fn move_legacy(&self) {
+        let owner = self.file.lock().unwrap().legacy_owner.clone();
+        let legacy = self.profile(Some(owner.as_deref().unwrap_or(LEGACY_PROFILE)));
+        let moved = move_entries(&self.data_root, &legacy.data_dir, &legacy_data_entries());
+        let names: Vec<String> = LEGACY_CACHE_ENTRIES.iter().map(|name| name.to_string()).collect();
+        move_entries(&self.cache_root, &legacy.cache_dir, &names);
+        if moved > 0 {
+            println!("👥 Moved {} existing library entries into their own profile", moved);
+        }
+    }
+
+    /// Creates the registry on first start. If the legacy profile holds a
+    /// session, that account gets it right away.
+    fn adopt_legacy(&self) -> AppResult<()> {
+        let legacy = self.profile(Some(LEGACY_PROFILE));
+        if SessionState::is_stored(&legacy.data_dir) {
+            match SessionState::load(legacy.data_dir.clone()) {
+                Ok(session) => {
+                    if let Some(info) = session.current() {
+                        let id = self.remember(&info)?;
+                        self.set_active(&id)?;
+                        return Ok(());
+                    }
+                }
+                Err(e) => println!("⚠️ Could not read the existing session: {}", e.message),
+            }
+        }
+        self.save(&self.file.lock().unwrap())
+    }
+
+    fn save(&self, file: &AccountsFile) -> AppResult<()> {
+        let path = self.data_root.join(ACCOUNTS_FILE);
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(file)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// Profile id of the account a session belongs to.
+    pub fn account_id(info: &SessionInfo) -> String {
+        let key = match &info.user_id {
+            Some(user_id) => format!("user:{}", user_id),
+            None => format!("email:{}", info.email.to_lowercase()),
+        };
+        format!("{:x}", Sha256::digest(key.as_bytes()))[..16].to_string()
+    }
+
+    /// Directories of the account `id`, or of the signed-out profile.
+    pub fn profile(&self, id: Option<&str>) -> Profile {
+        let name = id.unwrap_or(SIGNED_OUT_PROFILE);
+        Profile {
+            data_dir: self.data_root.join(PROFILES_DIR).join(name),
+            cache_dir: self.cache_root.join(PROFILES_DIR).join(name),
+            config_dir: self.config_root.as_ref().map(|dir| dir.join(PROFILES_DIR).join(name)),
+            shared_config_dir: self.config_root.clone(),
+        }
+    }
+
+    /// Profile of the account that was open when the app started.
+    pub fn active_profile(&self) -> Profile {
+        self.profile(self.active_id().as_deref())
+    }
+
+    pub fn active_id(&self) -> Option<String> {
+        self.file.lock().unwrap().active.clone()
+    }
+
+    /// Accounts that have logged in on this device.
+    pub fn list(&self) -> Vec<AccountSummary> {
+        let file = self.file.lock().unwrap();
+        let mut accounts: Vec<AccountSummary> = file
+            .accounts
+            .iter()
+            .map(|account| AccountSummary {
+                active: file.active.as_ref() == Some(&account.id),
+                signed_in: SessionState::is_stored(&self.profile(Some(&account.id)).data_dir),
+                account: account.clone(),
+            })
+            .collect();
+        // The open account first, then the most recently used.
+        accounts.sort_by(|a, b| {
+            (b.active, &b.account.last_used_at).cmp(&(a.active, &a.account.last_used_at))
+        });
+        accounts
+    }
+
+    /// Records a login and returns the account's id. The first account to
+    /// log in on this device takes over the data from before accounts.
+    pub fn remember(&self, info: &SessionInfo) -> AppResult<String> {
+        let id = Self::account_id(info);
+        let now = timestamp(Utc::now());
+        let mut file = self.file.lock().unwrap();
+        if file.accounts.is_empty() {
+            self.claim_legacy(&id)?;
+            file.legacy_owner = Some(id.clone());
+        }
+        match file.accounts.iter_mut().find(|a| a.id == id) {
+            Some(account) => {
+                account.email = info.email.clone();
+                account.username = info.username.clone();
+                account.last_used_at = now;
+            }
+            None => {
+                println!("👥 Added account {}", info.email);
+                file.accounts.push(Account {
+                    id: id.clone(),
+                    email: info.email.clone(),
+                    username: info.username.clone(),
+                    user_id: info.user_id.clone(),
+                    added_at: now.clone(),
+                    last_used_at: now,
+                });
+            }
+        }
+        self.save(&file)?;
+        Ok(id)
+    }
+
+    fn claim_legacy(&self, id: &str) -> AppResult<()> {
+        let legacy = self.profile(Some(LEGACY_PROFILE));
+        let target = self.profile(Some(id));
+        for (from, to) in [(&legacy.data_dir, &target.data_dir), (&legacy.cache_dir, &target.cache_dir)] {
+            if from.exists() && !to.exists() {
+                std::fs::create_dir_all(to.parent().unwrap_or(to))?;
+                std::fs::rename(from, to)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes `id` the account opened on the next start.
+    pub fn set_active(&self, id: &str) -> AppResult<()> {
+        let mut file = self.file.lock().unwrap();
+        if !file.accounts.iter().any(|a| a.id == id) {
+            return Err(AppError::not_found(format!("No account {}", id)));
+        }
+        file.active = Some(id.to_string());
+        self.save(&file)
+    }
+}
+
+/// Moves the entries `names` of `from` into `to`, logging the ones that
+/// cannot be moved. Returns how many were moved.
+fn move_entries(from: &Path, to: &Path, names: &[String]) -> usize {
+    let mut moved = 0;
+    for name in names {
+        let (source, target) = (from.join(name), to.join(name));
+        if std::fs::symlink_metadata(&source).is_err() {
+            continue;
+        }
+        if std::fs::symlink_metadata(&target).is_ok() {
+            println!("⚠️ Not moving {}: the profile already has one", source.display());
+            continue;
+        }
+        match std::fs::create_dir_all(to).and_then(|_| std::fs::rename(&source, &target)) {
+            Ok(()) => moved += 1,
+            Err(e) => println!("⚠️ Could not move {}, trying again on the next start: {}", source.display(), e),
+        }
+    }
+    moved
+}
@@ -0,0 +1,363 @@
+use crate::config::ApiConfig;
+use crate::error::{AppError, AppResult};
+use crate::retry::{is_retryable_status, RetryPolicy, WakingStatus};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde::Serialize;
+use std::error::Error;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+type WakingListener = Box<dyn Fn(WakingStatus) + Send + Sync>;
+type UnauthorizedListener = Box<dyn Fn(&str) + Send + Sync>;
+
+/// Shared HTTP client for the SmartMemo backend, held in Tauri managed state.
+///
+/// All API modules build their requests through this so the base URL,
+/// timeouts, user agent, default headers and retry policy are configured in
+/// one place.
+pub struct ApiClient {
+    http: Client,
+    base_url: String,
+    retry: RetryPolicy,
+    cold_start_idle: Duration,
+    cold_start_timeout: Duration,
+    waking_notice: Duration,
+    last_response: Mutex<Option<Instant>>,
+    waking_listener: Option<WakingListener>,
+    unauthorized_listener: Option<UnauthorizedListener>,
+}
+
+impl ApiClient {
+    pub fn new(config: &ApiConfig) -> Result<Self, Box<dyn Error>> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &config.default_headers {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+
+        let http = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+            .user_agent(config.user_agent.clone())
+            .default_headers(headers)
+            .build()?;
+
+        Ok(Self {
+            http,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            retry: config.retry.clone(),
+            cold_start_idle: Duration::from_secs(config.cold_start_idle_secs),
+            cold_start_timeout: Duration::from_secs(config.cold_start_timeout_secs),
+            waking_notice: Duration::from_millis(config.waking_notice_ms),
+            last_response: Mutex::new(None),
+            waking_listener: None,
+            unauthorized_listener: None,
+        })
+    }
+
+    /// Registers the callback used to report `backend:waking` transitions.
+    pub fn set_waking_listener<F>(&mut self, listener: F)
+    where
+        F: Fn(WakingStatus) + Send + Sync + 'static,
+    {
+        self.waking_listener = Some(Box::new(listener));
+    }
+
+    /// Registers the callback told about tokens the backend rejected with
+    /// 401, whichever module sent the request.
+    pub fn set_unauthorized_listener<F>(&mut self, listener: F)
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        self.unauthorized_listener = Some(Box::new(listener));
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Joins an endpoint path such as `/get_memos` onto the base URL.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    pub fn get(&self, path: &str) -> ApiRequest<'_> {
+        self.request(Method::GET, path)
+    }
+
+    pub fn post(&self, path: &str) -> ApiRequest<'_> {
+        self.request(Method::POST, path)
+    }
+
+    pub fn put(&self, path: &str) -> ApiRequest<'_> {
+        self.request(Method::PUT, path)
+    }
+
+    pub fn patch(&self, path: &str) -> ApiRequest<'_> {
+        self.request(Method::PATCH, path)
+    }
+
+    pub fn delete(&self, path: &str) -> ApiRequest<'_> {
+        self.request(Method::DELETE, path)
+    }
+
+    fn request(&self, method: Method, path: &str) -> ApiRequest<'_> {
+        // Safe methods can always be repeated; POST/PATCH must opt in.
+        let idempotent = matches!(method, Method::GET | Method::HEAD | Method::PUT | Method::DELETE);
+        ApiRequest {
+            client: self,
+            builder: self.http.request(method, self.url(path)),
+            idempotent,
+            bearer: None,
+        }
+    }
+
+    /// The backend counts as cold when we have not heard from it recently.
+    fn is_cold(&self) -> bool {
+        match *self.last_response.lock().unwrap() {
+            Some(at) => at.elapsed() >= self.cold_start_idle,
+            None => true,
+        }
+    }
+
+    fn mark_alive(&self) {
+        *self.last_response.lock().unwrap() = Some(Instant::now());
+    }
+
+    fn notify_waking(&self, status: WakingStatus) {
+        if let Some(listener) = &self.waking_listener {
+            listener(status);
+        }
+    }
+
+    /// Clears the warm-up state in the UI once a request has finished.
+    fn settle_waking(&self, waking: bool, attempt: u32, max_attempts: u32) {
+        if waking {
+            self.notify_waking(WakingStatus {
+                waking: false,
+                attempt,
+                max_attempts,
+                retry_in_ms: None,
+            });
+        }
+    }
+
+    async fn execute(&self, builder: RequestBuilder, idempotent: bool, bearer: Option<&str>) -> AppResult<Response> {
+        // Bodies that cannot be cloned (streams) only get one shot.
+        let max_attempts = if idempotent && builder.try_clone().is_some() {
+            self.retry.max_attempts.max(1)
+        } else {
+            1
+        };
+        let mut pending = Some(builder);
+        let mut waking = false;
+
+        for attempt in 1..=max_attempts {
+            let request = if attempt == max_attempts {
+                pending.take().expect("request consumed before last attempt")
+            } else {
+                pending
+                    .as_ref()
+                    .and_then(|b| b.try_clone())
+                    .expect("retryable request must be cloneable")
+            };
+
+            let cold = self.is_cold();
+            let request = if cold {
+                request.timeout(self.cold_start_timeout)
+            } else {
+                request
+            };
+
+            let send = request.send();
+            tokio::pin!(send);
+            let result = if cold && !waking {
+                // Tell the UI early if a cold backend is slow to answer.
+                tokio::select! {
+                    result = &mut send => result,
+                    _ = tokio::time::sleep(self.waking_notice) => {
+                        waking = true;
+                        self.notify_waking(WakingStatus {
+                            waking: true,
+                            attempt,
+                            max_attempts,
+                            retry_in_ms: None,
+                        });
+                        send.await
+                    }
+                }
+            } else {
+                send.await
+            };
+
+            let delay = match result {
+                Ok(res) if is_retryable_status(res.status().as_u16()) && attempt < max_attempts => {
+                    println!("⏳ Backend answered {} (attempt {}/{})", res.status(), attempt, max_attempts);
+                    self.retry.delay_for(attempt, Some(&res))
+                }
+                Ok(res) => {
+                    self.mark_alive();
+                    self.settle_waking(waking, attempt, max_attempts);
+                    if let (StatusCode::UNAUTHORIZED, Some(token), Some(listener)) =
+                        (res.status(), bearer, &self.unauthorized_listener)
+                    {
+                        listener(token);
+                    }
+                    return Ok(res);
+                }
+                Err(e) => {
+                    let error = AppError::from(e);
+                    if !error.retryable || attempt == max_attempts {
+                        self.settle_waking(waking, attempt, max_attempts);
+                        return Err(error);
+                    }
+                    println!("⏳ Request failed (attempt {}/{}): {}", attempt, max_attempts, error);
+                    self.retry.delay_for(attempt, None)
+                }
+            };
+
+            waking = true;
+            self.notify_waking(WakingStatus {
+                waking: true,
+                attempt,
+                max_attempts,
+                retry_in_ms: Some(delay.as_millis() as u64),
+            });
+            tokio::time::sleep(delay).await;
+        }
+
+        unreachable!("the last attempt always returns")
+    }
+}
+
+/// A request bound to the [`ApiClient`] that sent it, so sending goes
+/// through the shared retry and cold-start handling.
+pub struct ApiRequest<'a> {
+    client: &'a ApiClient,
+    builder: RequestBuilder,
+    idempotent: bool,
+    /// Token from the `Authorization` header, reported if the backend
+    /// rejects it.
+    bearer: Option<String>,
+}
+
+impl ApiRequest<'_> {
+    pub fn header(mut self, name: &str, value: impl AsRef<str>) -> Self {
+        if name.eq_ignore_ascii_case("authorization") {
+            self.bearer = value.as_ref().strip_prefix("Bearer ").map(str::to_string);
+        }
+        self.builder = self.builder.header(name, value.as_ref());
+        self
+    }
+
+    pub fn json<T: Serialize + ?Sized>(mut self, body: &T) -> Self {
+        self.builder = self.builder.json(body);
+        self
+    }
+
+    pub fn query<T: Serialize + ?Sized>(mut self, query: &T) -> Self {
+        self.builder = self.builder.query(query);
+        self
+    }
+
+    /// Sends raw bytes as the request body.
+    pub fn body(mut self, bytes: Vec<u8>) -> Self {
+        self.builder = self.builder.body(bytes);
+        self
+    }
+
+    pub fn multipart(mut self, form: MultipartBody) -> Self {
+        let content_type = form.content_type();
+        self.builder = self
+            .builder
+            .header("Content-Type", content_type)
+            .body(form.into_body());
+        self
+    }
+
+    /// Attaches an `Idempotency-Key` so the backend de-duplicates repeats,
+    /// which also makes the request safe to retry.
+    pub fn idempotency_key(mut self, key: &str) -> Self {
+        self.builder = self.builder.header("Idempotency-Key", key);
+        self.idempotent = true;
+        self
+    }
+
+    /// Marks a POST/PATCH as safe to repeat, e.g. pure AI computations or
+    /// full-state updates.
+    pub fn idempotent(mut self) -> Self {
+        self.idempotent = true;
+        self
+    }
+
+    pub async fn send(self) -> AppResult<Response> {
+        self.client
+            .execute(self.builder, self.idempotent, self.bearer.as_deref())
+            .await
+    }
+}
+
+/// A `multipart/form-data` body assembled in memory.
+///
+/// Unlike `reqwest::multipart::Form` the finished body can be cloned, so
+/// uploads built with it still go through the retry layer.
+pub struct MultipartBody {
+    boundary: String,
+    body: Vec<u8>,
+}
+
+impl MultipartBody {
+    pub fn new() -> Self {
+        Self {
+            boundary: format!("smartmemo-{}", Uuid::new_v4().simple()),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Adds a part holding a JSON document.
+    pub fn json<T: Serialize + ?Sized>(self, name: &str, value: &T) -> AppResult<Self> {
+        let bytes = serde_json::to_vec(value)?;
+        Ok(self.part(name, None, "application/json", &bytes))
+    }
+
+    /// Adds a file part.
+    pub fn file(self, name: &str, file_name: &str, mime: &str, bytes: &[u8]) -> Self {
+        self.part(name, Some(file_name), mime, bytes)
+    }
+
+    fn part(mut self, name: &str, file_name: Option<&str>, mime: &str, bytes: &[u8]) -> Self {
+        let disposition = match file_name {
+            Some(file_name) => format!("form-data; name=\"{}\"; filename=\"{}\"", name, file_name),
+            None => format!("form-data; name=\"{}\"", name),
+        };
+        self.body.extend_from_slice(format!("--{}\r\n", self.boundary).as_bytes());
+        self.body
+            .extend_from_slice(format!("Content-Disposition: {}\r\n", disposition).as_bytes());
+        self.body
+            .extend_from_slice(format!("Content-Type: {}\r\n\r\n", mime).as_bytes());
+        self.body.extend_from_slice(bytes);
+        self.body.extend_from_slice(b"\r\n");
+        self
+    }
+
+    /// Appends the closing boundary and returns the encoded body.
+    fn into_body(mut self) -> Vec<u8> {
+        self.body
+            .extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+        self.body
+    }
+}
+
+impl Default for MultipartBody {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,9 +1,7 @@
-use reqwest::Client;
+use crate::api_client::ApiClient;
+use crate::error::{AppError, AppResult};
 use serde::Deserialize;
 use serde_json::json;
-use std::error::Error;
-
-const API_BASE_URL: &str = "https://smartmemo-backend-rust.onrender.com/api";
 
 #[derive(Deserialize)]
 struct ApiKeyResponse {
@@ -19,32 +17,30 @@ struct HelperStatusResponse {
 }
 
 /// Saves the Gemini API key by sending it to the backend server.
-pub async fn save_api_key(token: &str, gemini_key: &str) -> Result<(), Box<dyn Error>> {
-    let client = Client::new();
+pub async fn save_api_key(api: &ApiClient, token: &str, gemini_key: &str) -> AppResult<()> {
     let payload = json!({
         "gemini_api_key": gemini_key
     });
 
-    let res = client
-        .post(&format!("{}/api_keys/save", API_BASE_URL))
+    let res = api
+        .post("/api_keys/save")
         .header("Authorization", format!("Bearer {}", token))
         .json(&payload)
+        .idempotent()
         .send()
         .await?;
 
     if !res.status().is_success() {
-        let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("API Error: {}", error_body).into());
+        return Err(AppError::from_response(res).await);
     }
 
     Ok(())
 }
 
 /// Retrieves the Gemini API key from the backend server.
-pub async fn get_api_key(token: &str) -> Result<Option<String>, Box<dyn Error>> {
-    let client = Client::new();
-    let res = client
-        .get(&format!("{}/api_keys/get", API_BASE_URL))
+pub async fn get_api_key(api: &ApiClient, token: &str) -> AppResult<Option<String>> {
+    let res = api
+        .get("/api_keys/get")
         .header("Authorization", format!("Bearer {}", token))
         .send()
         .await?;
@@ -54,8 +50,7 @@ pub async fn get_api_key(token: &str) -> Result<Option<String>, Box<dyn Error>>
     }
 
     if !res.status().is_success() {
-        let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("API Error: {}", error_body).into());
+        return Err(AppError::from_response(res).await);
     }
 
     let response: ApiKeyResponse = res.json().await?;
@@ -65,64 +60,58 @@ pub async fn get_api_key(token: &str) -> Result<Option<String>, Box<dyn Error>>
 }
 
 /// Deletes the Gemini API key from the backend server.
-pub async fn delete_gemini_api_key(token: &str) -> Result<(), Box<dyn Error>> {
-    let client = Client::new();
-    let res = client
-        .delete(&format!("{}/api_keys/gemini", API_BASE_URL))
+pub async fn delete_gemini_api_key(api: &ApiClient, token: &str) -> AppResult<()> {
+    let res = api
+        .delete("/api_keys/gemini")
         .header("Authorization", format!("Bearer {}", token))
         .send()
         .await?;
 
     if !res.status().is_success() {
-        let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("API Error: {}", error_body).into());
+        return Err(AppError::from_response(res).await);
     }
 
     Ok(())
 }
 
 /// Deletes the ElevenLabs API key from the backend server.
-pub async fn delete_elevenlabs_api_key(token: &str) -> Result<(), Box<dyn Error>> {
-    let client = Client::new();
-    let res = client
-        .delete(&format!("{}/api_keys/elevenlabs", API_BASE_URL))
+pub async fn delete_elevenlabs_api_key(api: &ApiClient, token: &str) -> AppResult<()> {
+    let res = api
+        .delete("/api_keys/elevenlabs")
         .header("Authorization", format!("Bearer {}", token))
         .send()
         .await?;
 
     if !res.status().is_success() {
-        let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("API Error: {}", error_body).into());
+        return Err(AppError::from_response(res).await);
     }
 
     Ok(())
 }
 
 /// Updates the helper application state on the backend server.
-pub async fn update_helper_app_state(token: &str, enabled: bool) -> Result<(), Box<dyn Error>> {
-    let client = Client::new();
+pub async fn update_helper_app_state(api: &ApiClient, token: &str, enabled: bool) -> AppResult<()> {
     let payload = json!({ "status": enabled });
 
-    let res = client
-        .post(&format!("{}/helper/status", API_BASE_URL))
+    let res = api
+        .post("/helper/status")
         .header("Authorization", format!("Bearer {}", token))
         .json(&payload)
+        .idempotent()
         .send()
         .await?;
 
     if !res.status().is_success() {
-        let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("API Error: {}", error_body).into());
+        return Err(AppError::from_response(res).await);
     }
 
     Ok(())
 }
 
 /// Retrieves the helper application state from the backend server.
-pub async fn get_helper_app_state(token: &str) -> Result<bool, Box<dyn Error>> {
-    let client = Client::new();
-    let res = client
-        .get(&format!("{}/helper/status", API_BASE_URL))
+pub async fn get_helper_app_state(api: &ApiClient, token: &str) -> AppResult<bool> {
+    let res = api
+        .get("/helper/status")
         .header("Authorization", format!("Bearer {}", token))
         .send()
         .await?;
@@ -130,8 +119,7 @@ pub async fn get_helper_app_state(token: &str) -> Result<bool, Box<dyn Error>> {
    
 
     if !res.status().is_success() {
-        let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("API Error: {}", error_body).into());
+        return Err(AppError::from_response(res).await);
     }
 
     let response: HelperStatusResponse = res.json().await?;
@@ -0,0 +1,56 @@
+use base64::Engine;
+use serde::{Deserialize, Deserializer};
+
+/// Guesses the container format of a recording from its leading bytes.
+///
+/// The recorder produces WebM/Opus, but imported files can be anything the
+/// backend accepts, so fall back to a generic binary type.
+pub fn sniff_mime(bytes: &[u8]) -> &'static str {
+    match bytes {
+        [0x1A, 0x45, 0xDF, 0xA3, ..] => "audio/webm",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => "audio/wav",
+        [b'O', b'g', b'g', b'S', ..] => "audio/ogg",
+        [b'f', b'L', b'a', b'C', ..] => "audio/flac",
+        [b'I', b'D', b'3', ..] | [0xFF, 0xFB, ..] | [0xFF, 0xF3, ..] | [0xFF, 0xF2, ..] => "audio/mpeg",
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => "audio/mp4",
+        [0xFF, 0xF1, ..] | [0xFF, 0xF9, ..] => "audio/aac",
+        _ => "application/octet-stream",
+    }
+}
+
+/// File extension matching [`sniff_mime`], used for upload file names.
+pub fn extension_for(mime: &str) -> &'static str {
+    match mime {
+        "audio/webm" => "webm",
+        "audio/wav" => "wav",
+        "audio/ogg" => "ogg",
+        "audio/flac" => "flac",
+        "audio/mpeg" => "mp3",
+        "audio/mp4" => "m4a",
+        "audio/aac" => "aac",
+        _ => "bin",
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EncodedAudio {
+    Base64(String),
+    Bytes(Vec<u8>),
+}
+
+/// Accepts `audio_blob` either as a base64 string (current backend) or as a
+/// JSON array of byte values (older backend responses).
+pub fn deserialize_audio_blob<'de, D>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<EncodedAudio>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(EncodedAudio::Bytes(bytes)) => Ok(Some(bytes)),
+        Some(EncodedAudio::Base64(encoded)) => base64::engine::general_purpose::STANDARD
+            .decode(encoded.trim())
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
@@ -0,0 +1,115 @@
+use crate::error::AppResult;
+use crate::vault::Vault;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use tokio::fs;
+
+/// Default upper bound for cached recordings.
+const DEFAULT_MAX_BYTES: u64 = 512 * 1024 * 1024;
+
+/// On-disk cache of memo recordings fetched with `get_memo_audio`, keyed by
+/// memo id. The least recently written files are evicted once the cache
+/// grows past its size limit. With a [`Vault`] attached, cached files are
+/// encrypted while it has a passphrase and nothing is cached while it is
+/// locked.
+pub struct AudioCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    vault: Option<Vault>,
+}
+
+impl AudioCache {
+    pub fn new(cache_dir: PathBuf) -> AppResult<Self> {
+        let dir = cache_dir.join("audio");
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            max_bytes: DEFAULT_MAX_BYTES,
+            vault: None,
+        })
+    }
+
+    pub fn with_vault(mut self, vault: Vault) -> Self {
+        self.vault = Some(vault);
+        self
+    }
+
+    fn path_for(&self, memo_id: &str) -> PathBuf {
+        // Memo ids come from the backend; keep them from escaping the cache dir.
+        let safe: String = memo_id
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect();
+        self.dir.join(format!("{}.audio", safe))
+    }
+
+    pub async fn get(&self, memo_id: &str) -> Option<Vec<u8>> {
+        let cached = fs::read(self.path_for(memo_id)).await.ok()?;
+        match &self.vault {
+            Some(vault) => vault.open(cached).ok(),
+            None => Some(cached),
+        }
+    }
+
+    pub async fn put(&self, memo_id: &str, audio: &[u8]) -> AppResult<()> {
+        let contents = match &self.vault {
+            Some(vault) if vault.status().locked => return Ok(()),
+            Some(vault) => vault.seal(audio)?,
+            None => audio.to_vec(),
+        };
+        let path = self.path_for(memo_id);
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, contents).await?;
+        fs::rename(&tmp, &path).await?;
+        self.prune().await
+    }
+
+    pub async fn remove(&self, memo_id: &str) {
+        let _ = fs::remove_file(self.path_for(memo_id)).await;
+    }
+
+    pub async fn clear(&self) -> AppResult<()> {
+        let mut entries = fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let _ = fs::remove_file(entry.path()).await;
+        }
+        Ok(())
+    }
+
+    /// Total size of the cached files.
+    pub async fn size(&self) -> AppResult<u64> {
+        Ok(self.files().await?.iter().map(|(_, len, _)| len).sum())
+    }
+
+    async fn files(&self) -> AppResult<Vec<(SystemTime, u64, PathBuf)>> {
+        let mut files = Vec::new();
+        let mut entries = fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let meta = entry.metadata().await?;
+            if meta.is_file() {
+                files.push((meta.modified()?, meta.len(), entry.path()));
+            }
+        }
+        Ok(files)
+    }
+
+    /// Evicts the oldest files until the cache fits in `max_bytes`.
+    async fn prune(&self) -> AppResult<()> {
+        let mut files = self.files().await?;
+        let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+        if total <= self.max_bytes {
+            return Ok(());
+        }
+
+        files.sort_by_key(|(modified, _, _)| *modified);
+        for (_, len, path) in files {
+            if total <= self.max_bytes {
+                break;
+            }
+            if fs::remove_file(&path).await.is_ok() {
+                total -= len;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,282 @@
+use crate::error::{AppError, AppResult};
+use crate::local_store::timestamp;
+use crate::vault::Vault;
+use chrono::{Duration, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::fs;
+
+/// Unreferenced recordings younger than this are left alone by the garbage
+/// collector, so a memo being saved right now does not lose its file.
+pub const GC_GRACE_MINUTES: i64 = 10;
+
+/// Outcome of one garbage collection run.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct GcReport {
+    pub removed_files: usize,
+    pub freed_bytes: u64,
+}
+
+/// Recordings stored as files under the app data directory, named by the
+/// SHA-256 of their contents so identical recordings are kept once.
+///
+/// The `audio_blobs` table tracks every file and how many memos point at it
+/// through `voice_memos.audio_hash`; the counts are kept by database triggers.
+/// With a [`Vault`] attached, files are encrypted while it has a passphrase.
+pub struct AudioStore {
+    dir: PathBuf,
+    vault: Option<Vault>,
+}
+
+impl AudioStore {
+    pub fn new(data_dir: PathBuf) -> AppResult<Self> {
+        let dir = data_dir.join("audio");
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir, vault: None })
+    }
+
+    pub fn with_vault(mut self, vault: Vault) -> Self {
+        self.vault = Some(vault);
+        self
+    }
+
+    pub fn hash(audio: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(audio))
+    }
+
+    /// Files are spread over subdirectories named by the first two hex digits.
+    fn path_for(&self, hash: &str) -> PathBuf {
+        self.dir.join(&hash[..2]).join(hash)
+    }
+
+    fn is_hash(name: &str) -> bool {
+        name.len() == 64 && name.bytes().all(|b| b.is_ascii_hexdigit())
+    }
+
+    /// Stores a recording unless an identical one is already on disk and
+    /// returns its hash. The file counts as referenced once a memo's
+    /// `audio_hash` is set to it, normally in the same transaction.
+    ///
+    /// The `audio_blobs` row is written before the file is looked at, so
+    /// the caller's transaction holds the database write lock while it does
+    /// and [`AudioStore::collect_garbage`] cannot remove the file meanwhile.
+    pub async fn put(&self, conn: &mut SqliteConnection, audio: &[u8]) -> AppResult<String> {
+        let hash = Self::hash(audio);
+        sqlx::query(
+            "INSERT INTO audio_blobs (hash, size, touched_at) VALUES (?, ?, ?)
+             ON CONFLICT(hash) DO UPDATE SET touched_at = excluded.touched_at",
+        )
+        .bind(&hash)
+        .bind(audio.len() as i64)
+        .bind(timestamp(Utc::now()))
+        .execute(conn)
+        .await?;
+
+        let path = self.path_for(&hash);
+        if self.holds(&path, &hash).await {
+            println!("🎧 Recording {} already stored, reusing it", hash);
+            return Ok(hash);
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).await?;
+        }
+        let contents = match &self.vault {
+            Some(vault) => vault.seal(audio)?,
+            None => audio.to_vec(),
+        };
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, contents).await?;
+        fs::rename(&tmp, &path).await?;
+        println!("🎧 Stored recording {} ({} bytes)", hash, audio.len());
+        Ok(hash)
+    }
+
+    /// Whether the file at `path` can be read back as the recording `hash`.
+    /// Missing, truncated or damaged files are written again by `put`.
+    async fn holds(&self, path: &Path, hash: &str) -> bool {
+        let Ok(contents) = fs::read(path).await else {
+            return false;
+        };
+        let audio = match &self.vault {
+            Some(vault) => match vault.open(contents) {
+                Ok(audio) => audio,
+                Err(_) => return false,
+            },
+            None => contents,
+        };
+        Self::hash(&audio) == hash
+    }
+
+    pub async fn read(&self, hash: &str) -> AppResult<Vec<u8>> {
+        if !Self::is_hash(hash) {
+            return Err(AppError::internal(format!("Invalid recording hash: {}", hash)));
+        }
+        match fs::read(self.path_for(hash)).await {
+            Ok(audio) => match &self.vault {
+                Some(vault) => vault.open(audio),
+                None => Ok(audio),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(AppError::not_found(format!("Recording {} is missing from the audio store", hash)))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Moves recordings still kept inside `voice_memos.audio_blob` (from
+    /// before the audio store existed) into files.
+    pub async fn adopt_inline(&self, pool: &SqlitePool) -> AppResult<usize> {
+        let ids: Vec<String> =
+            sqlx::query_scalar("SELECT id FROM voice_memos WHERE audio_hash IS NULL AND length(audio_blob) > 0")
+                .fetch_all(pool)
+                .await?;
+        for id in &ids {
+            let mut tx = pool.begin().await?;
+            let audio: Vec<u8> = sqlx::query_scalar("SELECT audio_blob FROM voice_memos WHERE id = ?")
+                .bind(id)
+                .fetch_one(&mut *tx)
+                .await?;
+            let hash = self.put(&mut tx, &audio).await?;
+            sqlx::query("UPDATE voice_memos SET audio_hash = ?, audio_blob = X'' WHERE id = ?")
+                .bind(&hash)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+        }
+        if !ids.is_empty() {
+            println!("🎧 Moved {} recording(s) from the database into the audio store", ids.len());
+        }
+        Ok(ids.len())
+    }
+
+    /// Hashes of the files in the store, whether tracked or not.
+    pub(crate) async fn stored_hashes(&self) -> AppResult<HashSet<String>> {
+        let mut hashes = HashSet::new();
+        let mut shards = fs::read_dir(&self.dir).await?;
+        while let Some(shard) = shards.next_entry().await? {
+            if !shard.file_type().await?.is_dir() {
+                continue;
+            }
+            let mut files = fs::read_dir(shard.path()).await?;
+            while let Some(file) = files.next_entry().await? {
+                let name = file.file_name().to_string_lossy().to_string();
+                if Self::is_hash(&name) {
+                    hashes.insert(name);
+                }
+            }
+        }
+        Ok(hashes)
+    }
+
+    /// Deletes a damaged file so that [`AudioStore::put`] writes it again.
+    pub(crate) async fn remove_file(&self, hash: &str) -> AppResult<()> {
+        if !Self::is_hash(hash) {
+            return Ok(());
+        }
+        match fs::remove_file(self.path_for(hash)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Rewrites every stored file for which `convert` returns new contents,
+    /// e.g. to encrypt or decrypt them. Returns the number of files changed.
+    pub(crate) async fn convert_files(&self, convert: impl Fn(&[u8]) -> AppResult<Option<Vec<u8>>>) -> AppResult<usize> {
+        let mut converted = 0;
+        let mut shards = fs::read_dir(&self.dir).await?;
+        while let Some(shard) = shards.next_entry().await? {
+            if !shard.file_type().await?.is_dir() {
+                continue;
+            }
+            let mut files = fs::read_dir(shard.path()).await?;
+            while let Some(file) = files.next_entry().await? {
+                let path = file.path();
+                if !Self::is_hash(&file.file_name().to_string_lossy()) {
+                    continue;
+                }
+                if let Some(contents) = convert(&fs::read(&path).await?)? {
+                    let tmp = path.with_extension("tmp");
+                    fs::write(&tmp, contents).await?;
+                    fs::rename(&tmp, &path).await?;
+                    converted += 1;
+                }
+            }
+        }
+        Ok(converted)
+    }
+
+    /// Removes recordings no memo points to, and stray files the store does
+    /// not know about, once they are older than `min_age`.
+    ///
+    /// Files are removed before the transaction commits, while it holds the
+    /// database write lock: a concurrent [`AudioStore::put`] either waits
+    /// and then writes the file again, or has already claimed it.
+    pub async fn collect_garbage(&self, pool: &SqlitePool, min_age: Duration) -> AppResult<GcReport> {
+        let cutoff = Utc::now() - min_age;
+        let mut report = GcReport::default();
+
+        let mut tx = pool.begin().await?;
+        // Recount first so a drifted count can never delete a file in use.
+        sqlx::query("UPDATE audio_blobs SET refcount = (SELECT COUNT(*) FROM voice_memos WHERE audio_hash = audio_blobs.hash)")
+            .execute(&mut *tx)
+            .await?;
+        let unreferenced: Vec<(String, i64)> =
+            sqlx::query_as("DELETE FROM audio_blobs WHERE refcount = 0 AND touched_at < ? RETURNING hash, size")
+                .bind(timestamp(cutoff))
+                .fetch_all(&mut *tx)
+                .await?;
+        let known: HashSet<String> = sqlx::query_scalar("SELECT hash FROM audio_blobs")
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .collect();
+
+        for (hash, size) in unreferenced {
+            if fs::remove_file(self.path_for(&hash)).await.is_ok() {
+                report.removed_files += 1;
+                report.freed_bytes += size as u64;
+            }
+        }
+
+        let cutoff: SystemTime = cutoff.into();
+        let mut shards = fs::read_dir(&self.dir).await?;
+        while let Some(shard) = shards.next_entry().await? {
+            if !shard.file_type().await?.is_dir() {
+                continue;
+            }
+            let mut files = fs::read_dir(shard.path()).await?;
+            while let Some(file) = files.next_entry().await? {
+                let name = file.file_name().to_string_lossy().to_string();
+                if known.contains(&name) || !is_older(&file.path(), cutoff).await {
+                    continue;
+                }
+                let size = file.metadata().await.map(|m| m.len()).unwrap_or(0);
+                if fs::remove_file(file.path()).await.is_ok() {
+                    report.removed_files += 1;
+                    report.freed_bytes += size;
+                }
+            }
+        }
+        tx.commit().await?;
+
+        if report.removed_files > 0 {
+            println!(
+                "🧹 Removed {} unused recording(s), freed {} bytes",
+                report.removed_files, report.freed_bytes
+            );
+        }
+        Ok(report)
+    }
+}
+
+async fn is_older(path: &Path, cutoff: SystemTime) -> bool {
+    match fs::metadata(path).await.and_then(|m| m.modified()) {
+        Ok(modified) => modified <= cutoff,
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,462 @@
+use crate::audio_store::AudioStore;
+use crate::error::{AppError, AppResult};
+use crate::events::{emit_backup_progress, emit_memo_updated};
+use crate::local_store::{replace_tags, tags_for, timestamp};
+use crate::revisions::{self, RevisionSource};
+use crate::storage;
+use crate::trash;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, Runtime};
+use tokio::fs;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Version of the archive layout written by [`export_library`].
+const ARCHIVE_FORMAT: u32 = 1;
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Contents of `manifest.json`. Recordings are stored next to it as
+/// `audio/<sha256>`, once per distinct recording.
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    format: u32,
+    exported_at: String,
+    memos: Vec<ArchivedMemo>,
+    /// The app's `config.json`, if there was one.
+    settings: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, sqlx::FromRow)]
+struct ArchivedMemo {
+    id: String,
+    title: String,
+    transcript: Option<String>,
+    translate: Option<String>,
+    summary: Option<String>,
+    #[sqlx(skip)]
+    tags: Vec<String>,
+    duration: String,
+    created_at: String,
+    updated_at: Option<String>,
+    /// Id of the memo on the backend, if it was synced.
+    remote_id: Option<String>,
+    #[sqlx(flatten)]
+    audio: ArchivedAudio,
+}
+
+#[derive(Serialize, Deserialize, sqlx::FromRow)]
+struct ArchivedAudio {
+    #[sqlx(rename = "audio_hash")]
+    hash: Option<String>,
+    #[sqlx(rename = "audio_mime")]
+    mime: String,
+    #[sqlx(rename = "audio_size")]
+    size: i64,
+}
+
+fn audio_entry(hash: &str) -> String {
+    format!("audio/{}", hash)
+}
+
+/// Runs blocking file and zip I/O off the async runtime.
+async fn blocking<T, F>(f: F) -> AppResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> AppResult<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| AppError::internal(format!("Backup task failed: {}", e)))?
+}
+
+/// Adds one file to the archive. The writer is handed back, since it
+/// moves into the blocking task.
+async fn write_entry(
+    mut zip: ZipWriter<File>,
+    name: String,
+    options: FileOptions,
+    contents: Vec<u8>,
+) -> AppResult<ZipWriter<File>> {
+    blocking(move || {
+        zip.start_file(name, options)?;
+        zip.write_all(&contents)?;
+        Ok(zip)
+    })
+    .await
+}
+
+/// Reads one file from the archive, handing the archive back with it.
+async fn read_entry(mut archive: ZipArchive<File>, name: String) -> AppResult<(ZipArchive<File>, Vec<u8>)> {
+    blocking(move || {
+        let mut contents = Vec::new();
+        archive.by_name(&name)?.read_to_end(&mut contents)?;
+        Ok((archive, contents))
+    })
+    .await
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupOperation {
+    Export,
+    Import,
+}
+
+/// Payload of the `backup:progress` event, sent after each memo.
+#[derive(Serialize, Debug, Clone)]
+pub struct BackupProgress {
+    pub operation: BackupOperation,
+    pub done: usize,
+    pub total: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ExportReport {
+    pub memos: usize,
+    pub recordings: usize,
+    /// Size of the archive file.
+    pub bytes: u64,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Adds memos from the archive that are not in the library yet.
+    #[default]
+    Merge,
+    /// Deletes every memo in the library, including the trash, first. Memos
+    /// that are also on the backend are deleted there as well, as when the
+    /// trash is emptied.
+    Replace,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ImportOptions {
+    pub mode: ImportMode,
+    /// Overwrite `config.json` with the archived settings; they take effect
+    /// on the next start.
+    pub restore_settings: bool,
+}
+
+/// Why a memo from the archive was skipped.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateReason {
+    /// A memo with the same id is already in the library.
+    Id,
+    /// The library already has the same backend memo.
+    RemoteId,
+    /// The library already has a memo with the same recording.
+    Audio,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DuplicateMemo {
+    pub id: String,
+    pub title: String,
+    pub reason: DuplicateReason,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// Memos deleted from the library in [`ImportMode::Replace`].
+    pub replaced: usize,
+    pub duplicates: Vec<DuplicateMemo>,
+    pub settings_restored: bool,
+}
+
+/// Writes every memo outside the trash, with tags and recordings, plus the
+/// settings file to a zip archive at `path`.
+///
+/// Recordings are written decrypted, so the archive is readable without
+/// the library passphrase.
+pub async fn export_library<R: Runtime>(
+    app: &AppHandle<R>,
+    pool: &SqlitePool,
+    path: &Path,
+    settings: &Path,
+) -> AppResult<ExportReport> {
+    let mut memos: Vec<ArchivedMemo> = sqlx::query_as(
+        "SELECT id, name AS title, transcription AS transcript, translate, summary, duration,
+                date AS created_at, updated_at, remote_id, audio_hash, audio_mime, audio_size
+         FROM voice_memos WHERE deleted_at IS NULL ORDER BY date, id",
+    )
+    .fetch_all(pool)
+    .await?;
+    let ids: Vec<&str> = memos.iter().map(|m| m.id.as_str()).collect();
+    let mut tags = tags_for(pool, &ids).await?;
+    for memo in &mut memos {
+        memo.tags = tags.remove(&memo.id).unwrap_or_default();
+    }
+    let settings = match fs::read(settings).await {
+        Ok(raw) => Some(serde_json::from_slice(&raw)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    let partial = PathBuf::from(format!("{}.partial", path.display()));
+    let mut zip = ZipWriter::new(fs::File::create(&partial).await?.into_std().await);
+    // Recordings are already compressed.
+    let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+    let audio = app.state::<AudioStore>();
+    let mut written = HashSet::new();
+    for (done, memo) in memos.iter().enumerate() {
+        if let Some(hash) = &memo.audio.hash {
+            if written.insert(hash.clone()) {
+                let recording = audio.read(hash).await?;
+                zip = write_entry(zip, audio_entry(hash), stored, recording).await?;
+            }
+        }
+        emit_backup_progress(
+            app,
+            BackupProgress {
+                operation: BackupOperation::Export,
+                done: done + 1,
+                total: memos.len(),
+            },
+        );
+    }
+
+    let manifest = Manifest {
+        format: ARCHIVE_FORMAT,
+        exported_at: timestamp(Utc::now()),
+        memos,
+        settings,
+    };
+    let contents = serde_json::to_vec_pretty(&manifest)?;
+    let mut zip = write_entry(zip, MANIFEST_FILE.to_string(), FileOptions::default(), contents).await?;
+    blocking(move || Ok(zip.finish()?)).await?;
+    fs::rename(&partial, path).await?;
+
+    let report = ExportReport {
+        memos: manifest.memos.len(),
+        recordings: written.len(),
+        bytes: fs::metadata(path).await?.len(),
+    };
+    println!(
+        "📦 Exported {} memos and {} recordings to {}",
+        report.memos,
+        report.recordings,
+        path.display()
+    );
+    Ok(report)
+}
+
+/// Permanently deletes every memo for [`ImportMode::Replace`]. Memos that
+/// are also on the backend leave a tombstone, as when the trash is purged,
+/// and writes queued for any of them are dropped.
+async fn clear_library(conn: &mut SqliteConnection) -> AppResult<usize> {
+    sqlx::query(
+        "INSERT OR IGNORE INTO sync_tombstones (remote_id, deleted_at)
+         SELECT remote_id, COALESCE(deleted_at, ?) FROM voice_memos WHERE remote_id IS NOT NULL",
+    )
+    .bind(timestamp(Utc::now()))
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query(
+        "DELETE FROM outbox WHERE memo_id IN (SELECT id FROM voice_memos)
+            OR memo_id IN (SELECT remote_id FROM voice_memos WHERE remote_id IS NOT NULL)",
+    )
+    .execute(&mut *conn)
+    .await?;
+    let result = sqlx::query("DELETE FROM voice_memos").execute(&mut *conn).await?;
+    Ok(result.rows_affected() as usize)
+}
+
+/// Bytes the audio store needs for the recordings of `memos` it does not
+/// have yet, each counted once.
+async fn incoming_bytes(pool: &SqlitePool, memos: &[&ArchivedMemo]) -> AppResult<u64> {
+    let stored: HashSet<String> = sqlx::query_scalar("SELECT hash FROM audio_blobs")
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+    let mut seen = HashSet::new();
+    Ok(memos
+        .iter()
+        .filter_map(|memo| Some((memo.audio.hash.as_deref()?, memo.audio.size)))
+        .filter(|(hash, _)| !stored.contains(*hash) && seen.insert(*hash))
+        .map(|(_, size)| size.max(0) as u64)
+        .sum())
+}
+
+/// Restores memos from an archive written by [`export_library`] in one
+/// transaction. In merge mode memos whose id, backend id or recording is
+/// already in the library are skipped and reported as duplicates.
+///
+/// Fails with `QuotaExceeded` before changing anything if the new
+/// recordings would not fit the storage quota.
+pub async fn import_library<R: Runtime>(
+    app: &AppHandle<R>,
+    pool: &SqlitePool,
+    path: &Path,
+    options: &ImportOptions,
+    settings: &Path,
+) -> AppResult<ImportReport> {
+    let file = path.to_path_buf();
+    let (mut archive, manifest) = blocking(move || {
+        let mut archive = ZipArchive::new(File::open(file)?)?;
+        let mut raw = Vec::new();
+        archive.by_name(MANIFEST_FILE)?.read_to_end(&mut raw)?;
+        let manifest: Manifest = serde_json::from_slice(&raw)?;
+        Ok((archive, manifest))
+    })
+    .await?;
+    if manifest.format > ARCHIVE_FORMAT {
+        return Err(AppError::validation(
+            "The backup was made by a newer version of SmartMemo",
+        ));
+    }
+
+    // Merge mode skips memos the library already has, so only the others
+    // count against the quota.
+    let candidates: Vec<&ArchivedMemo> = match options.mode {
+        ImportMode::Replace => manifest.memos.iter().collect(),
+        ImportMode::Merge => {
+            let known: HashSet<String> =
+                sqlx::query_scalar("SELECT id FROM voice_memos UNION SELECT remote_id FROM voice_memos WHERE remote_id IS NOT NULL")
+                    .fetch_all(pool)
+                    .await?
+                    .into_iter()
+                    .collect();
+            manifest
+                .memos
+                .iter()
+                .filter(|m| !known.contains(&m.id) && !m.remote_id.as_ref().is_some_and(|id| known.contains(id)))
+                .collect()
+        }
+    };
+    storage::check_quota(app, incoming_bytes(pool, &candidates).await?).await?;
+
+    let mut report = ImportReport::default();
+    let mut tx = pool.begin().await?;
+    if options.mode == ImportMode::Replace {
+        report.replaced = clear_library(&mut tx).await?;
+    }
+    let mut ids: HashSet<String> = sqlx::query_scalar("SELECT id FROM voice_memos")
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .collect();
+    let mut remote_ids: HashSet<String> =
+        sqlx::query_scalar("SELECT remote_id FROM voice_memos WHERE remote_id IS NOT NULL")
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .collect();
+    let mut hashes: HashSet<String> =
+        sqlx::query_scalar("SELECT audio_hash FROM voice_memos WHERE audio_hash IS NOT NULL AND deleted_at IS NULL")
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .collect();
+
+    let audio = app.state::<AudioStore>();
+    let total = manifest.memos.len();
+    for (done, memo) in manifest.memos.iter().enumerate() {
+        let duplicate = if ids.contains(&memo.id) {
+            Some(DuplicateReason::Id)
+        } else if memo.remote_id.as_ref().is_some_and(|id| remote_ids.contains(id)) {
+            Some(DuplicateReason::RemoteId)
+        } else if memo.audio.hash.as_ref().is_some_and(|hash| hashes.contains(hash)) {
+            Some(DuplicateReason::Audio)
+        } else {
+            None
+        };
+        if let Some(reason) = duplicate {
+            report.duplicates.push(DuplicateMemo {
+                id: memo.id.clone(),
+                title: memo.title.clone(),
+                reason,
+            });
+        } else {
+            let hash = match &memo.audio.hash {
+                Some(hash) => {
+                    let recording;
+                    (archive, recording) = read_entry(archive, audio_entry(hash)).await?;
+                    if AudioStore::hash(&recording) != *hash {
+                        return Err(AppError::validation(format!(
+                            "The recording of memo {} is damaged in the backup",
+                            memo.id
+                        )));
+                    }
+                    Some(audio.put(&mut tx, &recording).await?)
+                }
+                None => None,
+            };
+            // Memos known to the backend are taken as in sync with it.
+            sqlx::query(
+                "INSERT INTO voice_memos (id, name, transcription, translate, summary, date, duration,
+                     audio_blob, audio_hash, audio_mime, audio_size, updated_at, remote_id, dirty)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, X'', ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&memo.id)
+            .bind(&memo.title)
+            .bind(&memo.transcript)
+            .bind(&memo.translate)
+            .bind(&memo.summary)
+            .bind(&memo.created_at)
+            .bind(&memo.duration)
+            .bind(&hash)
+            .bind(&memo.audio.mime)
+            .bind(memo.audio.size)
+            .bind(&memo.updated_at)
+            .bind(&memo.remote_id)
+            .bind(memo.remote_id.is_none())
+            .execute(&mut *tx)
+            .await?;
+            replace_tags(&mut tx, &memo.id, &memo.tags).await?;
+            revisions::record(&mut tx, &memo.id, RevisionSource::Import).await?;
+            // A memo restored from the backup is no longer deleted on the backend.
+            if let Some(remote_id) = &memo.remote_id {
+                sqlx::query("DELETE FROM sync_tombstones WHERE remote_id = ?")
+                    .bind(remote_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            ids.insert(memo.id.clone());
+            remote_ids.extend(memo.remote_id.clone());
+            hashes.extend(hash);
+            report.imported += 1;
+        }
+        emit_backup_progress(
+            app,
+            BackupProgress {
+                operation: BackupOperation::Import,
+                done: done + 1,
+                total,
+            },
+        );
+    }
+    tx.commit().await?;
+    if report.replaced > 0 {
+        trash::send_tombstones(app, pool).await?;
+    }
+
+    if options.restore_settings {
+        if let Some(archived) = &manifest.settings {
+            if let Some(dir) = settings.parent() {
+                fs::create_dir_all(dir).await?;
+            }
+            fs::write(settings, serde_json::to_vec_pretty(archived)?).await?;
+            report.settings_restored = true;
+        }
+    }
+    println!(
+        "📦 Imported {} memos from {} ({} duplicates skipped)",
+        report.imported,
+        path.display(),
+        report.duplicates.len()
+    );
+    emit_memo_updated(app);
+    Ok(report)
+}
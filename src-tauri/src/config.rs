@@ -0,0 +1,145 @@
+use crate::memo_store::StorageConfig;
+use crate::retry::RetryPolicy;
+use crate::storage::QuotaConfig;
+use crate::sync::SyncConfig;
+use crate::trash::TrashConfig;
+use crate::upload::UploadConfig;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_API_BASE_URL: &str = "https://smartmemo-backend-rust.onrender.com/api";
+pub const CONFIG_FILE_NAME: &str = "config.json";
+
+/// Settings for talking to the SmartMemo backend.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ApiConfig {
+    pub base_url: String,
+    pub timeout_secs: u64,
+    pub connect_timeout_secs: u64,
+    pub user_agent: String,
+    pub default_headers: HashMap<String, String>,
+    pub retry: RetryPolicy,
+    /// After this long without a response the backend is assumed to be asleep.
+    pub cold_start_idle_secs: u64,
+    /// Per-request timeout used while the backend may be waking up.
+    pub cold_start_timeout_secs: u64,
+    /// How long a cold request may run before `backend:waking` is emitted.
+    pub waking_notice_ms: u64,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_API_BASE_URL.to_string(),
+            timeout_secs: 60,
+            connect_timeout_secs: 15,
+            user_agent: format!("SmartMemo-Desktop/{}", env!("CARGO_PKG_VERSION")),
+            default_headers: HashMap::new(),
+            retry: RetryPolicy::default(),
+            cold_start_idle_secs: 600,
+            cold_start_timeout_secs: 120,
+            waking_notice_ms: 3_000,
+        }
+    }
+}
+
+/// Application configuration, read from `config.json` in the app config
+/// directory and then overridden by `SMARTMEMO_*` environment variables.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct AppConfig {
+    pub api: ApiConfig,
+    pub upload: UploadConfig,
+    pub storage: StorageConfig,
+    pub sync: SyncConfig,
+    pub trash: TrashConfig,
+    pub quota: QuotaConfig,
+}
+
+impl AppConfig {
+    /// Loads the configuration. `SMARTMEMO_CONFIG` may point at an explicit
+    /// file; otherwise `config.json` inside `config_dir` is used if present.
+    pub fn load(config_dir: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        let path = env::var("SMARTMEMO_CONFIG")
+            .map(PathBuf::from)
+            .ok()
+            .or_else(|| config_dir.map(|dir| dir.join(CONFIG_FILE_NAME)));
+
+        let mut config = match path {
+            Some(path) if path.exists() => {
+                let raw = std::fs::read_to_string(&path)?;
+                let config: AppConfig = serde_json::from_str(&raw)
+                    .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))?;
+                println!("⚙️ Loaded config from {}", path.display());
+                config
+            }
+            _ => AppConfig::default(),
+        };
+
+        config.apply_env_overrides()?;
+        Ok(config)
+    }
+
+    fn apply_env_overrides(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Ok(url) = env::var("SMARTMEMO_API_BASE_URL") {
+            self.api.base_url = url;
+        }
+        if let Ok(secs) = env::var("SMARTMEMO_API_TIMEOUT_SECS") {
+            self.api.timeout_secs = secs
+                .parse()
+                .map_err(|_| format!("SMARTMEMO_API_TIMEOUT_SECS is not a number: {}", secs))?;
+        }
+        if let Ok(secs) = env::var("SMARTMEMO_API_CONNECT_TIMEOUT_SECS") {
+            self.api.connect_timeout_secs = secs
+                .parse()
+                .map_err(|_| format!("SMARTMEMO_API_CONNECT_TIMEOUT_SECS is not a number: {}", secs))?;
+        }
+        if let Ok(attempts) = env::var("SMARTMEMO_API_MAX_ATTEMPTS") {
+            self.api.retry.max_attempts = attempts
+                .parse()
+                .map_err(|_| format!("SMARTMEMO_API_MAX_ATTEMPTS is not a number: {}", attempts))?;
+        }
+        if let Ok(backend) = env::var("SMARTMEMO_STORAGE_BACKEND") {
+            self.storage.backend = backend.parse()?;
+        }
+        if let Ok(policy) = env::var("SMARTMEMO_SYNC_CONFLICT_POLICY") {
+            self.sync.conflict_policy = policy.parse()?;
+        }
+        if let Ok(days) = env::var("SMARTMEMO_TRASH_RETENTION_DAYS") {
+            self.trash.retention_days = days
+                .parse()
+                .map_err(|_| format!("SMARTMEMO_TRASH_RETENTION_DAYS is not a number: {}", days))?;
+        }
+        if let Ok(mb) = env::var("SMARTMEMO_QUOTA_SOFT_LIMIT_MB") {
+            self.quota.soft_limit_mb = Some(
+                mb.parse()
+                    .map_err(|_| format!("SMARTMEMO_QUOTA_SOFT_LIMIT_MB is not a number: {}", mb))?,
+            );
+        }
+        if let Ok(mb) = env::var("SMARTMEMO_QUOTA_HARD_LIMIT_MB") {
+            self.quota.hard_limit_mb = Some(
+                mb.parse()
+                    .map_err(|_| format!("SMARTMEMO_QUOTA_HARD_LIMIT_MB is not a number: {}", mb))?,
+            );
+        }
+        if let Ok(agent) = env::var("SMARTMEMO_API_USER_AGENT") {
+            self.api.user_agent = agent;
+        }
+        // Extra headers as a comma separated list, e.g. "X-Env: staging, X-Team: core".
+        if let Ok(headers) = env::var("SMARTMEMO_API_HEADERS") {
+            for pair in headers.split(',').filter(|p| !p.trim().is_empty()) {
+                let (name, value) = pair
+                    .split_once(':')
+                    .ok_or_else(|| format!("SMARTMEMO_API_HEADERS entry is not `Name: value`: {}", pair))?;
+                self.api
+                    .default_headers
+                    .insert(name.trim().to_string(), value.trim().to_string());
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+use crate::retry::is_retryable_status;
+use reqwest::{Response, StatusCode};
+use serde::Serialize;
+use std::fmt;
+
+/// Broad category of a failure, so the frontend can react without parsing messages.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// Missing, invalid or expired token (HTTP 401).
+    Unauthorized,
+    /// Authenticated but not allowed (HTTP 403).
+    Forbidden,
+    NotFound,
+    /// The request was rejected as malformed (HTTP 400/422) or failed local checks.
+    Validation,
+    Conflict,
+    RateLimited,
+    /// The backend failed (HTTP 5xx).
+    Server,
+    /// The backend could not be reached.
+    Network,
+    Timeout,
+    /// A response could not be decoded.
+    Decode,
+    Io,
+    /// The local database failed.
+    Database,
+    /// The local library is encrypted and has not been unlocked.
+    Locked,
+    /// Local storage reached its hard limit (see [`crate::storage`]).
+    QuotaExceeded,
+    Internal,
+}
+
+/// Error returned by every command and API module.
+///
+/// Serialized to the frontend as
+/// `{ kind, status, retryable, message, body }`.
+#[derive(Serialize, Debug, Clone)]
+pub struct AppError {
+    pub kind: ErrorKind,
+    /// HTTP status code when the error came from a backend response.
+    pub status: Option<u16>,
+    /// Whether repeating the same request may succeed.
+    pub retryable: bool,
+    pub message: String,
+    /// Raw backend response body, if any.
+    pub body: Option<String>,
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            status: None,
+            retryable: matches!(kind, ErrorKind::Network | ErrorKind::Timeout),
+            message: message.into(),
+            body: None,
+        }
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Validation, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::NotFound, message)
+    }
+
+    pub fn locked() -> Self {
+        Self::new(ErrorKind::Locked, "The memo library is locked")
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Internal, message)
+    }
+
+    /// Builds an error from an HTTP status and the backend's response body.
+    pub fn from_status(status: StatusCode, body: String) -> Self {
+        let kind = match status.as_u16() {
+            400 | 422 => ErrorKind::Validation,
+            401 => ErrorKind::Unauthorized,
+            403 => ErrorKind::Forbidden,
+            404 => ErrorKind::NotFound,
+            408 => ErrorKind::Timeout,
+            409 => ErrorKind::Conflict,
+            429 => ErrorKind::RateLimited,
+            500..=599 => ErrorKind::Server,
+            _ => ErrorKind::Internal,
+        };
+        let retryable = is_retryable_status(status.as_u16());
+
+        Self {
+            kind,
+            status: Some(status.as_u16()),
+            retryable,
+            message: extract_message(&body)
+                .unwrap_or_else(|| format!("Request failed with status {}", status)),
+            body: if body.is_empty() { None } else { Some(body) },
+        }
+    }
+
+    /// Consumes a non-success response and turns it into an error.
+    pub async fn from_response(res: Response) -> Self {
+        let status = res.status();
+        let body = res.text().await.unwrap_or_default();
+        Self::from_status(status, body)
+    }
+}
+
+/// Pulls a human readable message out of a JSON error body, falling back to
+/// the plain text body.
+fn extract_message(body: &str) -> Option<String> {
+    let trimmed = body.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    if let Ok(value) = serde_json::from_str::<serde_json::Value>(trimmed) {
+        for key in ["message", "error", "detail"] {
+            if let Some(message) = value.get(key).and_then(|v| v.as_str()) {
+                return Some(message.to_string());
+            }
+        }
+    }
+    Some(trimmed.to_string())
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.status {
+            Some(status) => write!(f, "{:?} ({}): {}", self.kind, status, self.message),
+            None => write!(f, "{:?}: {}", self.kind, self.message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Self::new(ErrorKind::Timeout, e.to_string())
+        } else if e.is_decode() {
+            Self::new(ErrorKind::Decode, e.to_string())
+        } else if let Some(status) = e.status() {
+            Self::from_status(status, String::new())
+        } else if e.is_builder() {
+            Self::internal(e.to_string())
+        } else {
+            Self::new(ErrorKind::Network, e.to_string())
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        Self::new(ErrorKind::Io, e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::new(ErrorKind::Decode, e.to_string())
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(e: tauri::Error) -> Self {
+        Self::internal(e.to_string())
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::RowNotFound => Self::not_found("Record not found"),
+            sqlx::Error::Database(db) if db.is_unique_violation() => {
+                Self::new(ErrorKind::Conflict, db.to_string())
+            }
+            // SQLITE_NOTADB: an encrypted database opened without its key.
+            sqlx::Error::Database(db) if db.code().as_deref() == Some("26") => Self::locked(),
+            e => Self::new(ErrorKind::Database, e.to_string()),
+        }
+    }
+}
+
+impl From<zip::result::ZipError> for AppError {
+    fn from(e: zip::result::ZipError) -> Self {
+        match e {
+            zip::result::ZipError::Io(e) => e.into(),
+            e => Self::new(ErrorKind::Decode, format!("Invalid backup archive: {}", e)),
+        }
+    }
+}
+
+impl From<sqlx::migrate::MigrateError> for AppError {
+    fn from(e: sqlx::migrate::MigrateError) -> Self {
+        Self::new(ErrorKind::Database, format!("Database migration failed: {}", e))
+    }
+}
@@ -1,7 +1,14 @@
-use tauri::{AppHandle, Emitter};
+use crate::backup::BackupProgress;
+use crate::retry::WakingStatus;
+use crate::session::{AuthExpired, AuthExpiring, SessionInfo};
+use crate::storage::QuotaStatus;
+use crate::sync::{SyncConflict, SyncProgress};
+use crate::upload::UploadProgress;
+use crate::vault::VaultStatus;
+use tauri::{AppHandle, Emitter, Runtime};
 
 
-pub fn emit_memo_updated(app: &AppHandle) {
+pub fn emit_memo_updated<R: Runtime>(app: &AppHandle<R>) {
     println!("🔄 Emitting memo:updated event...");
     if let Err(e) = app.emit("memo:updated", "refresh") {
         println!("❌ Failed to emit memo:updated event: {:?}", e);
@@ -9,3 +16,71 @@ pub fn emit_memo_updated(app: &AppHandle) {
         println!("✅ Successfully emitted memo:updated event");
     }
 }
+
+pub fn emit_backend_waking<R: Runtime>(app: &AppHandle<R>, status: WakingStatus) {
+    println!("😴 Emitting backend:waking event (waking: {})...", status.waking);
+    if let Err(e) = app.emit("backend:waking", status) {
+        println!("❌ Failed to emit backend:waking event: {:?}", e);
+    }
+}
+
+pub fn emit_upload_progress<R: Runtime>(app: &AppHandle<R>, progress: UploadProgress) {
+    if let Err(e) = app.emit("upload:progress", progress) {
+        println!("❌ Failed to emit upload:progress event: {:?}", e);
+    }
+}
+
+pub fn emit_sync_progress<R: Runtime>(app: &AppHandle<R>, progress: SyncProgress) {
+    if let Err(e) = app.emit("sync:progress", progress) {
+        println!("❌ Failed to emit sync:progress event: {:?}", e);
+    }
+}
+
+pub fn emit_sync_conflict<R: Runtime>(app: &AppHandle<R>, conflict: SyncConflict) {
+    println!("⚔️ Emitting sync:conflict event for memo {}...", conflict.memo_id);
+    if let Err(e) = app.emit("sync:conflict", conflict) {
+        println!("❌ Failed to emit sync:conflict event: {:?}", e);
+    }
+}
+
+pub fn emit_vault_status<R: Runtime>(app: &AppHandle<R>, status: VaultStatus) {
+    println!("🔐 Emitting vault:status event (encrypted: {}, locked: {})...", status.encrypted, status.locked);
+    if let Err(e) = app.emit("vault:status", status) {
+        println!("❌ Failed to emit vault:status event: {:?}", e);
+    }
+}
+
+pub fn emit_backup_progress<R: Runtime>(app: &AppHandle<R>, progress: BackupProgress) {
+    if let Err(e) = app.emit("backup:progress", progress) {
+        println!("❌ Failed to emit backup:progress event: {:?}", e);
+    }
+}
+
+pub fn emit_storage_quota<R: Runtime>(app: &AppHandle<R>, status: QuotaStatus) {
+    println!("💽 Emitting storage:quota event ({} bytes used, {:?})...", status.used_bytes, status.state);
+    if let Err(e) = app.emit("storage:quota", status) {
+        println!("❌ Failed to emit storage:quota event: {:?}", e);
+    }
+}
+
+/// Sent on login and logout; the payload is `null` once logged out.
+pub fn emit_session_changed<R: Runtime>(app: &AppHandle<R>, session: Option<SessionInfo>) {
+    println!("🔑 Emitting session:changed event (logged in: {})...", session.is_some());
+    if let Err(e) = app.emit("session:changed", session) {
+        println!("❌ Failed to emit session:changed event: {:?}", e);
+    }
+}
+
+pub fn emit_auth_expiring<R: Runtime>(app: &AppHandle<R>, expiring: AuthExpiring) {
+    println!("⌛ Emitting auth:expiring event ({}s left)...", expiring.seconds_left);
+    if let Err(e) = app.emit("auth:expiring", expiring) {
+        println!("❌ Failed to emit auth:expiring event: {:?}", e);
+    }
+}
+
+pub fn emit_auth_expired<R: Runtime>(app: &AppHandle<R>, expired: AuthExpired) {
+    println!("⌛ Emitting auth:expired event ({:?})...", expired.reason);
+    if let Err(e) = app.emit("auth:expired", expired) {
+        println!("❌ Failed to emit auth:expired event: {:?}", e);
+    }
+}
@@ -1,40 +1,35 @@
-use std::error::Error;
 use tokio::fs;
-use reqwest::Client;
+use crate::api_client::ApiClient;
+use crate::audio;
+use crate::error::{AppError, AppResult};
 use serde_json::json;
 
-const API_BASE_URL: &str = "https://smartmemo-backend-rust.onrender.com/api";
-
 /// Transcribes an audio file by sending it to your backend server.
 ///
 /// # Arguments
 /// * `audio_path` - The local path to the audio file.
 /// * `token` - The JWT authentication token for the user.
-pub async fn transcribe_audio(audio_path: &str, token: &str) -> Result<String, Box<dyn Error>> {
+pub async fn transcribe_audio(api: &ApiClient, audio_path: &str, token: &str) -> AppResult<String> {
     // 1. Read the audio file into bytes
     let audio_bytes = fs::read(audio_path).await?;
     if audio_bytes.is_empty() {
-        return Err("No audio data provided".into());
+        return Err(AppError::validation("No audio data provided"));
     }
 
-    // 2. Create the HTTP client and the request payload
-    let client = Client::new();
-    let payload = json!({
-        "audio_bytes": audio_bytes
-    });
-
-    // 3. Send the request to your backend API
-    let res = client
-        .post(&format!("{}/transcribe", API_BASE_URL))
+    // 2. Send the raw bytes to your backend API; the container format goes in a header
+    let res = api
+        .post("/transcribe")
         .header("Authorization", format!("Bearer {}", token))
-        .json(&payload)
+        .header("Content-Type", "application/octet-stream")
+        .header("X-Audio-Content-Type", audio::sniff_mime(&audio_bytes))
+        .body(audio_bytes)
+        .idempotent()
         .send()
         .await?;
 
-    // 4. Handle the response
+    // 3. Handle the response
     if !res.status().is_success() {
-        let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("API Error: {}", error_body).into());
+        return Err(AppError::from_response(res).await);
     }
 
     let response_text = res.text().await?;
@@ -43,26 +38,26 @@ pub async fn transcribe_audio(audio_path: &str, token: &str) -> Result<String, B
 
 /// Translates text by sending it to your backend server.
 pub async fn translate_text(
+    api: &ApiClient,
     transcript: &str,
     target_language: &str,
     token: &str,
-) -> Result<String, Box<dyn Error>> {
-    let client = Client::new();
+) -> AppResult<String> {
     let payload = json!({
         "text": transcript,
         "lang": target_language
     });
 
-    let res = client
-        .post(&format!("{}/translate", API_BASE_URL))
+    let res = api
+        .post("/translate")
         .header("Authorization", format!("Bearer {}", token))
         .json(&payload)
+        .idempotent()
         .send()
         .await?;
 
     if !res.status().is_success() {
-        let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("API Error: {}", error_body).into());
+        return Err(AppError::from_response(res).await);
     }
 
     let response_text = res.text().await?;
@@ -70,22 +65,21 @@ pub async fn translate_text(
 }
 
 /// Summarizes text by sending it to your backend server.
-pub async fn summarize_text(text: &str, token: &str) -> Result<String, Box<dyn Error>> {
-    let client = Client::new();
+pub async fn summarize_text(api: &ApiClient, text: &str, token: &str) -> AppResult<String> {
     let payload = json!({
         "text": text
     });
 
-    let res = client
-        .post(&format!("{}/summary", API_BASE_URL))
+    let res = api
+        .post("/summary")
         .header("Authorization", format!("Bearer {}", token))
         .json(&payload)
+        .idempotent()
         .send()
         .await?;
 
     if !res.status().is_success() {
-        let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("API Error: {}", error_body).into());
+        return Err(AppError::from_response(res).await);
     }
 
     let response_text = res.text().await?;
@@ -93,22 +87,21 @@ pub async fn summarize_text(text: &str, token: &str) -> Result<String, Box<dyn E
 }
 
 /// Generates a memo title by sending the transcript to your backend server.
-pub async fn generate_memo_name(transcription: &str, token: &str) -> Result<String, Box<dyn Error>> {
-    let client = Client::new();
+pub async fn generate_memo_name(api: &ApiClient, transcription: &str, token: &str) -> AppResult<String> {
     let payload = json!({
         "transcript": transcription
     });
 
-    let res = client
-        .post(&format!("{}/generate_memo_name", API_BASE_URL))
+    let res = api
+        .post("/generate_memo_name")
         .header("Authorization", format!("Bearer {}", token))
         .json(&payload)
+        .idempotent()
         .send()
         .await?;
 
     if !res.status().is_success() {
-        let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("API Error: {}", error_body).into());
+        return Err(AppError::from_response(res).await);
     }
 
     let response_text = res.text().await?;
@@ -0,0 +1,97 @@
+use crate::error::AppResult;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+pub(crate) const PENDING_FILE_NAME: &str = "pending_uploads.json";
+/// Keys older than this are dropped; the backend no longer remembers them either.
+const KEY_TTL_HOURS: i64 = 24;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct PendingKey {
+    key: String,
+    created_at: DateTime<Utc>,
+}
+
+/// Idempotency keys for uploads that have not been confirmed by the server yet.
+///
+/// Keys are looked up by a fingerprint of the upload so that a retry of the
+/// same recording, whether from the retry layer, the user or after a restart,
+/// reuses the key and the backend can de-duplicate it.
+pub struct IdempotencyStore {
+    path: PathBuf,
+    pending: Mutex<HashMap<String, PendingKey>>,
+}
+
+impl IdempotencyStore {
+    /// Loads pending keys from `data_dir`, discarding expired ones.
+    pub fn load(data_dir: PathBuf) -> AppResult<Self> {
+        std::fs::create_dir_all(&data_dir)?;
+        let path = data_dir.join(PENDING_FILE_NAME);
+        let mut pending: HashMap<String, PendingKey> = match std::fs::read_to_string(&path) {
+            Ok(raw) => serde_json::from_str(&raw).unwrap_or_else(|e| {
+                println!("⚠️ Ignoring unreadable {}: {}", path.display(), e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        let cutoff = Utc::now() - Duration::hours(KEY_TTL_HOURS);
+        pending.retain(|_, p| p.created_at > cutoff);
+
+        Ok(Self {
+            path,
+            pending: Mutex::new(pending),
+        })
+    }
+
+    /// Fingerprint of an upload: the audio bytes plus its metadata.
+    pub fn fingerprint(audio: &[u8], duration: &str, tags: &[String]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(audio);
+        hasher.update(duration.as_bytes());
+        for tag in tags {
+            hasher.update([0u8]);
+            hasher.update(tag.as_bytes());
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Returns the persisted key for `fingerprint`, creating one if needed.
+    pub fn key_for(&self, fingerprint: &str) -> AppResult<String> {
+        let mut pending = self.pending.lock().unwrap();
+        if let Some(existing) = pending.get(fingerprint) {
+            println!("🔁 Reusing idempotency key {} for pending upload", existing.key);
+            return Ok(existing.key.clone());
+        }
+        let key = Uuid::new_v4().to_string();
+        pending.insert(
+            fingerprint.to_string(),
+            PendingKey {
+                key: key.clone(),
+                created_at: Utc::now(),
+            },
+        );
+        self.persist(&pending)?;
+        Ok(key)
+    }
+
+    /// Forgets the key once the server has confirmed the upload.
+    pub fn confirm(&self, fingerprint: &str) -> AppResult<()> {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.remove(fingerprint).is_some() {
+            self.persist(&pending)?;
+        }
+        Ok(())
+    }
+
+    fn persist(&self, pending: &HashMap<String, PendingKey>) -> AppResult<()> {
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(pending)?)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
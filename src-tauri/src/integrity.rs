@@ -0,0 +1,336 @@
+use crate::audio;
+use crate::audio_cache::AudioCache;
+use crate::audio_store::{AudioStore, GC_GRACE_MINUTES};
+use crate::error::{AppResult, ErrorKind};
+use crate::events::emit_memo_updated;
+use crate::memo_ops;
+use chrono::Duration;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use tauri::{AppHandle, Manager, Runtime};
+
+/// Bit rates outside this range (bits per second) mean the saved duration
+/// cannot belong to the recording. Speech codecs go down to a few kbit/s,
+/// uncompressed stereo WAV up to a few Mbit/s.
+const MIN_BITRATE: u64 = 2_000;
+const MAX_BITRATE: u64 = 6_000_000;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// The memo has no recording, or its file is gone.
+    MissingRecording,
+    /// The file's contents do not match the hash it is stored under.
+    ChecksumMismatch,
+    /// The file cannot be decrypted or is not a known audio format.
+    Undecodable,
+    /// The saved duration does not fit the size of the recording.
+    ImplausibleDuration,
+    /// The saved size or type differ from the recording.
+    WrongMetadata,
+    /// The recording is still kept inside the database row.
+    InlineRecording,
+    /// The memo points at a recording the audio store does not track.
+    DanglingReference,
+    /// A recording's reference count is wrong.
+    RefcountDrift,
+    /// A tracked recording no memo uses.
+    OrphanRecording,
+    /// A file in the audio store that is not tracked.
+    OrphanFile,
+    /// Tags or revisions of a memo that no longer exists.
+    OrphanRows,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct IntegrityIssue {
+    pub kind: IssueKind,
+    pub memo_id: Option<String>,
+    /// Hash of the recording involved.
+    pub hash: Option<String>,
+    pub detail: String,
+    pub repaired: bool,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct IntegrityReport {
+    /// Memos checked, including the trash.
+    pub memos_checked: usize,
+    /// Files found in the audio store.
+    pub recordings_checked: usize,
+    pub issues: Vec<IntegrityIssue>,
+}
+
+impl IntegrityReport {
+    fn push(&mut self, kind: IssueKind, memo_id: Option<&str>, hash: Option<&str>, detail: impl Into<String>) -> &mut IntegrityIssue {
+        self.issues.push(IntegrityIssue {
+            kind,
+            memo_id: memo_id.map(String::from),
+            hash: hash.map(String::from),
+            detail: detail.into(),
+            repaired: false,
+        });
+        self.issues.last_mut().unwrap()
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct MemoAudio {
+    id: String,
+    duration: String,
+    audio_hash: Option<String>,
+    audio_mime: String,
+    audio_size: i64,
+    inline_bytes: i64,
+    remote_id: Option<String>,
+}
+
+fn plausible_duration(duration: &str, bytes: usize) -> bool {
+    match memo_ops::duration_secs(duration) {
+        Some(seconds) => {
+            let bitrate = bytes as u64 * 8 / seconds.max(1);
+            (MIN_BITRATE..=MAX_BITRATE).contains(&bitrate)
+        }
+        None => false,
+    }
+}
+
+/// Checks every memo against its recording and the audio store against the
+/// memos without changing anything.
+pub async fn verify_library<R: Runtime>(app: &AppHandle<R>) -> AppResult<IntegrityReport> {
+    check(app, false).await
+}
+
+/// Like [`verify_library`], then fixes what it can: recordings that are
+/// missing or damaged are restored from the audio cache when it has a copy,
+/// metadata is corrected, reference counts are recounted and orphans are
+/// removed. Issues that remain keep `repaired: false`.
+pub async fn repair_library<R: Runtime>(app: &AppHandle<R>) -> AppResult<IntegrityReport> {
+    let report = check(app, true).await?;
+    let repaired = report.issues.iter().filter(|i| i.repaired).count();
+    println!(
+        "🩺 Library repair fixed {} of {} issue(s)",
+        repaired,
+        report.issues.len()
+    );
+    if repaired > 0 {
+        emit_memo_updated(app);
+    }
+    Ok(report)
+}
+
+async fn check<R: Runtime>(app: &AppHandle<R>, repair: bool) -> AppResult<IntegrityReport> {
+    let pool = app.state::<SqlitePool>();
+    let store = app.state::<AudioStore>();
+    let cache = app.state::<AudioCache>();
+    let mut report = IntegrityReport::default();
+
+    let memos: Vec<MemoAudio> = sqlx::query_as(
+        "SELECT id, duration, audio_hash, audio_mime, audio_size, length(audio_blob) AS inline_bytes, remote_id
+         FROM voice_memos ORDER BY date, id",
+    )
+    .fetch_all(&*pool)
+    .await?;
+    let tracked: HashSet<String> = sqlx::query_scalar("SELECT hash FROM audio_blobs")
+        .fetch_all(&*pool)
+        .await?
+        .into_iter()
+        .collect();
+    report.memos_checked = memos.len();
+
+    let mut inline = false;
+    for memo in &memos {
+        let id = Some(memo.id.as_str());
+        let Some(hash) = memo.audio_hash.as_deref() else {
+            if memo.inline_bytes > 0 {
+                report.push(IssueKind::InlineRecording, id, None, "Recording is stored in the database");
+                inline = true;
+            } else {
+                let repaired = repair && recover(&pool, &store, &cache, memo, None).await?;
+                report.push(IssueKind::MissingRecording, id, None, "Memo has no recording").repaired = repaired;
+            }
+            continue;
+        };
+
+        let recording = match store.read(hash).await {
+            Ok(recording) => recording,
+            Err(e) if e.kind == ErrorKind::NotFound => {
+                let repaired = repair && recover(&pool, &store, &cache, memo, None).await?;
+                report.push(IssueKind::MissingRecording, id, Some(hash), "Recording file is missing").repaired = repaired;
+                continue;
+            }
+            Err(e) if e.kind == ErrorKind::Decode => {
+                let repaired = repair && recover(&pool, &store, &cache, memo, Some(hash)).await?;
+                report.push(IssueKind::Undecodable, id, Some(hash), e.message).repaired = repaired;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        if AudioStore::hash(&recording) != hash {
+            let repaired = repair && recover(&pool, &store, &cache, memo, Some(hash)).await?;
+            report
+                .push(IssueKind::ChecksumMismatch, id, Some(hash), "Recording file is damaged")
+                .repaired = repaired;
+            continue;
+        }
+
+        if !tracked.contains(hash) {
+            let issue = report.push(
+                IssueKind::DanglingReference,
+                id,
+                Some(hash),
+                "Recording is not tracked by the audio store",
+            );
+            if repair {
+                // Storing it again adds the missing row; the count is fixed below.
+                let mut conn = pool.acquire().await?;
+                store.put(&mut conn, &recording).await?;
+                issue.repaired = true;
+            }
+        }
+        let mime = audio::sniff_mime(&recording);
+        if mime == "application/octet-stream" {
+            report.push(IssueKind::Undecodable, id, Some(hash), "Not a recognized audio format");
+        } else if memo.audio_mime != mime || memo.audio_size != recording.len() as i64 {
+            let issue = report.push(
+                IssueKind::WrongMetadata,
+                id,
+                Some(hash),
+                format!(
+                    "Saved as {} ({} bytes) but the recording is {} ({} bytes)",
+                    memo.audio_mime,
+                    memo.audio_size,
+                    mime,
+                    recording.len()
+                ),
+            );
+            if repair {
+                sqlx::query("UPDATE voice_memos SET audio_mime = ?, audio_size = ? WHERE id = ?")
+                    .bind(mime)
+                    .bind(recording.len() as i64)
+                    .bind(&memo.id)
+                    .execute(&*pool)
+                    .await?;
+                issue.repaired = true;
+            }
+        }
+        if !plausible_duration(&memo.duration, recording.len()) {
+            report.push(
+                IssueKind::ImplausibleDuration,
+                id,
+                Some(hash),
+                format!("Duration {} does not fit a {} byte recording", memo.duration, recording.len()),
+            );
+        }
+    }
+    if repair && inline {
+        store.adopt_inline(&pool).await?;
+        for issue in report.issues.iter_mut().filter(|i| i.kind == IssueKind::InlineRecording) {
+            issue.repaired = true;
+        }
+    }
+
+    let orphan_rows: Vec<String> = sqlx::query_scalar(
+        "SELECT memo_id FROM memo_tags WHERE memo_id NOT IN (SELECT id FROM voice_memos)
+         UNION SELECT memo_id FROM memo_revisions WHERE memo_id NOT IN (SELECT id FROM voice_memos)",
+    )
+    .fetch_all(&*pool)
+    .await?;
+    for memo_id in &orphan_rows {
+        report
+            .push(IssueKind::OrphanRows, Some(memo_id), None, "Tags or revisions of a deleted memo")
+            .repaired = repair;
+    }
+    if repair && !orphan_rows.is_empty() {
+        sqlx::query("DELETE FROM memo_tags WHERE memo_id NOT IN (SELECT id FROM voice_memos)")
+            .execute(&*pool)
+            .await?;
+        sqlx::query("DELETE FROM memo_revisions WHERE memo_id NOT IN (SELECT id FROM voice_memos)")
+            .execute(&*pool)
+            .await?;
+    }
+
+    let counts: Vec<(String, i64, i64)> = sqlx::query_as(
+        "SELECT hash, refcount, (SELECT COUNT(*) FROM voice_memos WHERE audio_hash = audio_blobs.hash) AS actual
+         FROM audio_blobs",
+    )
+    .fetch_all(&*pool)
+    .await?;
+    let files = store.stored_hashes().await?;
+    report.recordings_checked = files.len();
+    for (hash, refcount, actual) in &counts {
+        if refcount != actual {
+            report.push(
+                IssueKind::RefcountDrift,
+                None,
+                Some(hash),
+                format!("Counted {} references but {} memos use it", refcount, actual),
+            );
+        }
+        if *actual == 0 {
+            report.push(IssueKind::OrphanRecording, None, Some(hash), "No memo uses this recording");
+        }
+    }
+    let tracked: HashSet<&str> = counts.iter().map(|(hash, _, _)| hash.as_str()).collect();
+    for hash in files.iter().filter(|h| !tracked.contains(h.as_str())) {
+        report.push(IssueKind::OrphanFile, None, Some(hash), "File is not tracked by the audio store");
+    }
+
+    if repair {
+        // Recounts references, then removes orphans outside the grace period
+        // so recordings being saved right now are left alone.
+        store
+            .collect_garbage(&pool, Duration::minutes(GC_GRACE_MINUTES))
+            .await?;
+        let tracked: HashSet<String> = sqlx::query_scalar("SELECT hash FROM audio_blobs")
+            .fetch_all(&*pool)
+            .await?
+            .into_iter()
+            .collect();
+        let files = store.stored_hashes().await?;
+        for issue in &mut report.issues {
+            match issue.kind {
+                IssueKind::RefcountDrift => issue.repaired = true,
+                IssueKind::OrphanRecording | IssueKind::OrphanFile => {
+                    let hash = issue.hash.as_deref().unwrap_or_default();
+                    issue.repaired = !tracked.contains(hash) && !files.contains(hash);
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Restores a memo's recording from the audio cache, where recordings
+/// played from the backend are kept. `damaged` is the file to replace.
+async fn recover(
+    pool: &SqlitePool,
+    store: &AudioStore,
+    cache: &AudioCache,
+    memo: &MemoAudio,
+    damaged: Option<&str>,
+) -> AppResult<bool> {
+    for key in memo.remote_id.iter().chain([&memo.id]) {
+        let Some(recording) = cache.get(key).await else {
+            continue;
+        };
+        if let Some(hash) = damaged {
+            store.remove_file(hash).await?;
+        }
+        let mut tx = pool.begin().await?;
+        let hash = store.put(&mut tx, &recording).await?;
+        sqlx::query("UPDATE voice_memos SET audio_hash = ?, audio_blob = X'', audio_mime = ?, audio_size = ? WHERE id = ?")
+            .bind(&hash)
+            .bind(audio::sniff_mime(&recording))
+            .bind(recording.len() as i64)
+            .bind(&memo.id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        println!("🩹 Restored the recording of memo {} from the audio cache", memo.id);
+        return Ok(true);
+    }
+    Ok(false)
+}
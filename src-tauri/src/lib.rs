@@ -1,24 +1,70 @@
-mod api_key_ops;
-mod events;
-mod memo_ops;
-mod gemini;
+pub mod accounts;
+pub mod api_client;
+pub mod api_key_ops;
+pub mod audio;
+pub mod audio_cache;
+pub mod audio_store;
+pub mod backup;
+pub mod config;
+pub mod events;
+pub mod local_store;
+pub mod memo_ops;
+pub mod memo_store;
+pub mod outbox;
+pub mod retry;
+pub mod revisions;
+pub mod search;
+pub mod session;
+pub mod storage;
+pub mod tags;
+pub mod trash;
+pub mod sync;
+pub mod upload;
+pub mod gemini;
+pub mod idempotency;
+pub mod integrity;
+pub mod vault;
 mod models;
-mod db; 
-mod user_ops; // NEW: Added the user_ops module
+pub mod db;
+pub mod error;
+pub mod user_ops; // NEW: Added the user_ops module
 
 // Corrected 'use' statements
-use tauri::{command, AppHandle, Emitter, Manager};
+use tauri::{command, AppHandle, Emitter, Manager, State};
 use std::env;
+use std::path::Path;
 use tokio::fs;
 use uuid::Uuid;
 
+use accounts::{AccountRegistry, AccountSummary, Profile};
+use api_client::ApiClient;
+use audio_cache::AudioCache;
+use audio_store::{AudioStore, GcReport, GC_GRACE_MINUTES};
+use backup::{ExportReport, ImportOptions, ImportReport};
+use config::AppConfig;
+use error::{AppError, AppResult};
+use idempotency::IdempotencyStore;
+use integrity::IntegrityReport;
+use local_store::LocalMemoStore;
+use memo_store::{MemoUpdate, RemoteMemoStore, SharedMemoStore, StorageBackend};
+use outbox::{Outbox, PendingOperation, ReplayReport};
+use revisions::{Revision, RevisionDiff};
+use search::{SearchQuery, SearchResults};
+use session::{ExpiryReason, SessionInfo, SessionState};
+use sqlx::SqlitePool;
+use storage::StorageStats;
+use tags::TagCount;
+use trash::{TrashConfig, TrashedMemo};
+use std::sync::Arc;
+use sync::{SyncEngine, SyncReport};
+use upload::UploadManager;
+use vault::{Vault, VaultStatus};
+
 // Import the specific functions and the correct VoiceMemo struct from our modules
 use api_key_ops::*;
-use memo_ops::{
-    save_audio, save_memo, get_memos, get_memo, delete_memo, delete_all_memos, VoiceMemo
-};
+use memo_ops::{MemoPage, MemoQuery, MemoSummary, VoiceMemo};
 // NEW: Import user operations and payloads
-use user_ops::{signup, login, SignupPayload, LoginPayload, SignupResponse, LoginResponse};
+use user_ops::{signup, login, SignupPayload, LoginPayload, SignupResponse};
 
 
 // Re-export for clarity
@@ -42,11 +88,83 @@ pub fn run() {
                         .build(),
                 )?;
             }
-            
-            // Spawn an async task to initialize the database if it's needed locally
-            // tauri::async_runtime::spawn(async move {
-            //     db::init_db().await.expect("Failed to initialize local database");
-            // });
+
+            // Each account has its own library, caches and settings; the one
+            // that was open last is opened again
+            let accounts = AccountRegistry::load(
+                app.path().app_data_dir()?,
+                app.path().app_cache_dir()?,
+                app.path().app_config_dir().ok(),
+            )?;
+            let profile = accounts.active_profile();
+            println!("👥 Opening profile {}", profile.data_dir.display());
+            app.manage(accounts);
+            app.manage(profile.clone());
+
+            // Load backend settings (config file + env) and share one HTTP client
+            let config = AppConfig::load(profile.settings_dir())?;
+            let mut api_client = ApiClient::new(&config.api)?;
+            let handle = app.handle().clone();
+            api_client.set_waking_listener(move |status| events::emit_backend_waking(&handle, status));
+            let handle = app.handle().clone();
+            api_client.set_unauthorized_listener(move |token| {
+                session::expire_session(&handle, token, ExpiryReason::Rejected)
+            });
+            println!("🌐 Using backend at {}", api_client.base_url());
+            app.manage(api_client);
+
+            // Idempotency keys for uploads the server has not confirmed yet
+            let data_dir = profile.data_dir.clone();
+            app.manage(IdempotencyStore::load(data_dir.clone())?);
+
+            // Backend token of the signed-in user, kept out of the webview
+            app.manage(SessionState::load(data_dir.clone())?);
+            tauri::async_runtime::spawn(session::watch_expiry(app.handle().clone()));
+
+            // Resumable chunked uploads for long recordings
+            app.manage(UploadManager::new(data_dir.clone(), config.upload.clone())?);
+
+            // Optional passphrase encrypting the local library; starts out locked
+            let vault = Vault::load(data_dir.clone())?;
+            app.manage(vault.clone());
+
+            // Recordings fetched on demand by get_memo_audio
+            app.manage(AudioCache::new(profile.cache_dir.clone())?.with_vault(vault.clone()));
+
+            // Local database: memos stay available while the backend is unreachable
+            let pool = tauri::async_runtime::block_on(db::open_with_vault(&vault))?;
+            app.manage(pool.clone());
+            app.manage(SyncEngine::new(pool.clone(), config.sync.clone()));
+            app.manage(Outbox::new(pool.clone()));
+
+            // Trashed memos are purged once they are past the retention period
+            app.manage(config.trash.clone());
+            app.manage(config.quota.clone());
+            tauri::async_runtime::spawn(trash::run_purger(app.handle().clone(), pool.clone(), config.trash.clone()));
+
+            // Recordings of local memos, stored once per distinct content
+            let audio_store = AudioStore::new(data_dir)?.with_vault(vault.clone());
+            if !vault.status().locked {
+                tauri::async_runtime::block_on(audio_store.adopt_inline(&pool))?;
+            }
+            app.manage(audio_store);
+            let handle = app.handle().clone();
+            let gc_pool = pool.clone();
+            tauri::async_runtime::spawn(async move {
+                let grace = chrono::Duration::minutes(GC_GRACE_MINUTES);
+                if let Err(e) = handle.state::<AudioStore>().collect_garbage(&gc_pool, grace).await {
+                    println!("❌ Audio garbage collection failed: {}", e.message);
+                }
+            });
+
+            // Memo storage used by the memo commands
+            let store: SharedMemoStore = match config.storage.backend {
+                StorageBackend::Remote => Arc::new(RemoteMemoStore::new(app.handle().clone())),
+                StorageBackend::Local => Arc::new(LocalMemoStore::new(app.handle().clone(), pool)),
+            };
+            println!("🗂️ Memo storage: {:?}", config.storage.backend);
+            app.manage(store);
+            app.manage(config.storage.backend);
 
             println!("App setup completed successfully");
             Ok(())
@@ -55,14 +173,49 @@ pub fn run() {
             // User Auth Commands
             signup_command,
             login_command,
+            logout_command,
+            current_session_command,
+            list_accounts_command,
+            switch_account_command,
 
             // Memo Commands
             save_audio_command,
+            resume_uploads_command,
             save_memo_command,
             get_memos_command,
+            list_memos_command,
             get_memo_command,
+            get_memo_audio_command,
             delete_memo_command,
             clear_all_memos,
+            list_trash_command,
+            restore_memo_command,
+            empty_trash_command,
+            list_revisions_command,
+            diff_revisions_command,
+            revert_memo_command,
+            vault_status_command,
+            set_passphrase_command,
+            change_passphrase_command,
+            remove_passphrase_command,
+            lock_library_command,
+            unlock_library_command,
+            export_library_command,
+            import_library_command,
+            storage_stats_command,
+            verify_library_command,
+            repair_library_command,
+            search_memos_command,
+            list_tags_command,
+            rename_tag_command,
+            merge_tags_command,
+            delete_tag_command,
+            normalize_tags_command,
+            sync_memos_command,
+            list_pending_operations_command,
+            retry_pending_operations_command,
+            discard_pending_operation_command,
+            collect_audio_garbage_command,
 
             // AI Commands
             transcribe_audio_command,
@@ -89,63 +242,310 @@ pub fn run() {
 
 // NEW: User Auth Commands
 #[command]
-async fn signup_command(username: String, email: String, password: String) -> Result<SignupResponse, String> {
+async fn signup_command(api: State<'_, ApiClient>, username: String, email: String, password: String) -> AppResult<SignupResponse> {
     let payload = SignupPayload { username, email, password };
-    signup(payload).await.map_err(|e| e.to_string())
+    signup(&api, payload).await
+}
+
+#[command]
+async fn login_command(
+    app: AppHandle,
+    api: State<'_, ApiClient>,
+    accounts: State<'_, AccountRegistry>,
+    session: State<'_, SessionState>,
+    email: String,
+    password: String,
+) -> AppResult<SessionInfo> {
+    let payload = LoginPayload { email: email.clone(), password };
+    let response = login(&api, payload).await?;
+    let id = accounts.remember(&SessionInfo::from_token(&response.token, &email))?;
+    if accounts.active_id().as_deref() == Some(id.as_str()) {
+        let info = session.start(response.token, &email)?;
+        events::emit_session_changed(&app, Some(info.clone()));
+        return Ok(info);
+    }
+
+    // Another account: store its session in its own profile and reopen the app there
+    SessionState::load(accounts.profile(Some(&id)).data_dir)?.start(response.token, &email)?;
+    accounts.set_active(&id)?;
+    println!("👥 Restarting into the account of {}", email);
+    app.restart()
+}
+
+#[command]
+async fn logout_command(app: AppHandle, session: State<'_, SessionState>) -> AppResult<()> {
+    session.end()?;
+    events::emit_session_changed(&app, None);
+    Ok(())
+}
+
+#[command]
+fn current_session_command(session: State<'_, SessionState>) -> Option<SessionInfo> {
+    session.current()
+}
+
+#[command]
+fn list_accounts_command(accounts: State<'_, AccountRegistry>) -> Vec<AccountSummary> {
+    accounts.list()
+}
+
+// Accounts never share an open library, so switching restarts the app into the other profile
+#[command]
+fn switch_account_command(app: AppHandle, accounts: State<'_, AccountRegistry>, id: String) -> AppResult<()> {
+    if accounts.active_id().as_deref() == Some(id.as_str()) {
+        return Ok(());
+    }
+    accounts.set_active(&id)?;
+    println!("👥 Switching account, restarting...");
+    app.restart()
+}
+
+
+#[command]
+async fn save_audio_command(app: AppHandle, store: State<'_, SharedMemoStore>, session: State<'_, SessionState>, audio_blob: Vec<u8>, duration: String, tags: Vec<String>) -> AppResult<VoiceMemo> {
+    let token = session.token()?;
+    storage::check_quota(&app, audio_blob.len() as u64).await?;
+    store.create(&token, audio_blob, duration, tags).await
+}
+
+#[command]
+async fn resume_uploads_command(store: State<'_, SharedMemoStore>, session: State<'_, SessionState>) -> AppResult<Vec<VoiceMemo>> {
+    let token = session.token()?;
+    store.resume_uploads(&token).await
+}
+
+#[command]
+#[allow(clippy::too_many_arguments)]
+async fn save_memo_command(store: State<'_, SharedMemoStore>, session: State<'_, SessionState>, id: String, name: String, transcription: Option<String>, translate: Option<String>, summary: Option<String>, tags: Option<Vec<String>>) -> AppResult<VoiceMemo> {
+    let token = session.token()?;
+    let update = MemoUpdate { title: name, transcript: transcription, translate, summary, tags };
+    store.update(&token, &id, update).await
+}
+
+#[command]
+async fn get_memos_command(store: State<'_, SharedMemoStore>, session: State<'_, SessionState>, query: Option<MemoQuery>) -> AppResult<MemoPage<VoiceMemo>> {
+    let token = session.token()?;
+    store.list(&token, &query.unwrap_or_default()).await
+}
+
+#[command]
+async fn list_memos_command(store: State<'_, SharedMemoStore>, session: State<'_, SessionState>, query: Option<MemoQuery>) -> AppResult<MemoPage<MemoSummary>> {
+    let token = session.token()?;
+    store.list_summaries(&token, &query.unwrap_or_default()).await
+}
+
+#[command]
+async fn get_memo_audio_command(store: State<'_, SharedMemoStore>, session: State<'_, SessionState>, id: String) -> AppResult<Vec<u8>> {
+    let token = session.token()?;
+    store.audio(&token, &id).await
+}
+
+#[command]
+async fn get_memo_command(store: State<'_, SharedMemoStore>, session: State<'_, SessionState>, id: String) -> AppResult<Option<VoiceMemo>> {
+    let token = session.token()?;
+    store.get(&token, &id).await
+}
+
+#[command]
+async fn delete_memo_command(store: State<'_, SharedMemoStore>, session: State<'_, SessionState>, id: String) -> AppResult<()> {
+    let token = session.token()?;
+    store.delete(&token, &id).await
+}
+
+#[command]
+async fn clear_all_memos(store: State<'_, SharedMemoStore>, session: State<'_, SessionState>) -> AppResult<String> {
+    let token = session.token()?;
+    store.delete_all(&token).await
+}
+
+// Trash commands work on the local database
+#[command]
+async fn list_trash_command(pool: State<'_, SqlitePool>, config: State<'_, TrashConfig>) -> AppResult<Vec<TrashedMemo>> {
+    trash::list_trash(&pool, &config).await
+}
+
+#[command]
+async fn restore_memo_command(app: AppHandle, pool: State<'_, SqlitePool>, id: String) -> AppResult<()> {
+    trash::restore_memo(&app, &pool, &id).await
 }
 
+// Permanently deletes everything in the trash and returns how many memos went
 #[command]
-async fn login_command(email: String, password: String) -> Result<LoginResponse, String> {
-    let payload = LoginPayload { email, password };
-    login(payload).await.map_err(|e| e.to_string())
+async fn empty_trash_command(app: AppHandle, pool: State<'_, SqlitePool>) -> AppResult<usize> {
+    trash::empty_trash(&app, &pool).await
 }
 
+// Revision history is kept in the local database, newest revision first; remote storage records none
+#[command]
+async fn list_revisions_command(backend: State<'_, StorageBackend>, pool: State<'_, SqlitePool>, id: String) -> AppResult<Vec<Revision>> {
+    backend.require_local("Revision history")?;
+    revisions::list_revisions(&pool, &id).await
+}
+
+#[command]
+async fn diff_revisions_command(backend: State<'_, StorageBackend>, pool: State<'_, SqlitePool>, id: String, from: i64, to: i64) -> AppResult<RevisionDiff> {
+    backend.require_local("Revision history")?;
+    revisions::diff_revisions(&pool, &id, from, to).await
+}
+
+#[command]
+async fn revert_memo_command(app: AppHandle, backend: State<'_, StorageBackend>, pool: State<'_, SqlitePool>, id: String, revision: i64) -> AppResult<Revision> {
+    backend.require_local("Revision history")?;
+    revisions::revert_to_revision(&app, &pool, &id, revision).await
+}
+
+// Passphrase protection of the local library (database and recordings)
+#[command]
+fn vault_status_command(vault: State<'_, Vault>) -> VaultStatus {
+    vault.status()
+}
+
+#[command]
+async fn set_passphrase_command(app: AppHandle, vault: State<'_, Vault>, passphrase: String) -> AppResult<()> {
+    vault.set_passphrase(&app, &passphrase).await
+}
+
+#[command]
+async fn change_passphrase_command(
+    app: AppHandle,
+    vault: State<'_, Vault>,
+    current_passphrase: String,
+    new_passphrase: String,
+) -> AppResult<()> {
+    vault.change_passphrase(&app, &current_passphrase, &new_passphrase).await
+}
+
+#[command]
+async fn remove_passphrase_command(app: AppHandle, vault: State<'_, Vault>, passphrase: String) -> AppResult<()> {
+    vault.remove_passphrase(&app, &passphrase).await
+}
+
+#[command]
+async fn lock_library_command(app: AppHandle, vault: State<'_, Vault>) -> AppResult<()> {
+    vault.lock(&app).await
+}
+
+#[command]
+async fn unlock_library_command(app: AppHandle, vault: State<'_, Vault>, passphrase: String) -> AppResult<()> {
+    vault.unlock(&app, &passphrase).await
+}
+
+// Backup archives of the local library; the settings file is the account's config.json
+#[command]
+async fn export_library_command(app: AppHandle, pool: State<'_, SqlitePool>, profile: State<'_, Profile>, path: String) -> AppResult<ExportReport> {
+    let settings = profile.settings_file()?;
+    backup::export_library(&app, &pool, Path::new(&path), &settings).await
+}
+
+#[command]
+async fn import_library_command(
+    app: AppHandle,
+    pool: State<'_, SqlitePool>,
+    profile: State<'_, Profile>,
+    path: String,
+    options: Option<ImportOptions>,
+) -> AppResult<ImportReport> {
+    let settings = profile.own_settings_file()?;
+    let options = options.unwrap_or_default();
+    backup::import_library(&app, &pool, Path::new(&path), &options, &settings).await
+}
+
+// Space used by the local library, caches and pending uploads, with the quota
+#[command]
+async fn storage_stats_command(app: AppHandle) -> AppResult<StorageStats> {
+    storage::storage_stats(&app).await
+}
+
+// Checks memos against their recordings; repair fixes what it can and reports the rest
+#[command]
+async fn verify_library_command(app: AppHandle) -> AppResult<IntegrityReport> {
+    integrity::verify_library(&app).await
+}
+
+#[command]
+async fn repair_library_command(app: AppHandle) -> AppResult<IntegrityReport> {
+    integrity::repair_library(&app).await
+}
+
+// Searches the local database, so it also works offline; remote storage has nothing indexed there
+#[command]
+async fn search_memos_command(backend: State<'_, StorageBackend>, pool: State<'_, SqlitePool>, query: SearchQuery) -> AppResult<SearchResults> {
+    backend.require_local("Search")?;
+    search::search_memos(&pool, &query).await
+}
+
+// Tag commands work on the local database and return the number of memos changed; remote storage keeps its own tags
+#[command]
+async fn list_tags_command(backend: State<'_, StorageBackend>, pool: State<'_, SqlitePool>) -> AppResult<Vec<TagCount>> {
+    backend.require_local("Tags")?;
+    tags::list_tags(&pool).await
+}
+
+#[command]
+async fn rename_tag_command(app: AppHandle, backend: State<'_, StorageBackend>, pool: State<'_, SqlitePool>, from: String, to: String) -> AppResult<usize> {
+    backend.require_local("Tags")?;
+    tags::rename_tag(&app, &pool, &from, &to).await
+}
+
+#[command]
+async fn merge_tags_command(app: AppHandle, backend: State<'_, StorageBackend>, pool: State<'_, SqlitePool>, sources: Vec<String>, target: String) -> AppResult<usize> {
+    backend.require_local("Tags")?;
+    tags::merge_tags(&app, &pool, &sources, &target).await
+}
+
+#[command]
+async fn delete_tag_command(app: AppHandle, backend: State<'_, StorageBackend>, pool: State<'_, SqlitePool>, tag: String) -> AppResult<usize> {
+    backend.require_local("Tags")?;
+    tags::delete_tag(&app, &pool, &tag).await
+}
 
 #[command]
-async fn save_audio_command(app: AppHandle, token: String, audio_blob: Vec<u8>, duration: String, tags: Vec<String>) -> Result<VoiceMemo, String> {
-    save_audio(&app, &token, audio_blob, duration, tags).await.map_err(|e| e.to_string())
+async fn normalize_tags_command(app: AppHandle, backend: State<'_, StorageBackend>, pool: State<'_, SqlitePool>) -> AppResult<usize> {
+    backend.require_local("Tags")?;
+    tags::normalize_all_tags(&app, &pool).await
 }
 
 #[command]
-async fn save_memo_command(app: AppHandle, token: String, id: String, name: String, transcription: Option<String>, translate: Option<String>, summary: Option<String>, tags: Option<Vec<String>>) -> Result<VoiceMemo, String> {
-    save_memo(&app, &token, &id, &name, transcription, translate, summary, tags).await.map_err(|e| e.to_string())
+async fn sync_memos_command(app: AppHandle, engine: State<'_, SyncEngine>, session: State<'_, SessionState>) -> AppResult<SyncReport> {
+    let token = session.token()?;
+    engine.sync(&app, &token).await
 }
 
 #[command]
-async fn get_memos_command(token: String) -> Result<Vec<VoiceMemo>, String> {
-    get_memos(&token).await.map_err(|e| e.to_string())
+async fn list_pending_operations_command(outbox: State<'_, Outbox>) -> AppResult<Vec<PendingOperation>> {
+    outbox.list().await
 }
 
+// Retries one failed operation, or everything when no id is given
 #[command]
-async fn get_memo_command(token: String, id: String) -> Result<Option<VoiceMemo>, String> {
-    get_memo(&id, &token).await.map_err(|e| e.to_string())
+async fn retry_pending_operations_command(app: AppHandle, outbox: State<'_, Outbox>, session: State<'_, SessionState>, id: Option<i64>) -> AppResult<ReplayReport> {
+    let token = session.token()?;
+    outbox.reset_failed(id).await?;
+    outbox.replay(&app, &token).await
 }
 
 #[command]
-async fn delete_memo_command(app: AppHandle, token: String, id: String) -> Result<(), String> {
-    delete_memo(&app, &id, &token).await.map_err(|e| e.to_string())
+async fn discard_pending_operation_command(outbox: State<'_, Outbox>, id: i64) -> AppResult<()> {
+    outbox.discard(id).await
 }
 
 #[command]
-async fn clear_all_memos(app: AppHandle, token: String) -> Result<String, String> {
-    delete_all_memos(&app, &token).await.map_err(|e| e.to_string())
+async fn collect_audio_garbage_command(pool: State<'_, SqlitePool>, audio: State<'_, AudioStore>) -> AppResult<GcReport> {
+    audio.collect_garbage(&pool, chrono::Duration::minutes(GC_GRACE_MINUTES)).await
 }
 
 #[command]
-async fn transcribe_audio_command(token: String, audio_blob: Vec<u8>) -> Result<String, String> {
+async fn transcribe_audio_command(api: State<'_, ApiClient>, session: State<'_, SessionState>, audio_blob: Vec<u8>) -> AppResult<String> {
+    let token = session.token()?;
     let temp_dir = env::temp_dir();
     let file_path = temp_dir.join(format!("{}.tmp", Uuid::new_v4()));
     
-    fs::write(&file_path, audio_blob)
-        .await
-        .map_err(|e| e.to_string())?;
+    fs::write(&file_path, audio_blob).await?;
     
     let file_path_str = file_path.to_str()
-        .ok_or("Invalid temporary file path".to_string())?;
+        .ok_or_else(|| AppError::internal("Invalid temporary file path"))?;
     
-    let result = transcribe_audio(file_path_str, &token)
-        .await
-        .map_err(|e| e.to_string());
+    let result = transcribe_audio(&api, file_path_str, &token).await;
     
     let _ = fs::remove_file(file_path).await;
     
@@ -153,38 +553,45 @@ async fn transcribe_audio_command(token: String, audio_blob: Vec<u8>) -> Result<
 }
 
 #[command]
-async fn translate_text_command(token: String, text: String, target_language: String) -> Result<String, String> {
-    translate_text(&text, &target_language, &token).await.map_err(|e| e.to_string())
+async fn translate_text_command(api: State<'_, ApiClient>, session: State<'_, SessionState>, text: String, target_language: String) -> AppResult<String> {
+    let token = session.token()?;
+    translate_text(&api, &text, &target_language, &token).await
 }
 
 #[command]
-async fn summarize_text_command(token: String, text: String) -> Result<String, String> {
-    summarize_text(&text, &token).await.map_err(|e| e.to_string())
+async fn summarize_text_command(api: State<'_, ApiClient>, session: State<'_, SessionState>, text: String) -> AppResult<String> {
+    let token = session.token()?;
+    summarize_text(&api, &text, &token).await
 }
 
 #[command]
-async fn generate_memo_name_command(token: String, transcription: String) -> Result<String, String> {
-    generate_memo_name(&transcription, &token).await.map_err(|e| e.to_string())
+async fn generate_memo_name_command(api: State<'_, ApiClient>, session: State<'_, SessionState>, transcription: String) -> AppResult<String> {
+    let token = session.token()?;
+    generate_memo_name(&api, &transcription, &token).await
 }
 
 #[command]
-async fn save_api_key_command(token: String, gemini_key: String) -> Result<(), String> {
-    save_api_key(&token, &gemini_key).await.map_err(|e| e.to_string())
+async fn save_api_key_command(api: State<'_, ApiClient>, session: State<'_, SessionState>, gemini_key: String) -> AppResult<()> {
+    let token = session.token()?;
+    save_api_key(&api, &token, &gemini_key).await
 }
 
 #[command]
-async fn get_api_key_command(token: String) -> Result<Option<String>, String> {
-    get_api_key(&token).await.map_err(|e| e.to_string())
+async fn get_api_key_command(api: State<'_, ApiClient>, session: State<'_, SessionState>) -> AppResult<Option<String>> {
+    let token = session.token()?;
+    get_api_key(&api, &token).await
 }
 
 #[command]
-async fn delete_gemini_api_key_command(token: String) -> Result<(), String> {
-    delete_gemini_api_key(&token).await.map_err(|e| e.to_string())
+async fn delete_gemini_api_key_command(api: State<'_, ApiClient>, session: State<'_, SessionState>) -> AppResult<()> {
+    let token = session.token()?;
+    delete_gemini_api_key(&api, &token).await
 }
 
 #[command]
-async fn delete_elevenlabs_api_key_command(token: String) -> Result<(), String> {
-    delete_elevenlabs_api_key(&token).await.map_err(|e| e.to_string())
+async fn delete_elevenlabs_api_key_command(api: State<'_, ApiClient>, session: State<'_, SessionState>) -> AppResult<()> {
+    let token = session.token()?;
+    delete_elevenlabs_api_key(&api, &token).await
 }
 
 // --- Helper Window and Test Commands ---
@@ -192,33 +599,36 @@ async fn delete_elevenlabs_api_key_command(token: String) -> Result<(), String>
 #[command]
 async fn toggle_helper_window_command(
     app: AppHandle,
-    token: String,
+    api: State<'_, ApiClient>,
+    session: State<'_, SessionState>,
     enabled: bool,
-) -> Result<(), String> {
+) -> AppResult<()> {
+    let token = session.token()?;
     // This function now calls the API to update the state
-    update_helper_app_state(&token, enabled).await.map_err(|e| e.to_string())?;
+    update_helper_app_state(&api, &token, enabled).await?;
     
     if let Some(helper_window) = app.get_webview_window("helper") {
         if enabled {
-            helper_window.show().map_err(|e| e.to_string())?;
-            helper_window.set_focus().map_err(|e| e.to_string())?;
+            helper_window.show()?;
+            helper_window.set_focus()?;
         } else {
-            helper_window.hide().map_err(|e| e.to_string())?;
+            helper_window.hide()?;
         }
     } else {
-        return Err("Helper window not found".to_string());
+        return Err(AppError::not_found("Helper window not found"));
     }
     
     Ok(())
 }
 
 #[command]
-async fn get_helper_window_state_command(app: AppHandle, token: String) -> Result<bool, String> {
+async fn get_helper_window_state_command(app: AppHandle, api: State<'_, ApiClient>, session: State<'_, SessionState>) -> AppResult<bool> {
+    let token = session.token()?;
     // This function now calls the API to get the state
-    let db_state = get_helper_app_state(&token).await.map_err(|e| e.to_string())?;
+    let db_state = get_helper_app_state(&api, &token).await?;
     
     if let Some(helper_window) = app.get_webview_window("helper") {
-        let is_visible = helper_window.is_visible().map_err(|e| e.to_string())?;
+        let is_visible = helper_window.is_visible()?;
         Ok(db_state && is_visible)
     } else {
         Ok(false)
@@ -226,7 +636,7 @@ async fn get_helper_window_state_command(app: AppHandle, token: String) -> Resul
 }
 
 #[command]
-fn test_event_emission(app_handle: tauri::AppHandle) -> Result<String, String> {
+fn test_event_emission(app_handle: tauri::AppHandle) -> AppResult<String> {
     println!("🧪 Testing event emission...");
     match app_handle.emit("memo:updated", "test_payload") {
         Ok(_) => {
@@ -235,7 +645,7 @@ fn test_event_emission(app_handle: tauri::AppHandle) -> Result<String, String> {
         }
         Err(e) => {
             println!("❌ Failed to emit test event: {:?}", e);
-            Err(format!("Failed to emit event: {:?}", e))
+            Err(AppError::internal(format!("Failed to emit event: {:?}", e)))
         }
     }
 }
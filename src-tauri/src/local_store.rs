@@ -0,0 +1,380 @@
+use crate::audio;
+use crate::audio_store::AudioStore;
+use crate::error::{AppError, AppResult};
+use crate::events::emit_memo_updated;
+use crate::memo_ops::{
+    self, MemoCursor, MemoPage, MemoQuery, MemoSortField, MemoSummary, SortDirection, SortValue, VoiceMemo,
+    DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE,
+};
+use crate::memo_store::{MemoStore, MemoUpdate};
+use crate::revisions::{self, RevisionSource};
+use crate::tags::normalize_tags;
+use crate::trash;
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager, Runtime};
+use uuid::Uuid;
+
+/// Columns of `voice_memos` under the names `VoiceMemo` uses.
+const MEMO_COLUMNS: &str =
+    "id, name AS title, transcription AS transcript, translate, summary, duration, duration_secs, date AS created_at, updated_at";
+
+#[derive(sqlx::FromRow)]
+struct MemoRow {
+    id: String,
+    title: String,
+    transcript: Option<String>,
+    translate: Option<String>,
+    summary: Option<String>,
+    duration: String,
+    duration_secs: i64,
+    created_at: String,
+    updated_at: Option<String>,
+}
+
+impl MemoRow {
+    fn sort_value(&self, field: MemoSortField) -> SortValue {
+        match field {
+            MemoSortField::CreatedAt => SortValue::Text(self.created_at.clone()),
+            MemoSortField::Title => SortValue::Text(self.title.clone()),
+            MemoSortField::Duration => SortValue::Number(self.duration_secs),
+        }
+    }
+
+    fn into_memo(self, tags: Vec<String>, audio: Option<Vec<u8>>) -> VoiceMemo {
+        VoiceMemo {
+            id: self.id,
+            title: self.title,
+            transcript: self.transcript,
+            translate: self.translate,
+            summary: self.summary,
+            tags: Some(tags),
+            duration: self.duration,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            audio_blob: audio,
+        }
+    }
+}
+
+/// Timestamps are stored as UTC RFC 3339 with a fixed precision so they
+/// sort correctly as text.
+pub(crate) fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Column of `voice_memos` a listing sorted by `field` is ordered on.
+fn sort_column(field: MemoSortField) -> &'static str {
+    match field {
+        MemoSortField::CreatedAt => "date",
+        MemoSortField::Title => "name",
+        MemoSortField::Duration => "duration_secs",
+    }
+}
+
+fn parse_bound(name: &str, value: &str) -> AppResult<String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|at| timestamp(at.with_timezone(&Utc)))
+        .map_err(|_| AppError::validation(format!("{} is not an RFC 3339 timestamp: {}", name, value)))
+}
+
+/// Tags of the given memos, in the order they were saved.
+pub(crate) async fn tags_for(pool: &SqlitePool, ids: &[&str]) -> AppResult<HashMap<String, Vec<String>>> {
+    let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+    if ids.is_empty() {
+        return Ok(tags);
+    }
+    let mut qb = QueryBuilder::<Sqlite>::new("SELECT memo_id, tag FROM memo_tags WHERE memo_id IN (");
+    let mut separated = qb.separated(", ");
+    for id in ids {
+        separated.push_bind(*id);
+    }
+    qb.push(") ORDER BY memo_id, position");
+
+    let rows: Vec<(String, String)> = qb.build_query_as().fetch_all(pool).await?;
+    for (memo_id, tag) in rows {
+        tags.entry(memo_id).or_default().push(tag);
+    }
+    Ok(tags)
+}
+
+/// Replaces a memo's tags with their normalized form, keeping their order.
+pub(crate) async fn replace_tags(tx: &mut sqlx::Transaction<'_, Sqlite>, id: &str, tags: &[String]) -> AppResult<()> {
+    let tags = normalize_tags(tags);
+    sqlx::query("DELETE FROM memo_tags WHERE memo_id = ?")
+        .bind(id)
+        .execute(&mut **tx)
+        .await?;
+    for (position, tag) in tags.iter().enumerate() {
+        sqlx::query("INSERT INTO memo_tags (memo_id, position, tag) VALUES (?, ?, ?)")
+            .bind(id)
+            .bind(position as i64)
+            .bind(tag)
+            .execute(&mut **tx)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Loads one memo including its recording. Memos in the trash are not found.
+pub(crate) async fn load_memo(pool: &SqlitePool, audio: &AudioStore, id: &str) -> AppResult<Option<VoiceMemo>> {
+    let row: Option<MemoRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM voice_memos WHERE id = ? AND deleted_at IS NULL",
+        MEMO_COLUMNS
+    ))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let recording = load_audio(pool, audio, id).await?;
+    let tags = tags_for(pool, &[id]).await?.remove(id).unwrap_or_default();
+    Ok(Some(row.into_memo(tags, Some(recording))))
+}
+
+/// Loads several memos without their recordings, keyed by id. Includes
+/// memos in the trash.
+pub(crate) async fn load_memos(pool: &SqlitePool, ids: &[&str]) -> AppResult<HashMap<String, VoiceMemo>> {
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut qb = QueryBuilder::<Sqlite>::new(format!("SELECT {} FROM voice_memos WHERE id IN (", MEMO_COLUMNS));
+    let mut separated = qb.separated(", ");
+    for id in ids {
+        separated.push_bind(*id);
+    }
+    qb.push(")");
+    let rows: Vec<MemoRow> = qb.build_query_as().fetch_all(pool).await?;
+    let mut tags = tags_for(pool, ids).await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let memo_tags = tags.remove(&row.id).unwrap_or_default();
+            (row.id.clone(), row.into_memo(memo_tags, None))
+        })
+        .collect())
+}
+
+/// Reads the recording of one memo from the audio store. Memos in the
+/// trash are not found.
+pub(crate) async fn load_audio(pool: &SqlitePool, audio: &AudioStore, id: &str) -> AppResult<Vec<u8>> {
+    let hash: Option<String> = sqlx::query_scalar("SELECT audio_hash FROM voice_memos WHERE id = ? AND deleted_at IS NULL")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::not_found(format!("Memo {} not found", id)))?;
+    match hash {
+        Some(hash) => audio.read(&hash).await,
+        None => Err(AppError::not_found(format!("Memo {} has no recording", id))),
+    }
+}
+
+/// [`MemoStore`] kept in the local database (see [`crate::db`]), so memos
+/// can be listed, played and edited without the backend. The token is not
+/// needed: the database only holds the signed-in user's memos. Recordings
+/// live in the app's managed [`AudioStore`].
+pub struct LocalMemoStore<R: Runtime> {
+    app: AppHandle<R>,
+    pool: SqlitePool,
+}
+
+impl<R: Runtime> LocalMemoStore<R> {
+    pub fn new(app: AppHandle<R>, pool: SqlitePool) -> Self {
+        Self { app, pool }
+    }
+
+    async fn fetch_page(&self, query: &MemoQuery) -> AppResult<MemoPage<VoiceMemo>> {
+        let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+        if page_size == 0 || page_size > MAX_PAGE_SIZE {
+            return Err(AppError::validation(format!(
+                "page_size must be between 1 and {}",
+                MAX_PAGE_SIZE
+            )));
+        }
+        let mut qb = QueryBuilder::<Sqlite>::new(format!("SELECT {} FROM voice_memos WHERE deleted_at IS NULL", MEMO_COLUMNS));
+        if let Some(tags) = query.tags.as_ref().filter(|t| !t.is_empty()) {
+            let mut wanted = normalize_tags(tags);
+            wanted.sort();
+            qb.push(" AND id IN (SELECT memo_id FROM memo_tags WHERE tag IN (");
+            let mut separated = qb.separated(", ");
+            for tag in &wanted {
+                separated.push_bind(tag.clone());
+            }
+            qb.push(") GROUP BY memo_id HAVING COUNT(DISTINCT tag) = ")
+                .push_bind(wanted.len() as i64)
+                .push(")");
+        }
+        if let Some(from) = &query.created_from {
+            qb.push(" AND date >= ").push_bind(parse_bound("from", from)?);
+        }
+        if let Some(to) = &query.created_to {
+            qb.push(" AND date <= ").push_bind(parse_bound("to", to)?);
+        }
+        match query.has_transcript {
+            Some(true) => qb.push(" AND COALESCE(TRIM(transcription), '') <> ''"),
+            Some(false) => qb.push(" AND COALESCE(TRIM(transcription), '') = ''"),
+            None => &mut qb,
+        };
+        let column = sort_column(query.sort_by);
+        if let Some(raw) = &query.cursor {
+            let cursor = MemoCursor::decode(raw)?;
+            let op = match query.sort_dir {
+                SortDirection::Asc => ">",
+                SortDirection::Desc => "<",
+            };
+            qb.push(format!(" AND ({}, id) {} (", column, op));
+            match (query.sort_by, cursor.value) {
+                (MemoSortField::Duration, SortValue::Number(secs)) => qb.push_bind(secs),
+                (MemoSortField::CreatedAt | MemoSortField::Title, SortValue::Text(text)) => qb.push_bind(text),
+                _ => return Err(AppError::validation(format!("Invalid cursor: {}", raw))),
+            };
+            qb.push(", ").push_bind(cursor.id).push(")");
+        }
+        let dir = query.sort_dir.as_str();
+        qb.push(format!(" ORDER BY {} {}, id {}", column, dir, dir));
+        // One extra row tells us whether there is a next page.
+        qb.push(" LIMIT ").push_bind(page_size as i64 + 1);
+
+        let mut rows: Vec<MemoRow> = qb.build_query_as().fetch_all(&self.pool).await?;
+        let next_cursor = if rows.len() > page_size as usize {
+            rows.truncate(page_size as usize);
+            rows.last().map(|last| {
+                MemoCursor {
+                    value: last.sort_value(query.sort_by),
+                    id: last.id.clone(),
+                }
+                .encode()
+            })
+        } else {
+            None
+        };
+
+        let ids: Vec<&str> = rows.iter().map(|r| r.id.as_str()).collect();
+        let mut tags = tags_for(&self.pool, &ids).await?;
+        let items = rows
+            .into_iter()
+            .map(|row| {
+                let row_tags = tags.remove(&row.id).unwrap_or_default();
+                row.into_memo(row_tags, None)
+            })
+            .collect();
+        Ok(MemoPage { items, next_cursor })
+    }
+}
+
+#[async_trait]
+impl<R: Runtime> MemoStore for LocalMemoStore<R> {
+    async fn create(&self, _token: &str, audio: Vec<u8>, duration: String, tags: Vec<String>) -> AppResult<VoiceMemo> {
+        if audio.is_empty() {
+            return Err(AppError::validation("No audio data provided"));
+        }
+        let id = Uuid::new_v4().to_string();
+        let now = timestamp(Utc::now());
+
+        let store = self.app.state::<AudioStore>();
+        let mut tx = self.pool.begin().await?;
+        let hash = store.put(&mut tx, &audio).await?;
+        sqlx::query(
+            "INSERT INTO voice_memos (id, name, date, duration, audio_blob, audio_hash, audio_mime, audio_size, updated_at)
+             VALUES (?, ?, ?, ?, X'', ?, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(memo_ops::default_title())
+        .bind(&now)
+        .bind(&duration)
+        .bind(&hash)
+        .bind(audio::sniff_mime(&audio))
+        .bind(audio.len() as i64)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+        replace_tags(&mut tx, &id, &tags).await?;
+        revisions::record(&mut tx, &id, RevisionSource::Created).await?;
+        tx.commit().await?;
+        println!("💾 New memo stored locally with ID: {}", id);
+
+        let memo = load_memo(&self.pool, &self.app.state::<AudioStore>(), &id)
+            .await?
+            .ok_or_else(|| AppError::not_found("Memo should exist immediately after creation"))?;
+        emit_memo_updated(&self.app);
+        Ok(memo)
+    }
+
+    async fn update(&self, _token: &str, id: &str, update: MemoUpdate) -> AppResult<VoiceMemo> {
+        let mut tx = self.pool.begin().await?;
+        revisions::ensure_baseline(&mut tx, id).await?;
+        let result = sqlx::query(
+            "UPDATE voice_memos
+             SET name = ?, transcription = ?, translate = ?, summary = ?, updated_at = ?,
+                 version = version + 1, dirty = 1
+             WHERE id = ? AND deleted_at IS NULL",
+        )
+        .bind(&update.title)
+        .bind(&update.transcript)
+        .bind(&update.translate)
+        .bind(&update.summary)
+        .bind(timestamp(Utc::now()))
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::not_found(format!("Memo {} not found", id)));
+        }
+        if let Some(tags) = &update.tags {
+            replace_tags(&mut tx, id, tags).await?;
+        }
+        revisions::record(&mut tx, id, RevisionSource::Edit).await?;
+        tx.commit().await?;
+        println!("📝 Memo updated locally with ID: {}", id);
+
+        let memo = load_memo(&self.pool, &self.app.state::<AudioStore>(), id)
+            .await?
+            .ok_or_else(|| AppError::not_found("Memo should exist after update"))?;
+        emit_memo_updated(&self.app);
+        Ok(memo)
+    }
+
+    async fn get(&self, _token: &str, id: &str) -> AppResult<Option<VoiceMemo>> {
+        load_memo(&self.pool, &self.app.state::<AudioStore>(), id).await
+    }
+
+    async fn list(&self, _token: &str, query: &MemoQuery) -> AppResult<MemoPage<VoiceMemo>> {
+        self.fetch_page(query).await
+    }
+
+    async fn list_summaries(&self, _token: &str, query: &MemoQuery) -> AppResult<MemoPage<MemoSummary>> {
+        let page = self.fetch_page(query).await?;
+        Ok(MemoPage {
+            items: page.items.into_iter().map(MemoSummary::from).collect(),
+            next_cursor: page.next_cursor,
+        })
+    }
+
+    async fn audio(&self, _token: &str, id: &str) -> AppResult<Vec<u8>> {
+        load_audio(&self.pool, &self.app.state::<AudioStore>(), id).await
+    }
+
+    /// Moves the memo to the trash; see [`crate::trash`].
+    async fn delete(&self, _token: &str, id: &str) -> AppResult<()> {
+        trash::trash_memo(&self.pool, id).await?;
+        emit_memo_updated(&self.app);
+        Ok(())
+    }
+
+    /// Moves every memo to the trash.
+    async fn delete_all(&self, _token: &str) -> AppResult<String> {
+        let moved = trash::trash_all(&self.pool).await?;
+        emit_memo_updated(&self.app);
+        Ok(format!("Moved {} memos to the trash", moved))
+    }
+
+    /// Recordings are stored before anything is uploaded, so there is no
+    /// memo to recover here. The next sync sends them again and picks up an
+    /// interrupted upload of the same recording where it stopped.
+    async fn resume_uploads(&self, _token: &str) -> AppResult<Vec<VoiceMemo>> {
+        Ok(Vec::new())
+    }
+}
@@ -1,9 +1,15 @@
 use crate::events::emit_memo_updated;
-use reqwest::Client;
+use crate::api_client::{ApiClient, MultipartBody};
+use crate::audio;
+use crate::audio_cache::AudioCache;
+use crate::error::{AppError, AppResult};
+use crate::idempotency::IdempotencyStore;
+use crate::tags::normalize_tags;
+use crate::upload::UploadManager;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
-use tauri::AppHandle;
-
-const API_BASE_URL: &str = "https://smartmemo-backend-rust.onrender.com/api";
+use tauri::{AppHandle, Runtime};
 
 // This struct should match the `MemoOutput` from your backend API
 // It's used to deserialize the responses from GET requests.
@@ -17,9 +23,253 @@ pub struct VoiceMemo {
     pub tags: Option<Vec<String>>,
     pub duration: String,
     pub created_at: String,
+    /// Last change on the side that sent it; older backends leave it out.
+    #[serde(default)]
+    pub updated_at: Option<String>,
+    #[serde(default, deserialize_with = "audio::deserialize_audio_blob")]
     pub audio_blob: Option<Vec<u8>>,
 }
 
+/// Lightweight listing entry: everything the memo list needs without the
+/// recording or the full texts.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MemoSummary {
+    pub id: String,
+    pub title: String,
+    pub tags: Option<Vec<String>>,
+    pub duration: String,
+    pub created_at: String,
+    pub has_transcript: bool,
+    pub has_summary: bool,
+    pub has_translation: bool,
+}
+
+// The summary view of `/get_memos` sends the `has_*` flags; older backends
+// send the texts instead, so accept either.
+#[derive(Deserialize)]
+struct MemoListItem {
+    id: String,
+    title: String,
+    tags: Option<Vec<String>>,
+    duration: String,
+    created_at: String,
+    has_transcript: Option<bool>,
+    has_summary: Option<bool>,
+    has_translation: Option<bool>,
+    transcript: Option<String>,
+    summary: Option<String>,
+    translate: Option<String>,
+}
+
+fn is_present(text: &Option<String>) -> bool {
+    text.as_deref().is_some_and(|t| !t.trim().is_empty())
+}
+
+impl From<MemoListItem> for MemoSummary {
+    fn from(item: MemoListItem) -> Self {
+        Self {
+            has_transcript: item.has_transcript.unwrap_or_else(|| is_present(&item.transcript)),
+            has_summary: item.has_summary.unwrap_or_else(|| is_present(&item.summary)),
+            has_translation: item.has_translation.unwrap_or_else(|| is_present(&item.translate)),
+            id: item.id,
+            title: item.title,
+            tags: item.tags,
+            duration: item.duration,
+            created_at: item.created_at,
+        }
+    }
+}
+
+impl From<VoiceMemo> for MemoSummary {
+    fn from(memo: VoiceMemo) -> Self {
+        Self {
+            has_transcript: is_present(&memo.transcript),
+            has_summary: is_present(&memo.summary),
+            has_translation: is_present(&memo.translate),
+            id: memo.id,
+            title: memo.title,
+            tags: memo.tags,
+            duration: memo.duration,
+            created_at: memo.created_at,
+        }
+    }
+}
+
+/// Field a memo listing is ordered by.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoSortField {
+    #[default]
+    CreatedAt,
+    Title,
+    Duration,
+}
+
+impl MemoSortField {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            MemoSortField::CreatedAt => "created_at",
+            MemoSortField::Title => "title",
+            MemoSortField::Duration => "duration",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortDirection {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            SortDirection::Asc => "asc",
+            SortDirection::Desc => "desc",
+        }
+    }
+}
+
+/// Paging, sorting and filtering options for memo listings.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct MemoQuery {
+    /// Opaque cursor from a previous page's `next_cursor`.
+    pub cursor: Option<String>,
+    pub page_size: Option<u32>,
+    pub sort_by: MemoSortField,
+    pub sort_dir: SortDirection,
+    /// Only memos carrying all of these tags.
+    pub tags: Option<Vec<String>>,
+    /// Inclusive `created_at` bounds (RFC 3339).
+    pub created_from: Option<String>,
+    pub created_to: Option<String>,
+    pub has_transcript: Option<bool>,
+}
+
+pub const MAX_PAGE_SIZE: u32 = 200;
+/// Page size when a query does not set one.
+pub const DEFAULT_PAGE_SIZE: u32 = 50;
+
+impl MemoQuery {
+    /// Converts the query into `/get_memos` query parameters.
+    fn to_params(&self) -> AppResult<Vec<(&'static str, String)>> {
+        let mut params = vec![
+            ("sort", self.sort_by.as_str().to_string()),
+            ("order", self.sort_dir.as_str().to_string()),
+        ];
+        if let Some(cursor) = &self.cursor {
+            params.push(("cursor", cursor.clone()));
+        }
+        if let Some(size) = self.page_size {
+            if size == 0 || size > MAX_PAGE_SIZE {
+                return Err(AppError::validation(format!(
+                    "page_size must be between 1 and {}",
+                    MAX_PAGE_SIZE
+                )));
+            }
+            params.push(("limit", size.to_string()));
+        }
+        if let Some(tags) = self.tags.as_ref().filter(|t| !t.is_empty()) {
+            params.push(("tags", tags.join(",")));
+        }
+        for (name, bound) in [("from", &self.created_from), ("to", &self.created_to)] {
+            if let Some(bound) = bound {
+                chrono::DateTime::parse_from_rfc3339(bound).map_err(|_| {
+                    AppError::validation(format!("{} is not an RFC 3339 timestamp: {}", name, bound))
+                })?;
+                params.push((name, bound.clone()));
+            }
+        }
+        if let Some(has_transcript) = self.has_transcript {
+            params.push(("has_transcript", has_transcript.to_string()));
+        }
+        Ok(params)
+    }
+}
+
+/// Longer durations are rejected so parsing one cannot overflow.
+const MAX_DURATION_LEN: usize = 12;
+
+/// Seconds in a memo duration such as "45", "01:05" or "1:02:03". The
+/// `duration_secs` column (migration 0009) accepts the same inputs.
+pub fn duration_secs(duration: &str) -> Option<u64> {
+    let parts: Vec<&str> = duration.split(':').collect();
+    if duration.len() > MAX_DURATION_LEN || parts.len() > 3 {
+        return None;
+    }
+    parts.into_iter().try_fold(0u64, |total, part| {
+        if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        total.checked_mul(60)?.checked_add(part.parse().ok()?)
+    })
+}
+
+/// Value of the field a listing is sorted by.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(untagged)]
+pub enum SortValue {
+    /// Durations, in seconds.
+    Number(i64),
+    /// Titles and creation timestamps.
+    Text(String),
+}
+
+/// Where the next page of a listing starts: right after the memo with this
+/// sort value and id. Unlike an offset it stays put when memos are created
+/// or trashed between pages. The local store and the backend encode it the
+/// same way, as URL-safe base64 of its JSON.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MemoCursor {
+    pub value: SortValue,
+    pub id: String,
+}
+
+impl MemoCursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(cursor: &str) -> AppResult<Self> {
+        URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| AppError::validation(format!("Invalid cursor: {}", cursor)))
+    }
+}
+
+/// One page of a memo listing.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MemoPage<T> {
+    pub items: Vec<T>,
+    /// Pass back as `cursor` to get the next page; `None` on the last page.
+    pub next_cursor: Option<String>,
+}
+
+// Backends without paging answer with a bare array.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PageResponse<T> {
+    Page(MemoPage<T>),
+    All(Vec<T>),
+}
+
+impl<T> From<PageResponse<T>> for MemoPage<T> {
+    fn from(res: PageResponse<T>) -> Self {
+        match res {
+            PageResponse::Page(page) => page,
+            PageResponse::All(items) => MemoPage {
+                items,
+                next_cursor: None,
+            },
+        }
+    }
+}
+
 // This struct matches the response from create/update/delete operations
 #[derive(Deserialize, Debug)]
 struct MemoResponse {
@@ -27,49 +277,140 @@ struct MemoResponse {
     memo_id: String,
 }
 
+/// Title given to a recording until the user or the AI names it.
+pub(crate) fn default_title() -> String {
+    format!("Untitled Recording - {}", chrono::Local::now().format("%Y-%m-%d %H:%M"))
+}
+
+/// Metadata sent alongside a new recording.
+fn new_memo_metadata(duration: &str, tags: &[String]) -> serde_json::Value {
+    serde_json::json!({
+        "title": default_title(),
+        "duration": duration,
+        "tags": normalize_tags(tags),
+    })
+}
+
 /// Creates a new memo by sending the audio data and initial info to the backend.
-pub async fn save_audio(
-    app: &AppHandle,
+///
+/// The request carries an `Idempotency-Key` that stays the same for every
+/// retry of this recording until the server confirms it, so a lost response
+/// never produces a duplicate memo. Recordings above the configured size
+/// threshold go through [`save_audio_chunked`] instead.
+#[allow(clippy::too_many_arguments)]
+pub async fn save_audio<R: Runtime>(
+    app: &AppHandle<R>,
+    api: &ApiClient,
+    keys: &IdempotencyStore,
+    uploads: &UploadManager,
     token: &str,
     audio_blob: Vec<u8>,
     duration: String,
     tags: Vec<String>,
-) -> Result<VoiceMemo, Box<dyn std::error::Error>> {
-    let client = Client::new();
+) -> AppResult<VoiceMemo> {
+    if uploads.should_chunk(audio_blob.len()) {
+        return save_audio_chunked(app, api, keys, uploads, token, audio_blob, duration, tags).await;
+    }
 
-    // The backend's `save_memo` endpoint handles the initial creation.
-    let payload = serde_json::json!({
-        "title": format!("Untitled Recording - {}", chrono::Local::now().format("%Y-%m-%d %H:%M")),
-        "duration": duration,
-        "audio_blob": audio_blob,
-        "tags": tags,
-    });
+    let fingerprint = IdempotencyStore::fingerprint(&audio_blob, &duration, &tags);
+    let idempotency_key = keys.key_for(&fingerprint)?;
 
-    let res = client
-        .post(&format!("{}/save_memo", API_BASE_URL))
+    // The backend's `save_memo` endpoint handles the initial creation. Metadata
+    // goes in a JSON part and the recording as a binary file part.
+    let metadata = new_memo_metadata(&duration, &tags);
+    let mime = audio::sniff_mime(&audio_blob);
+    let form = MultipartBody::new()
+        .json("metadata", &metadata)?
+        .file("audio", &format!("recording.{}", audio::extension_for(mime)), mime, &audio_blob);
+
+    let res = api
+        .post("/save_memo")
         .header("Authorization", format!("Bearer {}", token))
-        .json(&payload)
+        .idempotency_key(&idempotency_key)
+        .multipart(form)
         .send()
         .await?;
 
     if !res.status().is_success() {
-        let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("API Error: {}", error_body).into());
+        return Err(AppError::from_response(res).await);
     }
 
     let response: MemoResponse = res.json().await?;
+    keys.confirm(&fingerprint)?;
     println!("💾 New memo created via API with ID: {}", response.memo_id);
 
     // After creating, fetch the full memo object to return it
-    let new_memo = get_memo(&response.memo_id, token).await?;
+    let new_memo = get_memo(api, &response.memo_id, token)
+        .await?
+        .ok_or_else(|| AppError::not_found("Memo should exist immediately after creation"))?;
     emit_memo_updated(app);
 
-    Ok(new_memo.expect("Memo should exist immediately after creation"))
+    Ok(new_memo)
+}
+
+/// Creates a new memo using the resumable chunked upload protocol. Progress
+/// is kept on disk, so calling this again for the same recording (or
+/// [`resume_uploads`] after a restart) continues where it stopped.
+#[allow(clippy::too_many_arguments)]
+pub async fn save_audio_chunked<R: Runtime>(
+    app: &AppHandle<R>,
+    api: &ApiClient,
+    keys: &IdempotencyStore,
+    uploads: &UploadManager,
+    token: &str,
+    audio_blob: Vec<u8>,
+    duration: String,
+    tags: Vec<String>,
+) -> AppResult<VoiceMemo> {
+    let fingerprint = IdempotencyStore::fingerprint(&audio_blob, &duration, &tags);
+    let idempotency_key = keys.key_for(&fingerprint)?;
+    let metadata = new_memo_metadata(&duration, &tags);
+
+    let memo_id = uploads
+        .upload(app, api, token, &fingerprint, &idempotency_key, &audio_blob, metadata)
+        .await?;
+    keys.confirm(&fingerprint)?;
+    println!("💾 New memo created via chunked upload with ID: {}", memo_id);
+
+    let new_memo = get_memo(api, &memo_id, token)
+        .await?
+        .ok_or_else(|| AppError::not_found("Memo should exist immediately after creation"))?;
+    emit_memo_updated(app);
+
+    Ok(new_memo)
+}
+
+/// Finishes chunked uploads that were interrupted, e.g. by a crash or lost
+/// connection, and returns the memos they created.
+pub async fn resume_uploads<R: Runtime>(
+    app: &AppHandle<R>,
+    api: &ApiClient,
+    keys: &IdempotencyStore,
+    uploads: &UploadManager,
+    token: &str,
+) -> AppResult<Vec<VoiceMemo>> {
+    let mut memos = Vec::new();
+    for manifest in uploads.pending().await? {
+        let fingerprint = manifest.fingerprint.clone();
+        println!("⏯️ Resuming upload {}", manifest.upload_id);
+        let memo_id = uploads.resume(app, api, token, manifest).await?;
+        keys.confirm(&fingerprint)?;
+        if let Some(memo) = get_memo(api, &memo_id, token).await? {
+            memos.push(memo);
+        }
+    }
+
+    if !memos.is_empty() {
+        emit_memo_updated(app);
+    }
+    Ok(memos)
 }
 
 /// Updates an existing voice memo with new details.
-pub async fn save_memo(
-    app: &AppHandle,
+#[allow(clippy::too_many_arguments)]
+pub async fn save_memo<R: Runtime>(
+    app: &AppHandle<R>,
+    api: &ApiClient,
     token: &str,
     id: &str,
     name: &str,
@@ -77,65 +418,112 @@ pub async fn save_memo(
     translate: Option<String>,
     summary: Option<String>,
     tags: Option<Vec<String>>,
-) -> Result<VoiceMemo, Box<dyn std::error::Error>> {
-    let client = Client::new();
-
+) -> AppResult<VoiceMemo> {
     let payload = serde_json::json!({
         "title": name,
         "transcript": transcription,
         "translate": translate,
         "summary": summary,
-        "tags": tags,
+        "tags": tags.as_deref().map(normalize_tags),
     });
 
-    let res = client
-        .patch(&format!("{}/update_memo/{}", API_BASE_URL, id))
+    let res = api
+        .patch(&format!("/update_memo/{}", id))
         .header("Authorization", format!("Bearer {}", token))
         .json(&payload)
+        .idempotent()
         .send()
         .await?;
 
     if !res.status().is_success() {
-        let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("API Error: {}", error_body).into());
+        return Err(AppError::from_response(res).await);
     }
 
     let response: MemoResponse = res.json().await?;
     println!("📝 Memo updated via API with ID: {}", response.memo_id);
     
     // Fetch the updated memo to return it
-    let updated_memo = get_memo(id, token).await?;
+    let updated_memo = get_memo(api, id, token)
+        .await?
+        .ok_or_else(|| AppError::not_found("Memo should exist after update"))?;
     emit_memo_updated(app);
 
-    Ok(updated_memo.expect("Memo should exist after update"))
+    Ok(updated_memo)
 }
 
-/// Retrieves all voice memos for the authenticated user.
-pub async fn get_memos(token: &str) -> Result<Vec<VoiceMemo>, Box<dyn std::error::Error>> {
-    let client = Client::new();
+/// Retrieves one page of voice memos for the authenticated user, without
+/// their recordings; use [`get_memo_audio`] to load one on demand.
+pub async fn get_memos(api: &ApiClient, token: &str, query: &MemoQuery) -> AppResult<MemoPage<VoiceMemo>> {
+    let res = api
+        .get("/get_memos")
+        .header("Authorization", format!("Bearer {}", token))
+        .query(&[("include_audio", "false")])
+        .query(&query.to_params()?)
+        .send()
+        .await?;
 
-    let res = client
-        .get(&format!("{}/get_memos", API_BASE_URL))
+    if !res.status().is_success() {
+        return Err(AppError::from_response(res).await);
+    }
+
+    let page: MemoPage<VoiceMemo> = res.json::<PageResponse<VoiceMemo>>().await?.into();
+    println!("📋 Retrieved {} memos from API", page.items.len());
+    Ok(page)
+}
+
+/// Retrieves one page of the memo list as [`MemoSummary`] entries.
+pub async fn list_memos(api: &ApiClient, token: &str, query: &MemoQuery) -> AppResult<MemoPage<MemoSummary>> {
+    let res = api
+        .get("/get_memos")
         .header("Authorization", format!("Bearer {}", token))
+        .query(&[("view", "summary")])
+        .query(&query.to_params()?)
         .send()
         .await?;
 
     if !res.status().is_success() {
-        let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("API Error: {}", error_body).into());
+        return Err(AppError::from_response(res).await);
     }
 
-    let memos: Vec<VoiceMemo> = res.json().await?;
-    println!("📋 Retrieved {} memos from API", memos.len());
-    Ok(memos)
+    let page: MemoPage<MemoListItem> = res.json::<PageResponse<MemoListItem>>().await?.into();
+    println!("📋 Retrieved {} memo summaries from API", page.items.len());
+    Ok(MemoPage {
+        items: page.items.into_iter().map(MemoSummary::from).collect(),
+        next_cursor: page.next_cursor,
+    })
 }
 
-/// Retrieves a single voice memo by its ID for the authenticated user.
-pub async fn get_memo(id: &str, token: &str) -> Result<Option<VoiceMemo>, Box<dyn std::error::Error>> {
-    let client = Client::new();
+/// Returns the recording of one memo, from the local cache when possible.
+pub async fn get_memo_audio(
+    api: &ApiClient,
+    cache: &AudioCache,
+    id: &str,
+    token: &str,
+) -> AppResult<Vec<u8>> {
+    if let Some(audio) = cache.get(id).await {
+        return Ok(audio);
+    }
+
+    let res = api
+        .get(&format!("/get_memo/{}/audio", id))
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await?;
+
+    if !res.status().is_success() {
+        return Err(AppError::from_response(res).await);
+    }
+
+    let audio = res.bytes().await?.to_vec();
+    println!("🎧 Fetched {} bytes of audio for memo {}", audio.len(), id);
+    cache.put(id, &audio).await?;
+    Ok(audio)
+}
 
-    let res = client
-        .get(&format!("{}/get_memo/{}", API_BASE_URL, id))
+/// Retrieves a single voice memo by its ID for the authenticated user.
+pub async fn get_memo(api: &ApiClient, id: &str, token: &str) -> AppResult<Option<VoiceMemo>> {
+    let res = api
+        .get(&format!("/get_memo/{}", id))
         .header("Authorization", format!("Bearer {}", token))
         .send()
         .await?;
@@ -145,8 +533,7 @@ pub async fn get_memo(id: &str, token: &str) -> Result<Option<VoiceMemo>, Box<dy
     }
 
     if !res.status().is_success() {
-        let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("API Error: {}", error_body).into());
+        return Err(AppError::from_response(res).await);
     }
 
     let memo: VoiceMemo = res.json().await?;
@@ -154,18 +541,15 @@ pub async fn get_memo(id: &str, token: &str) -> Result<Option<VoiceMemo>, Box<dy
 }
 
 /// Deletes a voice memo by its ID for the authenticated user.
-pub async fn delete_memo(app: &AppHandle, id: &str, token: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let client = Client::new();
-
-    let res = client
-        .delete(&format!("{}/delete_memo/{}", API_BASE_URL, id))
+pub async fn delete_memo<R: Runtime>(app: &AppHandle<R>, api: &ApiClient, id: &str, token: &str) -> AppResult<()> {
+    let res = api
+        .delete(&format!("/delete_memo/{}", id))
         .header("Authorization", format!("Bearer {}", token))
         .send()
         .await?;
 
     if !res.status().is_success() {
-        let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("API Error: {}", error_body).into());
+        return Err(AppError::from_response(res).await);
     }
     
     println!("🗑️ Memo deleted via API with ID: {}", id);
@@ -174,18 +558,15 @@ pub async fn delete_memo(app: &AppHandle, id: &str, token: &str) -> Result<(), B
 }
 
 /// Deletes all voice memos for the authenticated user.
-pub async fn delete_all_memos(app: &AppHandle, token: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let client = Client::new();
-
-    let res = client
-        .delete(&format!("{}/delete_all_memos", API_BASE_URL))
+pub async fn delete_all_memos<R: Runtime>(app: &AppHandle<R>, api: &ApiClient, token: &str) -> AppResult<String> {
+    let res = api
+        .delete("/delete_all_memos")
         .header("Authorization", format!("Bearer {}", token))
         .send()
         .await?;
 
     if !res.status().is_success() {
-        let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("API Error: {}", error_body).into());
+        return Err(AppError::from_response(res).await);
     }
 
     let response: MemoResponse = res.json().await?;
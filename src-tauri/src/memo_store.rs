@@ -0,0 +1,305 @@
+use crate::api_client::ApiClient;
+use crate::audio_cache::AudioCache;
+use crate::error::{AppError, AppResult};
+use crate::events::emit_memo_updated;
+use crate::idempotency::IdempotencyStore;
+use crate::memo_ops::{self, MemoPage, MemoQuery, MemoSummary, VoiceMemo, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use crate::outbox::{is_offline, Outbox};
+use crate::trash;
+use crate::upload::UploadManager;
+use crate::vault::Vault;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::future::Future;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager, Runtime};
+
+/// Where memos are stored.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    /// The SmartMemo backend, via `memo_ops`.
+    Remote,
+    /// The local database, which keeps working offline.
+    #[default]
+    Local,
+}
+
+impl std::str::FromStr for StorageBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "remote" => Ok(StorageBackend::Remote),
+            "local" => Ok(StorageBackend::Local),
+            other => Err(format!("Unknown storage backend: {}", other)),
+        }
+    }
+}
+
+impl StorageBackend {
+    /// Fails for features that only exist for memos in the local database,
+    /// such as revision history, when memos are stored on the backend.
+    pub fn require_local(self, feature: &str) -> AppResult<()> {
+        match self {
+            StorageBackend::Local => Ok(()),
+            StorageBackend::Remote => Err(AppError::validation(format!(
+                "{} is only available when memos are stored locally",
+                feature
+            ))),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub backend: StorageBackend,
+}
+
+/// Editable fields of a memo, as sent by `save_memo_command`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MemoUpdate {
+    pub title: String,
+    pub transcript: Option<String>,
+    pub translate: Option<String>,
+    pub summary: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+/// Storage for voice memos. Commands talk to whichever implementation was
+/// picked at startup through the managed [`SharedMemoStore`].
+///
+/// Implementations emit `memo:updated` after every change.
+#[async_trait]
+pub trait MemoStore: Send + Sync {
+    /// Creates a memo from a new recording.
+    async fn create(&self, token: &str, audio: Vec<u8>, duration: String, tags: Vec<String>) -> AppResult<VoiceMemo>;
+
+    async fn update(&self, token: &str, id: &str, update: MemoUpdate) -> AppResult<VoiceMemo>;
+
+    /// Returns a memo including its recording, or `None` if it does not exist.
+    async fn get(&self, token: &str, id: &str) -> AppResult<Option<VoiceMemo>>;
+
+    /// Returns one page of memos without their recordings.
+    async fn list(&self, token: &str, query: &MemoQuery) -> AppResult<MemoPage<VoiceMemo>>;
+
+    async fn list_summaries(&self, token: &str, query: &MemoQuery) -> AppResult<MemoPage<MemoSummary>>;
+
+    /// Returns the recording of one memo.
+    async fn audio(&self, token: &str, id: &str) -> AppResult<Vec<u8>>;
+
+    /// Moves one memo to the trash (see [`crate::trash`]), from where it can
+    /// be restored until it is purged.
+    async fn delete(&self, token: &str, id: &str) -> AppResult<()>;
+
+    /// Moves every memo to the trash, like [`MemoStore::delete`], and
+    /// returns a message for the user.
+    async fn delete_all(&self, token: &str) -> AppResult<String>;
+
+    /// Finishes chunked uploads that were interrupted and returns the memos
+    /// they created.
+    async fn resume_uploads(&self, token: &str) -> AppResult<Vec<VoiceMemo>>;
+}
+
+/// The memo store managed by the app.
+pub type SharedMemoStore = Arc<dyn MemoStore>;
+
+/// [`MemoStore`] backed by the SmartMemo API. The HTTP client, upload state,
+/// audio cache and outbox are taken from the app's managed state.
+///
+/// Writes that cannot reach the backend are queued in the [`Outbox`] and
+/// replayed before the next write. Deleted memos are kept in the local
+/// trash and left out of listings until they are purged. No revisions are
+/// recorded, so the revision commands are refused in this mode.
+pub struct RemoteMemoStore<R: Runtime> {
+    app: AppHandle<R>,
+}
+
+impl<R: Runtime> RemoteMemoStore<R> {
+    pub fn new(app: AppHandle<R>) -> Self {
+        Self { app }
+    }
+
+    /// Whether the local database, which holds the trash and the outbox,
+    /// can be read. While the vault is locked memos come straight from the
+    /// backend.
+    fn local_readable(&self) -> bool {
+        self.app.try_state::<Vault>().map_or(true, |vault| !vault.status().locked)
+    }
+
+    /// Replays queued writes so a new one lands after them. Returns whether
+    /// the new write has to be queued as well.
+    async fn must_queue(&self, outbox: &Outbox, token: &str, memo_id: Option<&str>) -> AppResult<bool> {
+        if !self.local_readable() {
+            return Ok(false);
+        }
+        if outbox.has_pending().await? {
+            outbox.replay(&self.app, token).await?;
+        }
+        if outbox.has_pending().await? {
+            return Ok(true);
+        }
+        match memo_id {
+            Some(id) => outbox.blocks(id).await,
+            None => Ok(false),
+        }
+    }
+
+    async fn is_trashed(&self, id: &str) -> AppResult<bool> {
+        if !self.local_readable() {
+            return Ok(false);
+        }
+        Ok(trash::hidden_remote_ids(&self.app.state::<SqlitePool>()).await?.contains(id))
+    }
+
+    /// Fetches pages until `query.page_size` memos outside the trash are
+    /// found, so trashed memos do not leave pages short.
+    async fn visible_page<T, F, Fut>(&self, query: &MemoQuery, id: impl Fn(&T) -> &str, fetch: F) -> AppResult<MemoPage<T>>
+    where
+        F: Fn(MemoQuery) -> Fut,
+        Fut: Future<Output = AppResult<MemoPage<T>>>,
+    {
+        if !self.local_readable() {
+            return fetch(query.clone()).await;
+        }
+        let hidden = trash::hidden_remote_ids(&self.app.state::<SqlitePool>()).await?;
+        if hidden.is_empty() {
+            return fetch(query.clone()).await;
+        }
+        let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+        let mut query = query.clone();
+        let mut items = Vec::new();
+        loop {
+            query.page_size = Some(page_size - items.len() as u32);
+            let page = fetch(query.clone()).await?;
+            items.extend(page.items.into_iter().filter(|item| !hidden.contains(id(item))));
+            match page.next_cursor {
+                Some(cursor) if items.len() < page_size as usize => query.cursor = Some(cursor),
+                next_cursor => return Ok(MemoPage { items, next_cursor }),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<R: Runtime> MemoStore for RemoteMemoStore<R> {
+    async fn create(&self, token: &str, audio: Vec<u8>, duration: String, tags: Vec<String>) -> AppResult<VoiceMemo> {
+        let api = self.app.state::<ApiClient>();
+        let keys = self.app.state::<IdempotencyStore>();
+        let uploads = self.app.state::<UploadManager>();
+        let outbox = self.app.state::<Outbox>();
+        if self.must_queue(&outbox, token, None).await? {
+            return outbox.enqueue_create(audio, duration, tags).await;
+        }
+        match memo_ops::save_audio(&self.app, &api, &keys, &uploads, token, audio.clone(), duration.clone(), tags.clone()).await {
+            Err(e) if is_offline(&e) => outbox.enqueue_create(audio, duration, tags).await,
+            result => result,
+        }
+    }
+
+    async fn update(&self, token: &str, id: &str, update: MemoUpdate) -> AppResult<VoiceMemo> {
+        if self.is_trashed(id).await? {
+            return Err(AppError::not_found(format!("Memo {} not found", id)));
+        }
+        let api = self.app.state::<ApiClient>();
+        let outbox = self.app.state::<Outbox>();
+        if self.must_queue(&outbox, token, Some(id)).await? {
+            return outbox.enqueue_update(id, update).await;
+        }
+        let result = memo_ops::save_memo(
+            &self.app,
+            &api,
+            token,
+            id,
+            &update.title,
+            update.transcript.clone(),
+            update.translate.clone(),
+            update.summary.clone(),
+            update.tags.clone(),
+        )
+        .await;
+        match result {
+            Err(e) if is_offline(&e) => outbox.enqueue_update(id, update).await,
+            result => result,
+        }
+    }
+
+    async fn get(&self, token: &str, id: &str) -> AppResult<Option<VoiceMemo>> {
+        if self.is_trashed(id).await? {
+            return Ok(None);
+        }
+        memo_ops::get_memo(&self.app.state::<ApiClient>(), id, token).await
+    }
+
+    async fn list(&self, token: &str, query: &MemoQuery) -> AppResult<MemoPage<VoiceMemo>> {
+        let api = &*self.app.state::<ApiClient>();
+        self.visible_page(query, |m: &VoiceMemo| &m.id, |q| async move {
+            memo_ops::get_memos(api, token, &q).await
+        })
+        .await
+    }
+
+    async fn list_summaries(&self, token: &str, query: &MemoQuery) -> AppResult<MemoPage<MemoSummary>> {
+        let api = &*self.app.state::<ApiClient>();
+        self.visible_page(query, |m: &MemoSummary| &m.id, |q| async move {
+            memo_ops::list_memos(api, token, &q).await
+        })
+        .await
+    }
+
+    async fn audio(&self, token: &str, id: &str) -> AppResult<Vec<u8>> {
+        if self.is_trashed(id).await? {
+            return Err(AppError::not_found(format!("Memo {} not found", id)));
+        }
+        let api = self.app.state::<ApiClient>();
+        let cache = self.app.state::<AudioCache>();
+        memo_ops::get_memo_audio(&api, &cache, id, token).await
+    }
+
+    /// Keeps a copy in the local trash; the backend copy is deleted when the
+    /// trash is purged. While offline the move is queued.
+    async fn delete(&self, token: &str, id: &str) -> AppResult<()> {
+        let outbox = self.app.state::<Outbox>();
+        if self.must_queue(&outbox, token, Some(id)).await? {
+            return outbox.enqueue_delete(id).await;
+        }
+        match trash::trash_remote(&self.app, token, id).await {
+            Err(e) if is_offline(&e) => return outbox.enqueue_delete(id).await,
+            result => result?,
+        }
+        emit_memo_updated(&self.app);
+        Ok(())
+    }
+
+    /// Moves the memos one by one, each like [`MemoStore::delete`]. Listing
+    /// them needs the backend, so offline this fails before anything is
+    /// moved or queued.
+    async fn delete_all(&self, token: &str) -> AppResult<String> {
+        let mut query = MemoQuery {
+            page_size: Some(MAX_PAGE_SIZE),
+            ..MemoQuery::default()
+        };
+        let mut ids = Vec::new();
+        loop {
+            let page = self.list_summaries(token, &query).await?;
+            ids.extend(page.items.into_iter().map(|m| m.id));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        for id in &ids {
+            self.delete(token, id).await?;
+        }
+        Ok(format!("Moved {} memos to the trash", ids.len()))
+    }
+
+    async fn resume_uploads(&self, token: &str) -> AppResult<Vec<VoiceMemo>> {
+        let api = self.app.state::<ApiClient>();
+        let keys = self.app.state::<IdempotencyStore>();
+        let uploads = self.app.state::<UploadManager>();
+        memo_ops::resume_uploads(&self.app, &api, &keys, &uploads, token).await
+    }
+}
@@ -0,0 +1,413 @@
+use crate::api_client::ApiClient;
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::events::emit_memo_updated;
+use crate::idempotency::IdempotencyStore;
+use crate::local_store::timestamp;
+use crate::memo_ops::{self, default_title, VoiceMemo};
+use crate::memo_store::MemoUpdate;
+use crate::retry::is_retryable_status;
+use crate::trash;
+use crate::upload::UploadManager;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use tauri::{AppHandle, Manager, Runtime};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+/// Prefix of the ids handed out for recordings still waiting in the outbox.
+pub const PENDING_ID_PREFIX: &str = "pending-";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    Create,
+    Update,
+    Delete,
+}
+
+impl OperationKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            OperationKind::Create => "create",
+            OperationKind::Update => "update",
+            OperationKind::Delete => "delete",
+        }
+    }
+
+    fn parse(value: &str) -> AppResult<Self> {
+        match value {
+            "create" => Ok(OperationKind::Create),
+            "update" => Ok(OperationKind::Update),
+            "delete" => Ok(OperationKind::Delete),
+            other => Err(AppError::internal(format!("Unknown outbox operation: {}", other))),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CreatePayload {
+    duration: String,
+    tags: Vec<String>,
+}
+
+/// A queued memo write, as listed to the user.
+#[derive(Serialize, Debug, Clone)]
+pub struct PendingOperation {
+    pub id: i64,
+    pub operation: OperationKind,
+    pub memo_id: String,
+    /// Title of the memo for updates.
+    pub title: Option<String>,
+    /// Size of the recording for creates.
+    pub audio_size: Option<usize>,
+    pub attempts: i64,
+    /// Set once the backend rejected the operation; it then waits for an
+    /// explicit retry or discard.
+    pub failed: bool,
+    pub last_error: Option<String>,
+    pub created_at: String,
+}
+
+/// Outcome of one replay of the outbox.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ReplayReport {
+    pub replayed: usize,
+    pub failed: usize,
+    /// Operations still queued afterwards, failed ones included.
+    pub remaining: usize,
+}
+
+#[derive(sqlx::FromRow)]
+struct OutboxRow {
+    id: i64,
+    operation: String,
+    memo_id: String,
+    payload: String,
+    audio: Option<Vec<u8>>,
+    attempts: i64,
+    failed: bool,
+    last_error: Option<String>,
+    created_at: String,
+}
+
+/// Whether an error means the backend is unavailable, as opposed to the
+/// backend rejecting the request: it could not be reached at all, or it
+/// kept answering with a retryable status (a sleeping host answers 502-504)
+/// until the retries ran out.
+pub fn is_offline(error: &AppError) -> bool {
+    match error.status {
+        Some(status) => is_retryable_status(status),
+        None => matches!(error.kind, ErrorKind::Network | ErrorKind::Timeout),
+    }
+}
+
+pub fn is_pending_id(id: &str) -> bool {
+    id.starts_with(PENDING_ID_PREFIX)
+}
+
+/// Durable queue of memo writes made while the backend was unreachable.
+///
+/// Operations are stored in the local database and replayed in the order
+/// they were made. A newer edit of a memo replaces the queued one, and
+/// deleting a memo drops its queued edits.
+pub struct Outbox {
+    pool: SqlitePool,
+    replaying: Mutex<()>,
+}
+
+impl Outbox {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            replaying: Mutex::new(()),
+        }
+    }
+
+    /// Whether operations are waiting to be replayed. Failed operations do
+    /// not count; they only hold back later writes to the same memo.
+    pub async fn has_pending(&self) -> AppResult<bool> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM outbox WHERE failed = 0")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count > 0)
+    }
+
+    /// Whether a write to `memo_id` has to queue behind earlier operations.
+    pub async fn blocks(&self, memo_id: &str) -> AppResult<bool> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM outbox WHERE memo_id = ?")
+            .bind(memo_id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count > 0 || is_pending_id(memo_id))
+    }
+
+    async fn insert(&self, operation: OperationKind, memo_id: &str, payload: String, audio: Option<Vec<u8>>) -> AppResult<()> {
+        sqlx::query("INSERT INTO outbox (operation, memo_id, payload, audio, created_at) VALUES (?, ?, ?, ?, ?)")
+            .bind(operation.as_str())
+            .bind(memo_id)
+            .bind(payload)
+            .bind(audio)
+            .bind(timestamp(Utc::now()))
+            .execute(&self.pool)
+            .await?;
+        println!("📮 Queued {} of memo {}", operation.as_str(), memo_id);
+        Ok(())
+    }
+
+    /// Queues a new recording and returns a placeholder memo with a
+    /// [`PENDING_ID_PREFIX`] id that later operations can refer to.
+    pub async fn enqueue_create(&self, audio: Vec<u8>, duration: String, tags: Vec<String>) -> AppResult<VoiceMemo> {
+        let id = format!("{}{}", PENDING_ID_PREFIX, Uuid::new_v4());
+        let payload = serde_json::to_string(&CreatePayload {
+            duration: duration.clone(),
+            tags: tags.clone(),
+        })
+        .map_err(|e| AppError::internal(e.to_string()))?;
+        self.insert(OperationKind::Create, &id, payload, Some(audio.clone())).await?;
+
+        let now = timestamp(Utc::now());
+        Ok(VoiceMemo {
+            id,
+            title: default_title(),
+            transcript: None,
+            translate: None,
+            summary: None,
+            tags: Some(tags),
+            duration,
+            created_at: now.clone(),
+            updated_at: Some(now),
+            audio_blob: Some(audio),
+        })
+    }
+
+    /// Queues an edit, replacing any queued edit of the same memo. Fields
+    /// the edit does not carry are left empty in the returned memo.
+    pub async fn enqueue_update(&self, memo_id: &str, update: MemoUpdate) -> AppResult<VoiceMemo> {
+        let payload = serde_json::to_string(&update).map_err(|e| AppError::internal(e.to_string()))?;
+        let superseded = sqlx::query("DELETE FROM outbox WHERE memo_id = ? AND operation = 'update' AND failed = 0")
+            .bind(memo_id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        if superseded > 0 {
+            println!("📮 Dropped {} superseded edit(s) of memo {}", superseded, memo_id);
+        }
+        self.insert(OperationKind::Update, memo_id, payload, None).await?;
+
+        Ok(VoiceMemo {
+            id: memo_id.to_string(),
+            title: update.title,
+            transcript: update.transcript,
+            translate: update.translate,
+            summary: update.summary,
+            tags: update.tags,
+            duration: String::new(),
+            created_at: String::new(),
+            updated_at: Some(timestamp(Utc::now())),
+            audio_blob: None,
+        })
+    }
+
+    /// Queues moving a memo to the trash (see [`trash::trash_remote`]).
+    /// Queued edits of the memo are dropped, and a memo that never reached
+    /// the backend is simply removed from the queue.
+    pub async fn enqueue_delete(&self, memo_id: &str) -> AppResult<()> {
+        if is_pending_id(memo_id) {
+            let removed = sqlx::query("DELETE FROM outbox WHERE memo_id = ?")
+                .bind(memo_id)
+                .execute(&self.pool)
+                .await?
+                .rows_affected();
+            if removed == 0 {
+                return Err(AppError::not_found(format!("Memo {} not found", memo_id)));
+            }
+            println!("📮 Dropped queued memo {} before it was uploaded", memo_id);
+            return Ok(());
+        }
+        sqlx::query("DELETE FROM outbox WHERE memo_id = ? AND operation = 'update' AND failed = 0")
+            .bind(memo_id)
+            .execute(&self.pool)
+            .await?;
+        self.insert(OperationKind::Delete, memo_id, "null".to_string(), None).await
+    }
+
+    pub async fn list(&self) -> AppResult<Vec<PendingOperation>> {
+        let rows: Vec<OutboxRow> = sqlx::query_as("SELECT * FROM outbox ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.into_iter()
+            .map(|row| {
+                let operation = OperationKind::parse(&row.operation)?;
+                let title = match operation {
+                    OperationKind::Update => serde_json::from_str::<MemoUpdate>(&row.payload).ok().map(|u| u.title),
+                    _ => None,
+                };
+                Ok(PendingOperation {
+                    id: row.id,
+                    operation,
+                    memo_id: row.memo_id,
+                    title,
+                    audio_size: row.audio.as_ref().map(Vec::len),
+                    attempts: row.attempts,
+                    failed: row.failed,
+                    last_error: row.last_error,
+                    created_at: row.created_at,
+                })
+            })
+            .collect()
+    }
+
+    /// Removes a queued operation. Discarding a queued recording also drops
+    /// the queued writes to it, since they cannot be replayed without it.
+    pub async fn discard(&self, id: i64) -> AppResult<()> {
+        let row: Option<(String, String)> = sqlx::query_as("SELECT operation, memo_id FROM outbox WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        let Some((operation, memo_id)) = row else {
+            return Err(AppError::not_found(format!("Pending operation {} not found", id)));
+        };
+        if OperationKind::parse(&operation)? == OperationKind::Create {
+            sqlx::query("DELETE FROM outbox WHERE memo_id = ?")
+                .bind(&memo_id)
+                .execute(&self.pool)
+                .await?;
+        } else {
+            sqlx::query("DELETE FROM outbox WHERE id = ?").bind(id).execute(&self.pool).await?;
+        }
+        println!("🗑️ Discarded pending {} of memo {}", operation, memo_id);
+        Ok(())
+    }
+
+    /// Clears the failed mark of one operation, or of all of them, so the
+    /// next replay attempts them again.
+    pub async fn reset_failed(&self, id: Option<i64>) -> AppResult<()> {
+        let result = match id {
+            Some(id) => {
+                sqlx::query("UPDATE outbox SET failed = 0 WHERE id = ?")
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?
+            }
+            None => sqlx::query("UPDATE outbox SET failed = 0").execute(&self.pool).await?,
+        };
+        if let (Some(id), 0) = (id, result.rows_affected()) {
+            return Err(AppError::not_found(format!("Pending operation {} not found", id)));
+        }
+        Ok(())
+    }
+
+    /// Sends queued operations to the backend in order.
+    ///
+    /// Stops at the first operation that cannot reach the backend. An
+    /// operation the backend rejects is marked failed and holds back later
+    /// operations on the same memo, while other memos carry on.
+    pub async fn replay<R: Runtime>(&self, app: &AppHandle<R>, token: &str) -> AppResult<ReplayReport> {
+        let _replaying = self.replaying.lock().await;
+        let rows: Vec<OutboxRow> = sqlx::query_as("SELECT * FROM outbox ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
+        let mut report = ReplayReport::default();
+        let mut blocked = HashSet::new();
+        let mut created: HashMap<String, String> = HashMap::new();
+
+        if !rows.is_empty() {
+            println!("📮 Replaying {} queued operation(s)", rows.len());
+        }
+        for row in rows {
+            let memo_id = created.get(&row.memo_id).cloned().unwrap_or_else(|| row.memo_id.clone());
+            if row.failed || blocked.contains(&memo_id) {
+                blocked.insert(memo_id);
+                continue;
+            }
+
+            match self.send(app, token, &row, &memo_id).await {
+                Ok(Some(new_id)) => {
+                    // Later operations address the memo by its backend id.
+                    sqlx::query("UPDATE outbox SET memo_id = ? WHERE memo_id = ?")
+                        .bind(&new_id)
+                        .bind(&row.memo_id)
+                        .execute(&self.pool)
+                        .await?;
+                    created.insert(row.memo_id.clone(), new_id);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    let offline = is_offline(&e);
+                    println!("❌ Replaying {} of memo {} failed: {}", row.operation, memo_id, e.message);
+                    sqlx::query("UPDATE outbox SET attempts = attempts + 1, failed = ?, last_error = ? WHERE id = ?")
+                        .bind(!offline)
+                        .bind(&e.message)
+                        .bind(row.id)
+                        .execute(&self.pool)
+                        .await?;
+                    if offline {
+                        break;
+                    }
+                    report.failed += 1;
+                    blocked.insert(memo_id);
+                    continue;
+                }
+            }
+            sqlx::query("DELETE FROM outbox WHERE id = ?")
+                .bind(row.id)
+                .execute(&self.pool)
+                .await?;
+            report.replayed += 1;
+        }
+
+        let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM outbox")
+            .fetch_one(&self.pool)
+            .await?;
+        report.remaining = remaining as usize;
+        if report.replayed > 0 {
+            println!("✅ Replayed {} queued operation(s), {} remaining", report.replayed, report.remaining);
+            emit_memo_updated(app);
+        }
+        Ok(report)
+    }
+
+    /// Performs one queued operation. Returns the backend id of a created memo.
+    async fn send<R: Runtime>(&self, app: &AppHandle<R>, token: &str, row: &OutboxRow, memo_id: &str) -> AppResult<Option<String>> {
+        let api = app.state::<ApiClient>();
+        let invalid = |e: serde_json::Error| AppError::internal(format!("Corrupt outbox entry {}: {}", row.id, e));
+        match OperationKind::parse(&row.operation)? {
+            OperationKind::Create => {
+                let payload: CreatePayload = serde_json::from_str(&row.payload).map_err(invalid)?;
+                let keys = app.state::<IdempotencyStore>();
+                let uploads = app.state::<UploadManager>();
+                let audio = row.audio.clone().unwrap_or_default();
+                let memo =
+                    memo_ops::save_audio(app, &api, &keys, &uploads, token, audio, payload.duration, payload.tags).await?;
+                Ok(Some(memo.id))
+            }
+            OperationKind::Update => {
+                let update: MemoUpdate = serde_json::from_str(&row.payload).map_err(invalid)?;
+                memo_ops::save_memo(
+                    app,
+                    &api,
+                    token,
+                    memo_id,
+                    &update.title,
+                    update.transcript,
+                    update.translate,
+                    update.summary,
+                    update.tags,
+                )
+                .await?;
+                Ok(None)
+            }
+            OperationKind::Delete => {
+                match trash::trash_remote(app, token, memo_id).await {
+                    Ok(()) => {}
+                    // Already gone is what we wanted.
+                    Err(e) if e.kind == ErrorKind::NotFound => {}
+                    Err(e) => return Err(e),
+                }
+                Ok(None)
+            }
+        }
+    }
+}
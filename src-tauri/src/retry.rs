@@ -0,0 +1,64 @@
+use rand::Rng;
+use reqwest::header::RETRY_AFTER;
+use reqwest::Response;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Exponential backoff with full jitter for retryable backend failures.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one. `1` disables retries.
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay_ms: 500,
+            max_delay_ms: 8_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `attempt` (1-based): a random value between
+    /// zero and `base * 2^(attempt - 1)`, capped at `max_delay_ms`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay_ms
+            .saturating_mul(1u64 << (attempt.saturating_sub(1)).min(16));
+        let cap = exp.min(self.max_delay_ms);
+        Duration::from_millis(rand::thread_rng().gen_range(0..=cap))
+    }
+
+    /// Delay before the next attempt, honouring a `Retry-After: <seconds>`
+    /// header when the backend sends one.
+    pub fn delay_for(&self, attempt: u32, res: Option<&Response>) -> Duration {
+        let retry_after = res
+            .and_then(|r| r.headers().get(RETRY_AFTER))
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(|secs| Duration::from_millis(secs.saturating_mul(1000).min(self.max_delay_ms)));
+        retry_after.unwrap_or_else(|| self.backoff(attempt))
+    }
+}
+
+/// Status codes that usually mean the free-tier host is asleep or overloaded.
+pub fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 429 | 502 | 503 | 504)
+}
+
+/// Payload of the `backend:waking` event.
+#[derive(Serialize, Debug, Clone)]
+pub struct WakingStatus {
+    /// `true` while we are waiting on a slow or sleeping backend, `false`
+    /// once it has answered (or we gave up).
+    pub waking: bool,
+    pub attempt: u32,
+    pub max_attempts: u32,
+    pub retry_in_ms: Option<u64>,
+}
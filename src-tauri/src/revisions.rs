@@ -0,0 +1,380 @@
+use crate::error::{AppError, AppResult};
+use crate::events::emit_memo_updated;
+use crate::local_store::{replace_tags, timestamp};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+use sqlx::{Sqlite, SqlitePool, Transaction};
+use tauri::{AppHandle, Runtime};
+
+/// An editable field of a memo.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoField {
+    Title,
+    Transcript,
+    Translate,
+    Summary,
+    Tags,
+}
+
+/// Who produced a revision.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RevisionSource {
+    /// The memo as it was before its first recorded edit.
+    Original,
+    /// Recorded on this device.
+    Created,
+    /// Saved on this device.
+    Edit,
+    /// Pulled from the backend, i.e. edited on another device.
+    Sync,
+    /// Restored from an earlier revision.
+    Revert,
+    /// Restored from a backup archive.
+    Import,
+}
+
+impl RevisionSource {
+    fn as_str(self) -> &'static str {
+        match self {
+            RevisionSource::Original => "original",
+            RevisionSource::Created => "created",
+            RevisionSource::Edit => "edit",
+            RevisionSource::Sync => "sync",
+            RevisionSource::Revert => "revert",
+            RevisionSource::Import => "import",
+        }
+    }
+
+    fn parse(value: &str) -> AppResult<Self> {
+        match value {
+            "original" => Ok(RevisionSource::Original),
+            "created" => Ok(RevisionSource::Created),
+            "edit" => Ok(RevisionSource::Edit),
+            "sync" => Ok(RevisionSource::Sync),
+            "revert" => Ok(RevisionSource::Revert),
+            "import" => Ok(RevisionSource::Import),
+            other => Err(AppError::internal(format!("Unknown revision source: {}", other))),
+        }
+    }
+}
+
+/// The editable fields of a memo after one save.
+#[derive(Serialize, Debug, Clone)]
+pub struct Revision {
+    pub id: i64,
+    pub memo_id: String,
+    pub created_at: String,
+    pub source: RevisionSource,
+    /// Fields that differ from the previous revision.
+    pub changed_fields: Vec<MemoField>,
+    pub title: String,
+    pub transcript: Option<String>,
+    pub translate: Option<String>,
+    pub summary: Option<String>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// A run of words that is unchanged, added or removed.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TextChange {
+    pub kind: ChangeKind,
+    pub text: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FieldDiff {
+    pub field: MemoField,
+    pub changes: Vec<TextChange>,
+}
+
+/// Differences between two revisions of a memo; unchanged fields are left out.
+#[derive(Serialize, Debug, Clone)]
+pub struct RevisionDiff {
+    pub from: i64,
+    pub to: i64,
+    pub fields: Vec<FieldDiff>,
+}
+
+#[derive(sqlx::FromRow)]
+struct RevisionRow {
+    id: i64,
+    memo_id: String,
+    created_at: String,
+    source: String,
+    changed_fields: String,
+    title: String,
+    transcript: Option<String>,
+    translate: Option<String>,
+    summary: Option<String>,
+    tags: String,
+}
+
+impl RevisionRow {
+    fn into_revision(self) -> AppResult<Revision> {
+        Ok(Revision {
+            id: self.id,
+            memo_id: self.memo_id,
+            created_at: self.created_at,
+            source: RevisionSource::parse(&self.source)?,
+            changed_fields: serde_json::from_str(&self.changed_fields)?,
+            title: self.title,
+            transcript: self.transcript,
+            translate: self.translate,
+            summary: self.summary,
+            tags: serde_json::from_str(&self.tags)?,
+        })
+    }
+}
+
+const REVISION_COLUMNS: &str =
+    "id, memo_id, created_at, source, changed_fields, title, transcript, translate, summary, tags";
+
+/// Editable fields of a memo as stored right now.
+#[derive(sqlx::FromRow)]
+struct Snapshot {
+    title: String,
+    transcript: Option<String>,
+    translate: Option<String>,
+    summary: Option<String>,
+    #[sqlx(skip)]
+    tags: Vec<String>,
+}
+
+impl Snapshot {
+    fn text(&self, field: MemoField) -> String {
+        match field {
+            MemoField::Title => self.title.clone(),
+            MemoField::Transcript => self.transcript.clone().unwrap_or_default(),
+            MemoField::Translate => self.translate.clone().unwrap_or_default(),
+            MemoField::Summary => self.summary.clone().unwrap_or_default(),
+            MemoField::Tags => self.tags.join(", "),
+        }
+    }
+
+    fn changed_since(&self, previous: &Snapshot) -> Vec<MemoField> {
+        ALL_FIELDS
+            .into_iter()
+            .filter(|field| match field {
+                MemoField::Title => self.title != previous.title,
+                MemoField::Transcript => self.transcript != previous.transcript,
+                MemoField::Translate => self.translate != previous.translate,
+                MemoField::Summary => self.summary != previous.summary,
+                MemoField::Tags => self.tags != previous.tags,
+            })
+            .collect()
+    }
+}
+
+impl From<&Revision> for Snapshot {
+    fn from(revision: &Revision) -> Self {
+        Self {
+            title: revision.title.clone(),
+            transcript: revision.transcript.clone(),
+            translate: revision.translate.clone(),
+            summary: revision.summary.clone(),
+            tags: revision.tags.clone(),
+        }
+    }
+}
+
+const ALL_FIELDS: [MemoField; 5] = [
+    MemoField::Title,
+    MemoField::Transcript,
+    MemoField::Translate,
+    MemoField::Summary,
+    MemoField::Tags,
+];
+
+async fn snapshot(tx: &mut Transaction<'_, Sqlite>, memo_id: &str) -> AppResult<Option<Snapshot>> {
+    let row: Option<Snapshot> = sqlx::query_as(
+        "SELECT name AS title, transcription AS transcript, translate, summary FROM voice_memos WHERE id = ?",
+    )
+    .bind(memo_id)
+    .fetch_optional(&mut **tx)
+    .await?;
+    let Some(mut snapshot) = row else {
+        return Ok(None);
+    };
+    snapshot.tags = sqlx::query_scalar("SELECT tag FROM memo_tags WHERE memo_id = ? ORDER BY position")
+        .bind(memo_id)
+        .fetch_all(&mut **tx)
+        .await?;
+    Ok(Some(snapshot))
+}
+
+async fn latest(tx: &mut Transaction<'_, Sqlite>, memo_id: &str) -> AppResult<Option<Revision>> {
+    let row: Option<RevisionRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM memo_revisions WHERE memo_id = ? ORDER BY id DESC LIMIT 1",
+        REVISION_COLUMNS
+    ))
+    .bind(memo_id)
+    .fetch_optional(&mut **tx)
+    .await?;
+    row.map(RevisionRow::into_revision).transpose()
+}
+
+async fn insert(
+    tx: &mut Transaction<'_, Sqlite>,
+    memo_id: &str,
+    source: RevisionSource,
+    changed: &[MemoField],
+    snapshot: &Snapshot,
+) -> AppResult<()> {
+    sqlx::query(
+        "INSERT INTO memo_revisions
+            (memo_id, created_at, source, changed_fields, title, transcript, translate, summary, tags)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(memo_id)
+    .bind(timestamp(Utc::now()))
+    .bind(source.as_str())
+    .bind(serde_json::to_string(changed)?)
+    .bind(&snapshot.title)
+    .bind(&snapshot.transcript)
+    .bind(&snapshot.translate)
+    .bind(&snapshot.summary)
+    .bind(serde_json::to_string(&snapshot.tags)?)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+/// Snapshots a memo that has no history yet, so its text before the edit
+/// about to be made in `tx` is kept. Call before changing the memo.
+pub(crate) async fn ensure_baseline(tx: &mut Transaction<'_, Sqlite>, memo_id: &str) -> AppResult<()> {
+    if latest(tx, memo_id).await?.is_some() {
+        return Ok(());
+    }
+    if let Some(current) = snapshot(tx, memo_id).await? {
+        insert(tx, memo_id, RevisionSource::Original, &ALL_FIELDS, &current).await?;
+    }
+    Ok(())
+}
+
+/// Records the memo's current fields as a new revision. Nothing is recorded
+/// when no field changed since the last revision.
+pub(crate) async fn record(tx: &mut Transaction<'_, Sqlite>, memo_id: &str, source: RevisionSource) -> AppResult<()> {
+    let Some(current) = snapshot(tx, memo_id).await? else {
+        return Ok(());
+    };
+    let changed = match latest(tx, memo_id).await? {
+        Some(previous) => current.changed_since(&Snapshot::from(&previous)),
+        None => ALL_FIELDS.to_vec(),
+    };
+    if changed.is_empty() {
+        return Ok(());
+    }
+    insert(tx, memo_id, source, &changed, &current).await
+}
+
+/// Revisions of a memo, newest first.
+pub async fn list_revisions(pool: &SqlitePool, memo_id: &str) -> AppResult<Vec<Revision>> {
+    let rows: Vec<RevisionRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM memo_revisions WHERE memo_id = ? ORDER BY id DESC",
+        REVISION_COLUMNS
+    ))
+    .bind(memo_id)
+    .fetch_all(pool)
+    .await?;
+    rows.into_iter().map(RevisionRow::into_revision).collect()
+}
+
+async fn get_revision(pool: &SqlitePool, memo_id: &str, id: i64) -> AppResult<Revision> {
+    let row: Option<RevisionRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM memo_revisions WHERE id = ? AND memo_id = ?",
+        REVISION_COLUMNS
+    ))
+    .bind(id)
+    .bind(memo_id)
+    .fetch_optional(pool)
+    .await?;
+    row.ok_or_else(|| AppError::not_found(format!("Revision {} of memo {} not found", id, memo_id)))?
+        .into_revision()
+}
+
+/// Word-level diff of two texts, with neighbouring words of the same kind
+/// joined into one change.
+pub fn diff_text(before: &str, after: &str) -> Vec<TextChange> {
+    let diff = TextDiff::from_words(before, after);
+    let mut changes: Vec<TextChange> = Vec::new();
+    for change in diff.iter_all_changes() {
+        let kind = match change.tag() {
+            ChangeTag::Equal => ChangeKind::Equal,
+            ChangeTag::Insert => ChangeKind::Insert,
+            ChangeTag::Delete => ChangeKind::Delete,
+        };
+        match changes.last_mut() {
+            Some(last) if last.kind == kind => last.text.push_str(change.value()),
+            _ => changes.push(TextChange {
+                kind,
+                text: change.value().to_string(),
+            }),
+        }
+    }
+    changes
+}
+
+/// Field-by-field diff from revision `from` to revision `to` of a memo.
+pub async fn diff_revisions(pool: &SqlitePool, memo_id: &str, from: i64, to: i64) -> AppResult<RevisionDiff> {
+    let before = Snapshot::from(&get_revision(pool, memo_id, from).await?);
+    let after = Snapshot::from(&get_revision(pool, memo_id, to).await?);
+    let fields = after
+        .changed_since(&before)
+        .into_iter()
+        .map(|field| FieldDiff {
+            field,
+            changes: diff_text(&before.text(field), &after.text(field)),
+        })
+        .collect();
+    Ok(RevisionDiff { from, to, fields })
+}
+
+/// Restores a memo's fields from one of its revisions. The restore is a new
+/// revision itself, so it can be undone the same way, and it is synced like
+/// any other edit.
+pub async fn revert_to_revision<R: Runtime>(
+    app: &AppHandle<R>,
+    pool: &SqlitePool,
+    memo_id: &str,
+    revision_id: i64,
+) -> AppResult<Revision> {
+    let target = get_revision(pool, memo_id, revision_id).await?;
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query(
+        "UPDATE voice_memos
+         SET name = ?, transcription = ?, translate = ?, summary = ?, updated_at = ?,
+             version = version + 1, dirty = 1
+         WHERE id = ? AND deleted_at IS NULL",
+    )
+    .bind(&target.title)
+    .bind(&target.transcript)
+    .bind(&target.translate)
+    .bind(&target.summary)
+    .bind(timestamp(Utc::now()))
+    .bind(memo_id)
+    .execute(&mut *tx)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found(format!("Memo {} not found", memo_id)));
+    }
+    replace_tags(&mut tx, memo_id, &target.tags).await?;
+    record(&mut tx, memo_id, RevisionSource::Revert).await?;
+    let reverted = latest(&mut tx, memo_id)
+        .await?
+        .ok_or_else(|| AppError::internal("Memo should have a revision after revert"))?;
+    tx.commit().await?;
+    println!("⏪ Memo {} reverted to revision {}", memo_id, revision_id);
+    emit_memo_updated(app);
+    Ok(reverted)
+}
@@ -0,0 +1,177 @@
+use crate::error::{AppError, AppResult};
+use crate::local_store::load_memos;
+use crate::memo_ops::{MemoSummary, MAX_PAGE_SIZE};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+const DEFAULT_LIMIT: u32 = 20;
+/// Words of context around the matches in a snippet.
+const SNIPPET_WORDS: u32 = 16;
+// Private-use characters mark matches inside SQLite; they become <mark> tags
+// after the rest of the text has been HTML-escaped.
+const MATCH_START: char = '\u{E000}';
+const MATCH_END: char = '\u{E001}';
+
+/// Arguments of `search_memos_command`.
+///
+/// `text` is a list of words that must all appear. `"quoted words"` match
+/// as a phrase and a trailing `*` matches any word with that prefix.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SearchQuery {
+    pub text: String,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SearchHit {
+    pub memo: MemoSummary,
+    /// Relevance; higher is better. Title matches weigh the most.
+    pub score: f64,
+    /// The title as HTML with matches wrapped in `<mark>`.
+    pub title_html: String,
+    /// The best matching passage as HTML with matches wrapped in `<mark>`.
+    pub snippet_html: String,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SearchResults {
+    pub hits: Vec<SearchHit>,
+    /// Matching memos in total, for paging with `offset`.
+    pub total: u32,
+}
+
+#[derive(sqlx::FromRow)]
+struct HitRow {
+    memo_id: String,
+    score: f64,
+    title: String,
+    snippet: String,
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Turns user input into an FTS5 query. Every word is quoted so operators
+/// and punctuation in the input can't break the query. Returns `None` when
+/// nothing searchable is left.
+fn to_match_expression(text: &str) -> Option<String> {
+    let mut terms = Vec::new();
+    for (i, part) in text.split('"').enumerate() {
+        // Odd parts sit between quotes.
+        if i % 2 == 1 {
+            let phrase = words(part);
+            if !phrase.is_empty() {
+                terms.push(format!("\"{}\"", phrase.join(" ")));
+            }
+            continue;
+        }
+        for token in part.split_whitespace() {
+            let prefix = token.ends_with('*');
+            let token_words = words(token);
+            let last = token_words.len().saturating_sub(1);
+            for (j, word) in token_words.into_iter().enumerate() {
+                let star = if prefix && j == last { "*" } else { "" };
+                terms.push(format!("\"{}\"{}", word, star));
+            }
+        }
+    }
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/// HTML-escapes `text` and turns the match markers into `<mark>` tags.
+fn to_html(text: &str) -> String {
+    let mut html = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            MATCH_START => html.push_str("<mark>"),
+            MATCH_END => html.push_str("</mark>"),
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            c => html.push(c),
+        }
+    }
+    html
+}
+
+/// Searches titles, transcripts, summaries and translations in the local
+/// database, best matches first.
+pub async fn search_memos(pool: &SqlitePool, query: &SearchQuery) -> AppResult<SearchResults> {
+    if query.text.trim().is_empty() {
+        return Err(AppError::validation("Search text must not be empty"));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(AppError::validation(format!(
+            "limit must be between 1 and {}",
+            MAX_PAGE_SIZE
+        )));
+    }
+    let Some(expression) = to_match_expression(&query.text) else {
+        return Ok(SearchResults::default());
+    };
+
+    // Memos in the trash stay indexed so restoring them needs no reindexing.
+    let total: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM memo_search
+         WHERE memo_search MATCH ?
+           AND memo_id IN (SELECT id FROM voice_memos WHERE deleted_at IS NULL)",
+    )
+    .bind(&expression)
+    .fetch_one(pool)
+    .await?;
+    let start = MATCH_START.to_string();
+    let end = MATCH_END.to_string();
+    // bm25 weights per column: memo_id, title, transcript, summary, translate.
+    let rows: Vec<HitRow> = sqlx::query_as(
+        "SELECT memo_id,
+                -bm25(memo_search, 0.0, 10.0, 4.0, 2.0, 2.0) AS score,
+                highlight(memo_search, 1, ?1, ?2) AS title,
+                snippet(memo_search, -1, ?1, ?2, '…', ?3) AS snippet
+         FROM memo_search
+         WHERE memo_search MATCH ?4
+           AND memo_id IN (SELECT id FROM voice_memos WHERE deleted_at IS NULL)
+         ORDER BY bm25(memo_search, 0.0, 10.0, 4.0, 2.0, 2.0)
+         LIMIT ?5 OFFSET ?6",
+    )
+    .bind(&start)
+    .bind(&end)
+    .bind(SNIPPET_WORDS as i64)
+    .bind(&expression)
+    .bind(limit as i64)
+    .bind(query.offset.unwrap_or(0) as i64)
+    .fetch_all(pool)
+    .await?;
+
+    let ids: Vec<&str> = rows.iter().map(|r| r.memo_id.as_str()).collect();
+    let mut memos = load_memos(pool, &ids).await?;
+    let hits = rows
+        .iter()
+        .filter_map(|row| {
+            let memo = memos.remove(&row.memo_id)?;
+            Some(SearchHit {
+                memo: MemoSummary::from(memo),
+                score: row.score,
+                title_html: to_html(&row.title),
+                snippet_html: to_html(&row.snippet),
+            })
+        })
+        .collect();
+    println!("🔎 Search for {:?} matched {} memo(s)", query.text, total);
+    Ok(SearchResults {
+        hits,
+        total: total as u32,
+    })
+}
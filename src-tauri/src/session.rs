@@ -0,0 +1,356 @@
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::events::{emit_auth_expired, emit_auth_expiring, emit_session_changed};
+use crate::local_store::timestamp;
+use crate::vault::{open_with, seal_with};
+use aes_gcm::aead::{KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use tauri::{AppHandle, Manager, Runtime};
+use zeroize::Zeroizing;
+
+/// The signed-in session, encrypted with a random key kept in the OS
+/// credential store under [`KEYRING_SERVICE`].
+pub(crate) const SESSION_FILE: &str = "session.bin";
+/// Credential store service of the session key; the account name is the
+/// id in [`PROFILE_ID_FILE`], so every profile has its own key.
+const KEYRING_SERVICE: &str = "SmartMemo session key";
+/// Random id of the profile in the data directory. Unlike the directory's
+/// path it stays the same when `AccountRegistry` moves the profile.
+pub(crate) const PROFILE_ID_FILE: &str = "profile.id";
+/// Fallback for the session key where no credential store is available,
+/// e.g. Linux without a Secret Service. The file is only protected by its
+/// permissions, so next to [`SESSION_FILE`] it obfuscates the token rather
+/// than protecting it from anyone who can read the data directory.
+pub(crate) const SESSION_KEY_FILE: &str = "session.key";
+
+/// How long before the token expires `auth:expiring` is sent.
+const EXPIRY_WARNING_MINUTES: i64 = 5;
+/// How often [`watch_expiry`] looks at the token.
+const EXPIRY_CHECK_SECS: u64 = 30;
+
+/// Claims the backend puts in its tokens.
+#[derive(Deserialize)]
+struct Claims {
+    sub: Option<String>,
+    email: Option<String>,
+    username: Option<String>,
+    /// Expiry, in seconds since the Unix epoch.
+    exp: Option<i64>,
+}
+
+impl Claims {
+    fn expires_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(self.exp?, 0)
+    }
+}
+
+/// Reads the claims of a JWT without verifying it; only the backend can do
+/// that.
+fn decode_claims(token: &str) -> Option<Claims> {
+    let payload = token.split('.').nth(1)?;
+    let json = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+    serde_json::from_slice(&json).ok()
+}
+
+/// The signed-in user, as shown to the frontend. The token itself never
+/// leaves the Rust side.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SessionInfo {
+    pub user_id: Option<String>,
+    pub email: String,
+    pub username: Option<String>,
+    pub logged_in_at: String,
+    /// From the token's `exp` claim; `None` for tokens that do not expire.
+    pub expires_at: Option<String>,
+}
+
+impl SessionInfo {
+    /// Describes the user `token` was issued to, who logged in as `email`.
+    pub fn from_token(token: &str, email: &str) -> Self {
+        let claims = decode_claims(token);
+        let expires_at = claims.as_ref().and_then(Claims::expires_at);
+        Self {
+            user_id: claims.as_ref().and_then(|c| c.sub.clone()),
+            email: claims
+                .as_ref()
+                .and_then(|c| c.email.clone())
+                .unwrap_or_else(|| email.to_string()),
+            username: claims.and_then(|c| c.username),
+            logged_in_at: timestamp(Utc::now()),
+            expires_at: expires_at.map(timestamp),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Session {
+    token: String,
+    info: SessionInfo,
+}
+
+/// Holds the backend token after `login_command` so commands no longer get
+/// it from the webview. The session is kept across restarts in an
+/// encrypted file in the app data directory.
+pub struct SessionState {
+    path: PathBuf,
+    cipher: Aes256Gcm,
+    current: RwLock<Option<Session>>,
+    /// Whether `auth:expiring` was already sent for the current session.
+    warned: AtomicBool,
+}
+
+/// Why a session ended without the user logging out.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiryReason {
+    /// The token's `exp` claim has passed.
+    Expired,
+    /// The backend answered 401 to a request made with the token.
+    Rejected,
+}
+
+/// Payload of the `auth:expired` event.
+#[derive(Serialize, Debug, Clone)]
+pub struct AuthExpired {
+    pub email: String,
+    pub reason: ExpiryReason,
+}
+
+/// Payload of the `auth:expiring` event.
+#[derive(Serialize, Debug, Clone)]
+pub struct AuthExpiring {
+    pub email: String,
+    pub expires_at: String,
+    pub seconds_left: i64,
+}
+
+impl SessionState {
+    /// Restores the session saved in `data_dir`, if any. A session that
+    /// cannot be read is dropped, so the user has to log in again.
+    pub fn load(data_dir: PathBuf) -> AppResult<Self> {
+        std::fs::create_dir_all(&data_dir)?;
+        let key = load_or_create_key(&data_dir)?;
+        let state = Self {
+            path: data_dir.join(SESSION_FILE),
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key[..])),
+            current: RwLock::new(None),
+            warned: AtomicBool::new(false),
+        };
+        match std::fs::read(&state.path) {
+            Ok(sealed) => match state.open(&sealed) {
+                Ok(session) => {
+                    println!("🔑 Restored session for {}", session.info.email);
+                    *state.current.write().unwrap() = Some(session);
+                }
+                Err(e) => {
+                    println!("⚠️ Discarding unreadable session: {}", e.message);
+                    let _ = std::fs::remove_file(&state.path);
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        Ok(state)
+    }
+
+    /// Whether `data_dir` holds a saved session.
+    pub fn is_stored(data_dir: &Path) -> bool {
+        data_dir.join(SESSION_FILE).exists()
+    }
+
+    fn open(&self, sealed: &[u8]) -> AppResult<Session> {
+        let json = Zeroizing::new(open_with(&self.cipher, sealed)?);
+        Ok(serde_json::from_slice(&json)?)
+    }
+
+    /// The token of the signed-in user; `Unauthorized` when logged out or
+    /// once the token has expired.
+    pub fn token(&self) -> AppResult<String> {
+        match &*self.current.read().unwrap() {
+            Some(session) if session.expires_at().is_some_and(|at| at <= Utc::now()) => Err(AppError::new(
+                ErrorKind::Unauthorized,
+                "Your session has expired, please log in again",
+            )),
+            Some(session) => Ok(session.token.clone()),
+            None => Err(AppError::new(ErrorKind::Unauthorized, "Not logged in")),
+        }
+    }
+
+    pub fn current(&self) -> Option<SessionInfo> {
+        self.current.read().unwrap().as_ref().map(|s| s.info.clone())
+    }
+
+    /// Replaces the session with one for `token`, signed in as `email`.
+    pub fn start(&self, token: String, email: &str) -> AppResult<SessionInfo> {
+        let info = SessionInfo::from_token(&token, email);
+        let session = Session { token, info };
+        let json = Zeroizing::new(serde_json::to_vec(&session)?);
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, seal_with(&self.cipher, &json)?)?;
+        std::fs::rename(&tmp, &self.path)?;
+
+        let info = session.info.clone();
+        *self.current.write().unwrap() = Some(session);
+        self.warned.store(false, Ordering::SeqCst);
+        println!("🔑 Logged in as {}", info.email);
+        Ok(info)
+    }
+
+    /// Forgets the session, in memory and on disk.
+    pub fn end(&self) -> AppResult<()> {
+        *self.current.write().unwrap() = None;
+        match std::fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        println!("🔑 Logged out");
+        Ok(())
+    }
+
+    /// Ends the session if it still uses `token`; a 401 for a request made
+    /// before the user logged in again must not end the new session.
+    fn expire(&self, token: &str) -> AppResult<Option<SessionInfo>> {
+        let info = match &*self.current.read().unwrap() {
+            Some(session) if session.token == token => session.info.clone(),
+            _ => return Ok(None),
+        };
+        self.end()?;
+        Ok(Some(info))
+    }
+}
+
+impl Session {
+    fn expires_at(&self) -> Option<DateTime<Utc>> {
+        decode_claims(&self.token)?.expires_at()
+    }
+}
+
+/// Ends the session `token` belongs to and sends every window back to the
+/// login screen with `auth:expired`.
+pub fn expire_session<R: Runtime>(app: &AppHandle<R>, token: &str, reason: ExpiryReason) {
+    let Some(state) = app.try_state::<SessionState>() else {
+        return;
+    };
+    match state.expire(token) {
+        Ok(Some(info)) => {
+            println!("⌛ Session for {} ended ({:?})", info.email, reason);
+            emit_session_changed(app, None);
+            emit_auth_expired(app, AuthExpired { email: info.email, reason });
+        }
+        Ok(None) => {}
+        Err(e) => println!("❌ Failed to end the expired session: {}", e.message),
+    }
+}
+
+/// Warns with `auth:expiring` shortly before the token expires and ends the
+/// session once it has. The backend has no refresh endpoint, so the user
+/// has to log in again.
+pub fn check_expiry<R: Runtime>(app: &AppHandle<R>, now: DateTime<Utc>) {
+    let state = app.state::<SessionState>();
+    let (token, email, expires_at) = match &*state.current.read().unwrap() {
+        Some(session) => match session.expires_at() {
+            Some(at) => (session.token.clone(), session.info.email.clone(), at),
+            None => return,
+        },
+        None => return,
+    };
+    if expires_at <= now {
+        expire_session(app, &token, ExpiryReason::Expired);
+    } else if expires_at - now <= Duration::minutes(EXPIRY_WARNING_MINUTES)
+        && !state.warned.swap(true, Ordering::SeqCst)
+    {
+        emit_auth_expiring(
+            app,
+            AuthExpiring {
+                email,
+                expires_at: timestamp(expires_at),
+                seconds_left: (expires_at - now).num_seconds(),
+            },
+        );
+    }
+}
+
+/// Runs [`check_expiry`] every [`EXPIRY_CHECK_SECS`] seconds.
+pub async fn watch_expiry<R: Runtime>(app: AppHandle<R>) {
+    let interval = std::time::Duration::from_secs(EXPIRY_CHECK_SECS);
+    loop {
+        check_expiry(&app, Utc::now());
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Returns the session key from the OS credential store, creating it (or
+/// moving it there from [`SESSION_KEY_FILE`]) on first use. Falls back to
+/// the file when the credential store cannot be used.
+fn load_or_create_key(data_dir: &Path) -> AppResult<Zeroizing<Vec<u8>>> {
+    let path = data_dir.join(SESSION_KEY_FILE);
+    match keyring_key(&profile_id(data_dir)?, &path) {
+        Ok(key) => Ok(key),
+        Err(e) => {
+            println!("⚠️ OS credential store unavailable, keeping the session key in {}: {}", SESSION_KEY_FILE, e);
+            load_or_create_key_file(&path)
+        }
+    }
+}
+
+/// Reads the id in [`PROFILE_ID_FILE`], creating it on first use.
+fn profile_id(data_dir: &Path) -> AppResult<String> {
+    let path = data_dir.join(PROFILE_ID_FILE);
+    match std::fs::read_to_string(&path) {
+        Ok(id) if !id.trim().is_empty() => return Ok(id.trim().to_string()),
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    let id = URL_SAFE_NO_PAD.encode(bytes);
+    std::fs::write(&path, &id)?;
+    Ok(id)
+}
+
+fn keyring_key(profile_id: &str, file: &Path) -> keyring::Result<Zeroizing<Vec<u8>>> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, profile_id)?;
+    match entry.get_secret() {
+        Ok(key) if key.len() == 32 => return Ok(Zeroizing::new(key)),
+        Ok(_) => println!("⚠️ Replacing invalid session key"),
+        Err(keyring::Error::NoEntry) => {}
+        Err(e) => return Err(e),
+    }
+    // Keeps sessions saved before the key moved to the credential store.
+    let key = match std::fs::read(file) {
+        Ok(key) if key.len() == 32 => Zeroizing::new(key),
+        _ => {
+            let mut key = Zeroizing::new(vec![0u8; 32]);
+            OsRng.fill_bytes(&mut key);
+            key
+        }
+    };
+    entry.set_secret(&key)?;
+    let _ = std::fs::remove_file(file);
+    Ok(key)
+}
+
+fn load_or_create_key_file(path: &Path) -> AppResult<Zeroizing<Vec<u8>>> {
+    match std::fs::read(path) {
+        Ok(key) if key.len() == 32 => return Ok(Zeroizing::new(key)),
+        Ok(_) => println!("⚠️ Replacing invalid session key"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    let mut key = Zeroizing::new(vec![0u8; 32]);
+    OsRng.fill_bytes(&mut key);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    std::io::Write::write_all(&mut options.open(path)?, &key)?;
+    Ok(key)
+}
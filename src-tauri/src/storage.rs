@@ -0,0 +1,185 @@
+use crate::audio_cache::AudioCache;
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::events::emit_storage_quota;
+use crate::upload::UploadManager;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::{AppHandle, Manager, Runtime};
+
+/// How many memos `largest` and `oldest` list.
+const TOP_MEMOS: usize = 10;
+
+const MB: u64 = 1024 * 1024;
+
+/// Limits on the space taken by recordings kept on this device: the audio
+/// store, including memos in the trash, and uploads still in progress.
+/// Both limits are off by default.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct QuotaConfig {
+    /// Above this a `storage:quota` warning is sent with every new recording.
+    pub soft_limit_mb: Option<u64>,
+    /// New recordings that would go above this are refused.
+    pub hard_limit_mb: Option<u64>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaState {
+    Ok,
+    /// Above the soft limit.
+    Warning,
+    /// At or above the hard limit.
+    Full,
+}
+
+/// Payload of the `storage:quota` event.
+#[derive(Serialize, Debug, Clone)]
+pub struct QuotaStatus {
+    pub used_bytes: u64,
+    pub soft_limit_bytes: Option<u64>,
+    pub hard_limit_bytes: Option<u64>,
+    pub state: QuotaState,
+}
+
+impl QuotaStatus {
+    fn new(config: &QuotaConfig, used_bytes: u64) -> Self {
+        let soft_limit_bytes = config.soft_limit_mb.map(|mb| mb * MB);
+        let hard_limit_bytes = config.hard_limit_mb.map(|mb| mb * MB);
+        let state = if hard_limit_bytes.is_some_and(|limit| used_bytes >= limit) {
+            QuotaState::Full
+        } else if soft_limit_bytes.is_some_and(|limit| used_bytes > limit) {
+            QuotaState::Warning
+        } else {
+            QuotaState::Ok
+        };
+        Self {
+            used_bytes,
+            soft_limit_bytes,
+            hard_limit_bytes,
+            state,
+        }
+    }
+}
+
+/// Space used by one memo in the local library.
+#[derive(Serialize, Debug, Clone, sqlx::FromRow)]
+pub struct MemoUsage {
+    pub id: String,
+    pub title: String,
+    pub created_at: String,
+    pub audio_bytes: i64,
+    /// Transcript, translation and summary, in UTF-8 bytes.
+    pub transcript_bytes: i64,
+    pub in_trash: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct StorageStats {
+    /// Memos in the library, including the trash.
+    pub memo_count: usize,
+    /// Recording sizes summed over memos; a recording shared by several
+    /// memos is counted for each.
+    pub audio_bytes: u64,
+    /// Recording files on disk, each stored once.
+    pub stored_audio_bytes: u64,
+    pub transcript_bytes: u64,
+    /// Recordings of memos in the trash.
+    pub trash_bytes: u64,
+    /// Recordings of memos synced with the backend, i.e. roughly what they
+    /// take up there.
+    pub synced_audio_bytes: u64,
+    pub database_bytes: u64,
+    pub cache_bytes: u64,
+    /// Recordings kept for interrupted chunked uploads.
+    pub pending_upload_bytes: u64,
+    /// Every memo, oldest first.
+    pub memos: Vec<MemoUsage>,
+    pub largest: Vec<MemoUsage>,
+    pub oldest: Vec<MemoUsage>,
+    pub quota: QuotaStatus,
+}
+
+/// Size of the recording files in the audio store.
+async fn stored_audio_bytes(pool: &SqlitePool) -> AppResult<u64> {
+    let bytes: i64 = sqlx::query_scalar("SELECT COALESCE(SUM(size), 0) FROM audio_blobs")
+        .fetch_one(pool)
+        .await?;
+    Ok(bytes as u64)
+}
+
+/// Current quota usage; see [`QuotaConfig`].
+pub async fn quota_status<R: Runtime>(app: &AppHandle<R>) -> AppResult<QuotaStatus> {
+    let used = stored_audio_bytes(&app.state::<SqlitePool>()).await?
+        + app.state::<UploadManager>().disk_usage().await?;
+    Ok(QuotaStatus::new(&app.state::<QuotaConfig>(), used))
+}
+
+/// Called before a new recording of `incoming` bytes is saved. Fails with
+/// `QuotaExceeded` if it would go above the hard limit, and emits a
+/// `storage:quota` warning if it goes above the soft limit.
+pub async fn check_quota<R: Runtime>(app: &AppHandle<R>, incoming: u64) -> AppResult<()> {
+    let current = quota_status(app).await?;
+    let after = QuotaStatus::new(&app.state::<QuotaConfig>(), current.used_bytes + incoming);
+    match after.state {
+        QuotaState::Ok => Ok(()),
+        QuotaState::Warning => {
+            emit_storage_quota(app, after);
+            Ok(())
+        }
+        QuotaState::Full => {
+            let limit = after.hard_limit_bytes.unwrap_or_default() / MB;
+            emit_storage_quota(app, after);
+            Err(AppError::new(
+                ErrorKind::QuotaExceeded,
+                format!(
+                    "Local storage is limited to {} MB; empty the trash or delete memos to record more",
+                    limit
+                ),
+            ))
+        }
+    }
+}
+
+pub async fn storage_stats<R: Runtime>(app: &AppHandle<R>) -> AppResult<StorageStats> {
+    let pool = app.state::<SqlitePool>();
+    let memos: Vec<MemoUsage> = sqlx::query_as(
+        "SELECT id, name AS title, date AS created_at, audio_size AS audio_bytes,
+                COALESCE(length(CAST(transcription AS BLOB)), 0)
+                    + COALESCE(length(CAST(translate AS BLOB)), 0)
+                    + COALESCE(length(CAST(summary AS BLOB)), 0) AS transcript_bytes,
+                deleted_at IS NOT NULL AS in_trash
+         FROM voice_memos ORDER BY date, id",
+    )
+    .fetch_all(&*pool)
+    .await?;
+    let synced_audio_bytes: i64 =
+        sqlx::query_scalar("SELECT COALESCE(SUM(audio_size), 0) FROM voice_memos WHERE remote_id IS NOT NULL")
+            .fetch_one(&*pool)
+            .await?;
+    let (page_count, page_size): (i64, i64) =
+        sqlx::query_as("SELECT page_count, page_size FROM pragma_page_count(), pragma_page_size()")
+            .fetch_one(&*pool)
+            .await?;
+
+    let mut largest = memos.clone();
+    largest.sort_by_key(|m| std::cmp::Reverse(m.audio_bytes));
+    largest.truncate(TOP_MEMOS);
+    let oldest = memos.iter().take(TOP_MEMOS).cloned().collect();
+
+    Ok(StorageStats {
+        memo_count: memos.len(),
+        audio_bytes: memos.iter().map(|m| m.audio_bytes as u64).sum(),
+        stored_audio_bytes: stored_audio_bytes(&pool).await?,
+        transcript_bytes: memos.iter().map(|m| m.transcript_bytes as u64).sum(),
+        trash_bytes: memos.iter().filter(|m| m.in_trash).map(|m| m.audio_bytes as u64).sum(),
+        synced_audio_bytes: synced_audio_bytes as u64,
+        database_bytes: (page_count * page_size) as u64,
+        cache_bytes: app.state::<AudioCache>().size().await?,
+        pending_upload_bytes: app.state::<UploadManager>().disk_usage().await?,
+        largest,
+        oldest,
+        memos,
+        quota: quota_status(app).await?,
+    })
+}
@@ -0,0 +1,545 @@
+use crate::api_client::ApiClient;
+use crate::audio;
+use crate::audio_cache::AudioCache;
+use crate::audio_store::AudioStore;
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::events::{emit_memo_updated, emit_sync_conflict, emit_sync_progress};
+use crate::idempotency::IdempotencyStore;
+use crate::local_store::{load_memo, replace_tags, timestamp};
+use crate::memo_ops::{self, MemoQuery, VoiceMemo, MAX_PAGE_SIZE};
+use crate::revisions::{self, RevisionSource};
+use crate::trash;
+use crate::upload::UploadManager;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use tauri::{AppHandle, Manager, Runtime};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+/// What to do when a memo was edited both here and on the backend since the
+/// last sync.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    LocalWins,
+    RemoteWins,
+    /// The most recently edited side wins. Backend memos without
+    /// `updated_at` count as older.
+    #[default]
+    NewestWins,
+    /// Keep the local edit as a separate "conflicted copy" and take the
+    /// backend version for the original.
+    KeepBoth,
+}
+
+impl std::str::FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "local_wins" => Ok(ConflictPolicy::LocalWins),
+            "remote_wins" => Ok(ConflictPolicy::RemoteWins),
+            "newest_wins" => Ok(ConflictPolicy::NewestWins),
+            "keep_both" => Ok(ConflictPolicy::KeepBoth),
+            other => Err(format!("Unknown conflict policy: {}", other)),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SyncConfig {
+    pub conflict_policy: ConflictPolicy,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncPhase {
+    Pull,
+    Push,
+}
+
+/// Payload of the `sync:progress` event.
+#[derive(Serialize, Debug, Clone)]
+pub struct SyncProgress {
+    pub phase: SyncPhase,
+    pub done: usize,
+    pub total: usize,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Resolution {
+    KeptLocal,
+    KeptRemote,
+    KeptBoth,
+}
+
+/// Payload of the `sync:conflict` event.
+#[derive(Serialize, Debug, Clone)]
+pub struct SyncConflict {
+    pub memo_id: String,
+    pub title: String,
+    pub policy: ConflictPolicy,
+    pub resolution: Resolution,
+}
+
+/// Outcome of one sync run.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SyncReport {
+    /// Backend memos created or updated locally.
+    pub pulled: usize,
+    /// Local memos created or updated on the backend.
+    pub pushed: usize,
+    /// Memos removed locally because they were deleted on the backend.
+    pub deleted_local: usize,
+    /// Local deletions applied to the backend.
+    pub deleted_remote: usize,
+    pub conflicts: Vec<SyncConflict>,
+}
+
+#[derive(sqlx::FromRow)]
+struct SyncRow {
+    id: String,
+    title: String,
+    remote_id: Option<String>,
+    version: i64,
+    dirty: bool,
+    remote_hash: Option<String>,
+    updated_at: Option<String>,
+}
+
+/// Fingerprint of the fields both sides can edit, used to tell whether the
+/// backend copy changed since the last sync.
+fn content_hash(memo: &VoiceMemo) -> String {
+    let text = |t: &Option<String>| t.clone().filter(|t| !t.is_empty());
+    let fields = serde_json::json!([
+        memo.title,
+        text(&memo.transcript),
+        text(&memo.translate),
+        text(&memo.summary),
+        memo.tags.clone().unwrap_or_default(),
+    ]);
+    format!("{:x}", Sha256::digest(fields.to_string().as_bytes()))
+}
+
+pub(crate) fn normalize_timestamp(value: &str) -> String {
+    DateTime::parse_from_rfc3339(value)
+        .map(|at| timestamp(at.with_timezone(&Utc)))
+        .unwrap_or_else(|_| value.to_string())
+}
+
+fn is_newer(remote: Option<&str>, local: Option<&str>) -> bool {
+    let parse = |v: Option<&str>| v.and_then(|v| DateTime::parse_from_rfc3339(v).ok());
+    match (parse(remote), parse(local)) {
+        (Some(remote), Some(local)) => remote > local,
+        (Some(_), None) => true,
+        _ => false,
+    }
+}
+
+/// Reconciles the local database with the backend: pulls backend changes,
+/// pushes local edits and deletions, and settles memos edited on both sides
+/// according to the configured [`ConflictPolicy`].
+pub struct SyncEngine {
+    pool: SqlitePool,
+    config: SyncConfig,
+    running: Mutex<()>,
+}
+
+impl SyncEngine {
+    pub fn new(pool: SqlitePool, config: SyncConfig) -> Self {
+        Self {
+            pool,
+            config,
+            running: Mutex::new(()),
+        }
+    }
+
+    pub async fn sync<R: Runtime>(&self, app: &AppHandle<R>, token: &str) -> AppResult<SyncReport> {
+        let _running = self
+            .running
+            .try_lock()
+            .map_err(|_| AppError::new(ErrorKind::Conflict, "A sync is already running"))?;
+        let api = app.state::<ApiClient>();
+        let mut report = SyncReport::default();
+
+        println!("🔄 Sync started");
+        let remote = self.fetch_remote(&api, token).await?;
+        self.pull(app, &api, token, &remote, &mut report).await?;
+        self.push(app, &api, token, &mut report).await?;
+
+        if report.pulled > 0 || report.deleted_local > 0 || !report.conflicts.is_empty() {
+            emit_memo_updated(app);
+        }
+        println!(
+            "✅ Sync finished: {} pulled, {} pushed, {} deleted locally, {} deleted remotely, {} conflicts",
+            report.pulled,
+            report.pushed,
+            report.deleted_local,
+            report.deleted_remote,
+            report.conflicts.len()
+        );
+        Ok(report)
+    }
+
+    async fn fetch_remote(&self, api: &ApiClient, token: &str) -> AppResult<Vec<VoiceMemo>> {
+        let mut query = MemoQuery {
+            page_size: Some(MAX_PAGE_SIZE),
+            ..MemoQuery::default()
+        };
+        let mut memos = Vec::new();
+        loop {
+            let page = memo_ops::get_memos(api, token, &query).await?;
+            memos.extend(page.items);
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => return Ok(memos),
+            }
+        }
+    }
+
+    async fn synced_rows(&self) -> AppResult<HashMap<String, SyncRow>> {
+        let rows: Vec<SyncRow> = sqlx::query_as(
+            "SELECT id, name AS title, remote_id, version, dirty, remote_hash, updated_at
+             FROM voice_memos WHERE remote_id IS NOT NULL",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| row.remote_id.clone().map(|remote_id| (remote_id, row)))
+            .collect())
+    }
+
+    async fn pull<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        api: &ApiClient,
+        token: &str,
+        remote: &[VoiceMemo],
+        report: &mut SyncReport,
+    ) -> AppResult<()> {
+        let tombstones: HashSet<String> = sqlx::query_scalar("SELECT remote_id FROM sync_tombstones")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .collect();
+        let locals = self.synced_rows().await?;
+
+        for (done, memo) in remote.iter().enumerate() {
+            emit_sync_progress(
+                app,
+                SyncProgress {
+                    phase: SyncPhase::Pull,
+                    done,
+                    total: remote.len(),
+                },
+            );
+            if tombstones.contains(&memo.id) {
+                continue;
+            }
+            let hash = content_hash(memo);
+            match locals.get(&memo.id) {
+                None => {
+                    self.insert_remote(app, api, token, memo, &hash).await?;
+                    report.pulled += 1;
+                }
+                Some(local) if local.remote_hash.as_deref() == Some(hash.as_str()) => {}
+                Some(local) if !local.dirty => {
+                    if self.apply_remote(local, memo, &hash).await? {
+                        report.pulled += 1;
+                    }
+                }
+                Some(local) => {
+                    let conflict = self.resolve(local, memo, &hash).await?;
+                    if conflict.resolution != Resolution::KeptLocal {
+                        report.pulled += 1;
+                    }
+                    emit_sync_conflict(app, conflict.clone());
+                    report.conflicts.push(conflict);
+                }
+            }
+        }
+
+        // Synced memos missing from the backend were deleted there.
+        let remote_ids: HashSet<&str> = remote.iter().map(|m| m.id.as_str()).collect();
+        for (remote_id, local) in &locals {
+            if remote_ids.contains(remote_id.as_str()) {
+                continue;
+            }
+            if local.dirty {
+                // Edited here, deleted there: keep the edit and upload it again.
+                sqlx::query("UPDATE voice_memos SET remote_id = NULL, remote_hash = NULL WHERE id = ?")
+                    .bind(&local.id)
+                    .execute(&self.pool)
+                    .await?;
+                let conflict = SyncConflict {
+                    memo_id: local.id.clone(),
+                    title: local.title.clone(),
+                    policy: self.config.conflict_policy,
+                    resolution: Resolution::KeptLocal,
+                };
+                emit_sync_conflict(app, conflict.clone());
+                report.conflicts.push(conflict);
+            } else {
+                sqlx::query("DELETE FROM voice_memos WHERE id = ?")
+                    .bind(&local.id)
+                    .execute(&self.pool)
+                    .await?;
+                report.deleted_local += 1;
+            }
+        }
+
+        emit_sync_progress(
+            app,
+            SyncProgress {
+                phase: SyncPhase::Pull,
+                done: remote.len(),
+                total: remote.len(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Stores a memo that only exists on the backend.
+    async fn insert_remote<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        api: &ApiClient,
+        token: &str,
+        memo: &VoiceMemo,
+        hash: &str,
+    ) -> AppResult<()> {
+        let cache = app.state::<AudioCache>();
+        let audio = match &memo.audio_blob {
+            Some(audio) => audio.clone(),
+            None => memo_ops::get_memo_audio(api, &cache, &memo.id, token).await?,
+        };
+        let now = timestamp(Utc::now());
+
+        let mut tx = self.pool.begin().await?;
+        let audio_hash = app.state::<AudioStore>().put(&mut tx, &audio).await?;
+        sqlx::query(
+            "INSERT INTO voice_memos
+                (id, name, date, duration, audio_blob, audio_hash, transcription, translate, summary,
+                 audio_mime, audio_size, updated_at, remote_id, dirty, remote_hash, synced_at)
+             VALUES (?, ?, ?, ?, X'', ?, ?, ?, ?, ?, ?, ?, ?, 0, ?, ?)",
+        )
+        .bind(&memo.id)
+        .bind(&memo.title)
+        .bind(normalize_timestamp(&memo.created_at))
+        .bind(&memo.duration)
+        .bind(&audio_hash)
+        .bind(&memo.transcript)
+        .bind(&memo.translate)
+        .bind(&memo.summary)
+        .bind(audio::sniff_mime(&audio))
+        .bind(audio.len() as i64)
+        .bind(memo.updated_at.as_deref().map(normalize_timestamp).unwrap_or_else(|| now.clone()))
+        .bind(&memo.id)
+        .bind(hash)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+        replace_tags(&mut tx, &memo.id, memo.tags.as_deref().unwrap_or_default()).await?;
+        revisions::record(&mut tx, &memo.id, RevisionSource::Sync).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Overwrites the local copy with the backend's fields. Returns `false`
+    /// if the memo was edited locally in the meantime.
+    async fn apply_remote(&self, local: &SyncRow, memo: &VoiceMemo, hash: &str) -> AppResult<bool> {
+        let now = timestamp(Utc::now());
+        let mut tx = self.pool.begin().await?;
+        revisions::ensure_baseline(&mut tx, &local.id).await?;
+        let result = sqlx::query(
+            "UPDATE voice_memos
+             SET name = ?, transcription = ?, translate = ?, summary = ?, updated_at = ?,
+                 version = version + 1, dirty = 0, remote_hash = ?, synced_at = ?
+             WHERE id = ? AND version = ?",
+        )
+        .bind(&memo.title)
+        .bind(&memo.transcript)
+        .bind(&memo.translate)
+        .bind(&memo.summary)
+        .bind(memo.updated_at.as_deref().map(normalize_timestamp).unwrap_or_else(|| now.clone()))
+        .bind(hash)
+        .bind(&now)
+        .bind(&local.id)
+        .bind(local.version)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        replace_tags(&mut tx, &local.id, memo.tags.as_deref().unwrap_or_default()).await?;
+        revisions::record(&mut tx, &local.id, RevisionSource::Sync).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn resolve(&self, local: &SyncRow, memo: &VoiceMemo, hash: &str) -> AppResult<SyncConflict> {
+        let policy = self.config.conflict_policy;
+        let resolution = match policy {
+            ConflictPolicy::LocalWins => Resolution::KeptLocal,
+            ConflictPolicy::RemoteWins => Resolution::KeptRemote,
+            ConflictPolicy::NewestWins if is_newer(memo.updated_at.as_deref(), local.updated_at.as_deref()) => {
+                Resolution::KeptRemote
+            }
+            ConflictPolicy::NewestWins => Resolution::KeptLocal,
+            ConflictPolicy::KeepBoth => Resolution::KeptBoth,
+        };
+        println!("⚔️ Sync conflict on memo {} resolved as {:?}", local.id, resolution);
+
+        match resolution {
+            Resolution::KeptLocal => {
+                // Acknowledge the backend version; the push overwrites it.
+                sqlx::query("UPDATE voice_memos SET remote_hash = ? WHERE id = ?")
+                    .bind(hash)
+                    .bind(&local.id)
+                    .execute(&self.pool)
+                    .await?;
+            }
+            Resolution::KeptRemote => {
+                self.apply_remote(local, memo, hash).await?;
+            }
+            Resolution::KeptBoth => {
+                self.copy_as_new(&local.id).await?;
+                self.apply_remote(local, memo, hash).await?;
+            }
+        }
+
+        Ok(SyncConflict {
+            memo_id: local.id.clone(),
+            title: local.title.clone(),
+            policy,
+            resolution,
+        })
+    }
+
+    /// Duplicates a memo as a new, unsynced "conflicted copy".
+    async fn copy_as_new(&self, id: &str) -> AppResult<String> {
+        let copy_id = Uuid::new_v4().to_string();
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO voice_memos
+                (id, user_id, name, date, duration, audio_blob, audio_hash, transcription, translate, summary,
+                 audio_mime, audio_size, updated_at)
+             SELECT ?, user_id, name || ' (conflicted copy)', date, duration, audio_blob, audio_hash, transcription,
+                    translate, summary, audio_mime, audio_size, updated_at
+             FROM voice_memos WHERE id = ?",
+        )
+        .bind(&copy_id)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "INSERT INTO memo_tags (memo_id, position, tag)
+             SELECT ?, position, tag FROM memo_tags WHERE memo_id = ?",
+        )
+        .bind(&copy_id)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(copy_id)
+    }
+
+    async fn push<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        api: &ApiClient,
+        token: &str,
+        report: &mut SyncReport,
+    ) -> AppResult<()> {
+        let tombstones: Vec<String> = sqlx::query_scalar("SELECT remote_id FROM sync_tombstones")
+            .fetch_all(&self.pool)
+            .await?;
+        // Trashed memos wait until they are restored or purged.
+        let dirty: Vec<(String, i64, Option<String>)> = sqlx::query_as(
+            "SELECT id, version, remote_id FROM voice_memos WHERE dirty = 1 AND deleted_at IS NULL ORDER BY date",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let total = tombstones.len() + dirty.len();
+        let mut done = 0;
+
+        for remote_id in tombstones {
+            emit_sync_progress(app, SyncProgress { phase: SyncPhase::Push, done, total });
+            if trash::send_tombstone(app, api, &self.pool, token, &remote_id).await? {
+                report.deleted_remote += 1;
+            }
+            done += 1;
+        }
+
+        for (id, version, remote_id) in dirty {
+            emit_sync_progress(app, SyncProgress { phase: SyncPhase::Push, done, total });
+            done += 1;
+            let Some(memo) = load_memo(&self.pool, &app.state::<AudioStore>(), &id).await? else {
+                continue;
+            };
+
+            let remote_id = match remote_id {
+                Some(remote_id) => remote_id,
+                None => {
+                    let keys = app.state::<IdempotencyStore>();
+                    let uploads = app.state::<UploadManager>();
+                    let created = memo_ops::save_audio(
+                        app,
+                        api,
+                        &keys,
+                        &uploads,
+                        token,
+                        memo.audio_blob.clone().unwrap_or_default(),
+                        memo.duration.clone(),
+                        memo.tags.clone().unwrap_or_default(),
+                    )
+                    .await?;
+                    // Record the link right away so a failure below cannot
+                    // upload the recording twice.
+                    sqlx::query("UPDATE voice_memos SET remote_id = ? WHERE id = ?")
+                        .bind(&created.id)
+                        .bind(&id)
+                        .execute(&self.pool)
+                        .await?;
+                    created.id
+                }
+            };
+
+            memo_ops::save_memo(
+                app,
+                api,
+                token,
+                &remote_id,
+                &memo.title,
+                memo.transcript.clone(),
+                memo.translate.clone(),
+                memo.summary.clone(),
+                memo.tags.clone(),
+            )
+            .await?;
+
+            // Edits made while we were pushing keep the memo dirty.
+            sqlx::query(
+                "UPDATE voice_memos
+                 SET dirty = CASE WHEN version = ? THEN 0 ELSE dirty END, remote_hash = ?, synced_at = ?
+                 WHERE id = ?",
+            )
+            .bind(version)
+            .bind(content_hash(&memo))
+            .bind(timestamp(Utc::now()))
+            .bind(&id)
+            .execute(&self.pool)
+            .await?;
+            report.pushed += 1;
+        }
+
+        emit_sync_progress(app, SyncProgress { phase: SyncPhase::Push, done: total, total });
+        Ok(())
+    }
+}
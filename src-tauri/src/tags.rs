@@ -0,0 +1,145 @@
+use crate::error::{AppError, AppResult};
+use crate::events::emit_memo_updated;
+use crate::local_store::{replace_tags, timestamp};
+use crate::revisions::{self, RevisionSource};
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, HashSet};
+use tauri::{AppHandle, Runtime};
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TagCount {
+    pub tag: String,
+    /// Number of memos carrying the tag.
+    pub count: u32,
+}
+
+/// Canonical form of a tag: trimmed, inner whitespace collapsed to single
+/// spaces and lowercased, so "Work" and " work " are the same tag.
+pub fn normalize_tag(tag: &str) -> String {
+    tag.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// Normalizes tags, dropping empty ones and duplicates but keeping order.
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    tags.iter()
+        .map(|t| normalize_tag(t))
+        .filter(|t| !t.is_empty() && seen.insert(t.clone()))
+        .collect()
+}
+
+fn required(tag: &str, what: &str) -> AppResult<String> {
+    let tag = normalize_tag(tag);
+    if tag.is_empty() {
+        return Err(AppError::validation(format!("{} must not be empty", what)));
+    }
+    Ok(tag)
+}
+
+/// All tags on memos outside the trash with the number of memos using
+/// each, most used first.
+pub async fn list_tags(pool: &SqlitePool) -> AppResult<Vec<TagCount>> {
+    let rows: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT tag, COUNT(DISTINCT memo_id) AS count FROM memo_tags
+         WHERE memo_id IN (SELECT id FROM voice_memos WHERE deleted_at IS NULL)
+         GROUP BY tag ORDER BY count DESC, tag",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(tag, count)| TagCount { tag, count: count as u32 })
+        .collect())
+}
+
+/// Rewrites the tags of every memo in one transaction and emits a single
+/// `memo:updated`. Changed memos are marked for the next sync. Returns the
+/// number of memos changed.
+async fn rewrite_tags<R: Runtime>(
+    app: &AppHandle<R>,
+    pool: &SqlitePool,
+    rewrite: impl Fn(&str) -> Option<String>,
+) -> AppResult<usize> {
+    // Read in the same transaction, so tags saved meanwhile are not lost.
+    let mut tx = pool.begin().await?;
+    let rows: Vec<(String, String)> = sqlx::query_as("SELECT memo_id, tag FROM memo_tags ORDER BY memo_id, position")
+        .fetch_all(&mut *tx)
+        .await?;
+    let mut by_memo: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (memo_id, tag) in rows {
+        by_memo.entry(memo_id).or_default().push(tag);
+    }
+
+    let now = timestamp(Utc::now());
+    let mut changed = 0;
+    for (memo_id, tags) in by_memo {
+        let rewritten: Vec<String> = tags.iter().filter_map(|t| rewrite(t)).collect();
+        let rewritten = normalize_tags(&rewritten);
+        if rewritten == tags {
+            continue;
+        }
+        revisions::ensure_baseline(&mut tx, &memo_id).await?;
+        replace_tags(&mut tx, &memo_id, &rewritten).await?;
+        sqlx::query("UPDATE voice_memos SET updated_at = ?, version = version + 1, dirty = 1 WHERE id = ?")
+            .bind(&now)
+            .bind(&memo_id)
+            .execute(&mut *tx)
+            .await?;
+        revisions::record(&mut tx, &memo_id, RevisionSource::Edit).await?;
+        changed += 1;
+    }
+    tx.commit().await?;
+
+    if changed > 0 {
+        emit_memo_updated(app);
+    }
+    Ok(changed)
+}
+
+/// Renames a tag on every memo. Memos that already carry `to` keep it once,
+/// so renaming onto an existing tag merges the two.
+pub async fn rename_tag<R: Runtime>(app: &AppHandle<R>, pool: &SqlitePool, from: &str, to: &str) -> AppResult<usize> {
+    let from = required(from, "Tag")?;
+    let to = required(to, "New tag name")?;
+    let changed = rewrite_tags(app, pool, |t| {
+        Some(if normalize_tag(t) == from { to.clone() } else { t.to_string() })
+    })
+    .await?;
+    println!("🏷️ Renamed tag {:?} to {:?} on {} memo(s)", from, to, changed);
+    Ok(changed)
+}
+
+/// Replaces every tag in `sources` with `target`.
+pub async fn merge_tags<R: Runtime>(
+    app: &AppHandle<R>,
+    pool: &SqlitePool,
+    sources: &[String],
+    target: &str,
+) -> AppResult<usize> {
+    let target = required(target, "Target tag")?;
+    let sources: HashSet<String> = sources.iter().map(|t| normalize_tag(t)).collect();
+    let changed = rewrite_tags(app, pool, |t| {
+        Some(if sources.contains(&normalize_tag(t)) { target.clone() } else { t.to_string() })
+    })
+    .await?;
+    println!("🏷️ Merged {} tag(s) into {:?} on {} memo(s)", sources.len(), target, changed);
+    Ok(changed)
+}
+
+/// Removes a tag from every memo.
+pub async fn delete_tag<R: Runtime>(app: &AppHandle<R>, pool: &SqlitePool, tag: &str) -> AppResult<usize> {
+    let tag = required(tag, "Tag")?;
+    let changed = rewrite_tags(app, pool, |t| (normalize_tag(t) != tag).then(|| t.to_string())).await?;
+    println!("🏷️ Deleted tag {:?} from {} memo(s)", tag, changed);
+    Ok(changed)
+}
+
+/// Normalizes every stored tag, merging tags that differ only in case or
+/// whitespace (saved before tags were normalized on save).
+pub async fn normalize_all_tags<R: Runtime>(app: &AppHandle<R>, pool: &SqlitePool) -> AppResult<usize> {
+    let changed = rewrite_tags(app, pool, |t| Some(t.to_string())).await?;
+    println!("🏷️ Normalized tags on {} memo(s)", changed);
+    Ok(changed)
+}
@@ -0,0 +1,285 @@
+use crate::api_client::ApiClient;
+use crate::audio;
+use crate::audio_cache::AudioCache;
+use crate::audio_store::AudioStore;
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::events::emit_memo_updated;
+use crate::local_store::{load_memos, replace_tags, timestamp};
+use crate::memo_ops::{self, MemoSummary};
+use crate::session::SessionState;
+use crate::sync::normalize_timestamp;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use tauri::{AppHandle, Manager, Runtime};
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TrashConfig {
+    /// Days a memo stays in the trash before it is purged; 0 keeps it
+    /// until the trash is emptied.
+    pub retention_days: u32,
+    pub purge_interval_minutes: u64,
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        Self {
+            retention_days: 30,
+            purge_interval_minutes: 60,
+        }
+    }
+}
+
+impl TrashConfig {
+    fn retention(&self) -> Option<Duration> {
+        (self.retention_days > 0).then(|| Duration::days(self.retention_days as i64))
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TrashedMemo {
+    pub memo: MemoSummary,
+    pub deleted_at: String,
+    /// When the memo will be purged, if automatic purging is on.
+    pub purge_at: Option<String>,
+}
+
+/// Moves one memo to the trash. Fails with `NotFound` if it does not exist
+/// or is already trashed.
+pub async fn trash_memo(pool: &SqlitePool, id: &str) -> AppResult<()> {
+    let result = sqlx::query("UPDATE voice_memos SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL")
+        .bind(timestamp(Utc::now()))
+        .bind(id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found(format!("Memo {} not found", id)));
+    }
+    println!("🗑️ Memo moved to trash with ID: {}", id);
+    Ok(())
+}
+
+/// Moves a backend memo to the trash in remote mode. A copy of the memo and
+/// its recording is kept locally, and the backend copy is only deleted once
+/// the trashed memo is purged, so the deletion can be undone until then.
+pub async fn trash_remote<R: Runtime>(app: &AppHandle<R>, token: &str, id: &str) -> AppResult<()> {
+    let pool = app.state::<SqlitePool>();
+    let cache = app.state::<AudioCache>();
+    // A local copy from an earlier sync is trashed like any local memo.
+    let local: Option<(String, Option<String>)> =
+        sqlx::query_as("SELECT id, deleted_at FROM voice_memos WHERE remote_id = ?")
+            .bind(id)
+            .fetch_optional(&*pool)
+            .await?;
+    match local {
+        Some((_, Some(_))) => return Err(AppError::not_found(format!("Memo {} not found", id))),
+        Some((local_id, None)) => {
+            trash_memo(&pool, &local_id).await?;
+            cache.remove(id).await;
+            return Ok(());
+        }
+        None => {}
+    }
+
+    let api = app.state::<ApiClient>();
+    let memo = memo_ops::get_memo(&api, id, token)
+        .await?
+        .ok_or_else(|| AppError::not_found(format!("Memo {} not found", id)))?;
+    let recording = memo_ops::get_memo_audio(&api, &cache, id, token).await?;
+    let now = timestamp(Utc::now());
+
+    let mut tx = pool.begin().await?;
+    let hash = app.state::<AudioStore>().put(&mut tx, &recording).await?;
+    sqlx::query(
+        "INSERT INTO voice_memos
+            (id, name, date, duration, audio_blob, audio_hash, transcription, translate, summary,
+             audio_mime, audio_size, updated_at, remote_id, dirty, deleted_at)
+         VALUES (?, ?, ?, ?, X'', ?, ?, ?, ?, ?, ?, ?, ?, 0, ?)",
+    )
+    .bind(&memo.id)
+    .bind(&memo.title)
+    .bind(normalize_timestamp(&memo.created_at))
+    .bind(&memo.duration)
+    .bind(&hash)
+    .bind(&memo.transcript)
+    .bind(&memo.translate)
+    .bind(&memo.summary)
+    .bind(audio::sniff_mime(&recording))
+    .bind(recording.len() as i64)
+    .bind(memo.updated_at.as_deref().map(normalize_timestamp).unwrap_or_else(|| now.clone()))
+    .bind(&memo.id)
+    .bind(&now)
+    .execute(&mut *tx)
+    .await?;
+    replace_tags(&mut tx, &memo.id, memo.tags.as_deref().unwrap_or_default()).await?;
+    tx.commit().await?;
+    cache.remove(id).await;
+    println!("🗑️ Memo moved to trash with ID: {}", id);
+    Ok(())
+}
+
+/// Backend ids of memos in the trash, or purged before the backend was
+/// told. Remote listings leave them out.
+pub async fn hidden_remote_ids(pool: &SqlitePool) -> AppResult<HashSet<String>> {
+    let ids: Vec<String> = sqlx::query_scalar(
+        "SELECT remote_id FROM voice_memos WHERE deleted_at IS NOT NULL AND remote_id IS NOT NULL
+         UNION SELECT remote_id FROM sync_tombstones",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(ids.into_iter().collect())
+}
+
+/// Moves every memo to the trash and returns how many were moved.
+pub async fn trash_all(pool: &SqlitePool) -> AppResult<usize> {
+    let result = sqlx::query("UPDATE voice_memos SET deleted_at = ? WHERE deleted_at IS NULL")
+        .bind(timestamp(Utc::now()))
+        .execute(pool)
+        .await?;
+    println!("🗑️ Moved {} memos to trash", result.rows_affected());
+    Ok(result.rows_affected() as usize)
+}
+
+/// Memos in the trash, most recently deleted first.
+pub async fn list_trash(pool: &SqlitePool, config: &TrashConfig) -> AppResult<Vec<TrashedMemo>> {
+    let rows: Vec<(String, String)> =
+        sqlx::query_as("SELECT id, deleted_at FROM voice_memos WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC")
+            .fetch_all(pool)
+            .await?;
+    let ids: Vec<&str> = rows.iter().map(|(id, _)| id.as_str()).collect();
+    let mut memos = load_memos(pool, &ids).await?;
+    Ok(rows
+        .iter()
+        .filter_map(|(id, deleted_at)| {
+            let purge_at = config.retention().and_then(|retention| {
+                DateTime::parse_from_rfc3339(deleted_at)
+                    .ok()
+                    .map(|at| timestamp(at.with_timezone(&Utc) + retention))
+            });
+            Some(TrashedMemo {
+                memo: MemoSummary::from(memos.remove(id)?),
+                deleted_at: deleted_at.clone(),
+                purge_at,
+            })
+        })
+        .collect())
+}
+
+pub async fn restore_memo<R: Runtime>(app: &AppHandle<R>, pool: &SqlitePool, id: &str) -> AppResult<()> {
+    let result = sqlx::query("UPDATE voice_memos SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found(format!("Memo {} is not in the trash", id)));
+    }
+    println!("♻️ Memo restored from trash with ID: {}", id);
+    emit_memo_updated(app);
+    Ok(())
+}
+
+/// Permanently deletes trashed memos deleted before `cutoff` (all of them
+/// without a cutoff). Memos that are also on the backend leave a tombstone
+/// (see [`send_tombstones`]); their recordings are left to the audio
+/// garbage collector.
+async fn purge(pool: &SqlitePool, cutoff: Option<DateTime<Utc>>) -> AppResult<usize> {
+    let cutoff = cutoff.map(timestamp);
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT OR IGNORE INTO sync_tombstones (remote_id, deleted_at)
+         SELECT remote_id, deleted_at FROM voice_memos
+         WHERE deleted_at IS NOT NULL AND (?1 IS NULL OR deleted_at < ?1) AND remote_id IS NOT NULL",
+    )
+    .bind(&cutoff)
+    .execute(&mut *tx)
+    .await?;
+    let result = sqlx::query("DELETE FROM voice_memos WHERE deleted_at IS NOT NULL AND (?1 IS NULL OR deleted_at < ?1)")
+        .bind(&cutoff)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(result.rows_affected() as usize)
+}
+
+/// Deletes the backend copy of a purged memo and drops its tombstone.
+/// Returns whether the backend still had it.
+pub(crate) async fn send_tombstone<R: Runtime>(
+    app: &AppHandle<R>,
+    api: &ApiClient,
+    pool: &SqlitePool,
+    token: &str,
+    remote_id: &str,
+) -> AppResult<bool> {
+    let deleted = match memo_ops::delete_memo(app, api, remote_id, token).await {
+        Ok(()) => true,
+        Err(e) if e.kind == ErrorKind::NotFound => false,
+        Err(e) => return Err(e),
+    };
+    sqlx::query("DELETE FROM sync_tombstones WHERE remote_id = ?")
+        .bind(remote_id)
+        .execute(pool)
+        .await?;
+    Ok(deleted)
+}
+
+/// Deletes purged memos on the backend right away when signed in, instead
+/// of waiting for the next sync, which never runs in remote mode.
+/// Tombstones that cannot be sent now are left for later.
+pub(crate) async fn send_tombstones<R: Runtime>(app: &AppHandle<R>, pool: &SqlitePool) -> AppResult<()> {
+    let (Some(session), Some(api)) = (app.try_state::<SessionState>(), app.try_state::<ApiClient>()) else {
+        return Ok(());
+    };
+    let Ok(token) = session.token() else {
+        return Ok(());
+    };
+    let remote_ids: Vec<String> = sqlx::query_scalar("SELECT remote_id FROM sync_tombstones")
+        .fetch_all(pool)
+        .await?;
+    for remote_id in remote_ids {
+        if let Err(e) = send_tombstone(app, &api, pool, &token, &remote_id).await {
+            println!("⚠️ Purged memo {} stays on the backend for now: {}", remote_id, e.message);
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Permanently deletes everything in the trash.
+pub async fn empty_trash<R: Runtime>(app: &AppHandle<R>, pool: &SqlitePool) -> AppResult<usize> {
+    let purged = purge(pool, None).await?;
+    println!("🔥 Emptied trash: {} memos deleted permanently", purged);
+    if purged > 0 {
+        send_tombstones(app, pool).await?;
+        emit_memo_updated(app);
+    }
+    Ok(purged)
+}
+
+/// Permanently deletes memos that have been in the trash longer than the
+/// retention period.
+pub async fn purge_expired<R: Runtime>(app: &AppHandle<R>, pool: &SqlitePool, config: &TrashConfig) -> AppResult<usize> {
+    let Some(retention) = config.retention() else {
+        return Ok(0);
+    };
+    let purged = purge(pool, Some(Utc::now() - retention)).await?;
+    if purged > 0 {
+        println!("🔥 Purged {} memos from trash after {} days", purged, config.retention_days);
+        send_tombstones(app, pool).await?;
+        emit_memo_updated(app);
+    }
+    Ok(purged)
+}
+
+/// Purges expired trash now and then every `purge_interval_minutes`, for
+/// as long as the app runs.
+pub async fn run_purger<R: Runtime>(app: AppHandle<R>, pool: SqlitePool, config: TrashConfig) {
+    let interval = std::time::Duration::from_secs(config.purge_interval_minutes.max(1) * 60);
+    loop {
+        if let Err(e) = purge_expired(&app, &pool, &config).await {
+            println!("❌ Purging the trash failed: {}", e.message);
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
@@ -0,0 +1,395 @@
+use crate::api_client::ApiClient;
+use crate::audio;
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::events::emit_upload_progress;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use tauri::{AppHandle, Runtime};
+use tokio::fs;
+
+/// When and how recordings are split into chunks.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct UploadConfig {
+    /// Recordings larger than this use the resumable protocol.
+    pub chunked_threshold_bytes: usize,
+    /// Preferred part size; the server may override it on init.
+    pub chunk_size_bytes: usize,
+}
+
+impl Default for UploadConfig {
+    fn default() -> Self {
+        Self {
+            chunked_threshold_bytes: 8 * 1024 * 1024,
+            chunk_size_bytes: 4 * 1024 * 1024,
+        }
+    }
+}
+
+/// Progress of a resumable upload, persisted next to a copy of the audio so
+/// an interrupted upload can continue after a restart.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UploadManifest {
+    pub fingerprint: String,
+    pub idempotency_key: String,
+    pub upload_id: String,
+    pub total_size: usize,
+    pub chunk_size: usize,
+    pub sha256: String,
+    pub metadata: serde_json::Value,
+    pub completed_parts: Vec<u32>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UploadManifest {
+    fn part_count(&self) -> u32 {
+        self.total_size.div_ceil(self.chunk_size) as u32
+    }
+
+    /// Takes the parts the server reports as received, sorted and without
+    /// duplicates. A part past the end of the recording means the server
+    /// and this manifest describe different uploads.
+    fn set_received(&mut self, mut parts: Vec<u32>) -> AppResult<()> {
+        let part_count = self.part_count();
+        if let Some(part) = parts.iter().find(|&&part| part >= part_count) {
+            return Err(AppError::new(
+                ErrorKind::Decode,
+                format!(
+                    "Upload {} reports part {} but only has {} parts",
+                    self.upload_id, part, part_count
+                ),
+            ));
+        }
+        parts.sort_unstable();
+        parts.dedup();
+        self.completed_parts = parts;
+        Ok(())
+    }
+
+    fn uploaded_bytes(&self) -> usize {
+        self.completed_parts
+            .iter()
+            .map(|&part| part_range(part, self.chunk_size, self.total_size).len())
+            .sum()
+    }
+}
+
+/// Payload of the `upload:progress` event.
+#[derive(Serialize, Debug, Clone)]
+pub struct UploadProgress {
+    pub upload_id: String,
+    pub uploaded_bytes: usize,
+    pub total_bytes: usize,
+    pub completed_parts: u32,
+    pub total_parts: u32,
+}
+
+#[derive(Deserialize)]
+struct InitResponse {
+    upload_id: String,
+    chunk_size: Option<usize>,
+    #[serde(default)]
+    received_parts: Vec<u32>,
+}
+
+#[derive(Deserialize)]
+struct StatusResponse {
+    #[serde(default)]
+    received_parts: Vec<u32>,
+}
+
+#[derive(Deserialize)]
+struct CompleteResponse {
+    memo_id: String,
+}
+
+fn part_range(part: u32, chunk_size: usize, total_size: usize) -> std::ops::Range<usize> {
+    let start = part as usize * chunk_size;
+    start..(start + chunk_size).min(total_size)
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Drives the chunked upload protocol (init, parts with checksums, complete)
+/// and keeps its progress under `<app data>/uploads`.
+pub struct UploadManager {
+    dir: PathBuf,
+    config: UploadConfig,
+}
+
+impl UploadManager {
+    pub fn new(data_dir: PathBuf, config: UploadConfig) -> AppResult<Self> {
+        let dir = data_dir.join("uploads");
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir, config })
+    }
+
+    pub fn should_chunk(&self, size: usize) -> bool {
+        size > self.config.chunked_threshold_bytes
+    }
+
+    fn manifest_path(&self, fingerprint: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", fingerprint))
+    }
+
+    fn audio_path(&self, fingerprint: &str) -> PathBuf {
+        self.dir.join(format!("{}.audio", fingerprint))
+    }
+
+    async fn save_manifest(&self, manifest: &UploadManifest) -> AppResult<()> {
+        let path = self.manifest_path(&manifest.fingerprint);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(manifest)?).await?;
+        fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+
+    async fn load_manifest(&self, fingerprint: &str) -> AppResult<Option<UploadManifest>> {
+        match fs::read(self.manifest_path(fingerprint)).await {
+            Ok(raw) => Ok(Some(serde_json::from_slice(&raw)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn discard(&self, fingerprint: &str) {
+        let _ = fs::remove_file(self.manifest_path(fingerprint)).await;
+        let _ = fs::remove_file(self.audio_path(fingerprint)).await;
+    }
+
+    /// Size of the recordings kept for interrupted uploads.
+    pub async fn disk_usage(&self) -> AppResult<u64> {
+        let mut total = 0;
+        let mut entries = fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.path().extension().is_some_and(|ext| ext == "audio") {
+                total += entry.metadata().await?.len();
+            }
+        }
+        Ok(total)
+    }
+
+    /// Lists interrupted uploads whose manifests are still on disk, oldest first.
+    pub async fn pending(&self) -> AppResult<Vec<UploadManifest>> {
+        let mut manifests = Vec::new();
+        let mut entries = fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            match fs::read(&path).await.map(|raw| serde_json::from_slice::<UploadManifest>(&raw)) {
+                Ok(Ok(manifest)) => manifests.push(manifest),
+                _ => println!("⚠️ Skipping unreadable upload manifest {}", path.display()),
+            }
+        }
+        manifests.sort_by_key(|m| m.created_at);
+        Ok(manifests)
+    }
+
+    /// Uploads `audio` in chunks and returns the id of the created memo.
+    ///
+    /// If an earlier attempt for the same `fingerprint` was interrupted, the
+    /// parts the server already has are skipped.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn upload<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        api: &ApiClient,
+        token: &str,
+        fingerprint: &str,
+        idempotency_key: &str,
+        audio: &[u8],
+        metadata: serde_json::Value,
+    ) -> AppResult<String> {
+        let manifest = match self.load_manifest(fingerprint).await? {
+            Some(manifest) => manifest,
+            None => {
+                // Keep our own copy so the upload survives a restart.
+                fs::write(self.audio_path(fingerprint), audio).await?;
+                match self
+                    .init(api, token, fingerprint, idempotency_key, audio, metadata)
+                    .await
+                {
+                    Ok(manifest) => manifest,
+                    Err(e) => {
+                        self.discard(fingerprint).await;
+                        return Err(e);
+                    }
+                }
+            }
+        };
+        self.run(app, api, token, manifest, audio).await
+    }
+
+    /// Continues an upload found on disk by [`UploadManager::pending`].
+    pub async fn resume<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        api: &ApiClient,
+        token: &str,
+        manifest: UploadManifest,
+    ) -> AppResult<String> {
+        let audio = fs::read(self.audio_path(&manifest.fingerprint)).await?;
+        if sha256_hex(&audio) != manifest.sha256 {
+            self.discard(&manifest.fingerprint).await;
+            return Err(AppError::validation(format!(
+                "Stored audio for upload {} is corrupt; the upload was discarded",
+                manifest.upload_id
+            )));
+        }
+        self.run(app, api, token, manifest, &audio).await
+    }
+
+    async fn init(
+        &self,
+        api: &ApiClient,
+        token: &str,
+        fingerprint: &str,
+        idempotency_key: &str,
+        audio: &[u8],
+        metadata: serde_json::Value,
+    ) -> AppResult<UploadManifest> {
+        let sha256 = sha256_hex(audio);
+        let mime = audio::sniff_mime(audio);
+        let payload = serde_json::json!({
+            "file_name": format!("recording.{}", audio::extension_for(mime)),
+            "mime": mime,
+            "total_size": audio.len(),
+            "chunk_size": self.config.chunk_size_bytes,
+            "sha256": sha256,
+            "metadata": metadata,
+        });
+
+        let res = api
+            .post("/uploads/init")
+            .header("Authorization", format!("Bearer {}", token))
+            .idempotency_key(idempotency_key)
+            .json(&payload)
+            .send()
+            .await?;
+
+        if !res.status().is_success() {
+            return Err(AppError::from_response(res).await);
+        }
+
+        let response: InitResponse = res.json().await?;
+        println!("📦 Started chunked upload {} ({} bytes)", response.upload_id, audio.len());
+
+        let mut manifest = UploadManifest {
+            fingerprint: fingerprint.to_string(),
+            idempotency_key: idempotency_key.to_string(),
+            upload_id: response.upload_id,
+            total_size: audio.len(),
+            chunk_size: response
+                .chunk_size
+                .filter(|&size| size > 0)
+                .unwrap_or(self.config.chunk_size_bytes),
+            sha256,
+            metadata,
+            completed_parts: Vec::new(),
+            created_at: Utc::now(),
+        };
+        manifest.set_received(response.received_parts)?;
+        self.save_manifest(&manifest).await?;
+        Ok(manifest)
+    }
+
+    async fn run<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        api: &ApiClient,
+        token: &str,
+        mut manifest: UploadManifest,
+        audio: &[u8],
+    ) -> AppResult<String> {
+        // Ask the server which parts it actually has; our record may be stale.
+        let res = api
+            .get(&format!("/uploads/{}", manifest.upload_id))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await?;
+
+        if res.status() == 404 {
+            // The server expired the session; start over with the same key.
+            println!("♻️ Upload {} expired on the server, restarting", manifest.upload_id);
+            let fingerprint = manifest.fingerprint.clone();
+            let key = manifest.idempotency_key.clone();
+            manifest = self
+                .init(api, token, &fingerprint, &key, audio, manifest.metadata)
+                .await?;
+        } else if !res.status().is_success() {
+            return Err(AppError::from_response(res).await);
+        } else {
+            let status: StatusResponse = res.json().await?;
+            manifest.set_received(status.received_parts)?;
+            self.save_manifest(&manifest).await?;
+        }
+
+        let total_parts = manifest.part_count();
+        for part in 0..total_parts {
+            if manifest.completed_parts.contains(&part) {
+                continue;
+            }
+            let chunk = &audio[part_range(part, manifest.chunk_size, manifest.total_size)];
+
+            let res = api
+                .put(&format!("/uploads/{}/parts/{}", manifest.upload_id, part))
+                .header("Authorization", format!("Bearer {}", token))
+                .header("Content-Type", "application/octet-stream")
+                .header("X-Chunk-Sha256", sha256_hex(chunk))
+                .body(chunk.to_vec())
+                .send()
+                .await?;
+
+            if !res.status().is_success() {
+                return Err(AppError::from_response(res).await);
+            }
+
+            if let Err(i) = manifest.completed_parts.binary_search(&part) {
+                manifest.completed_parts.insert(i, part);
+            }
+            self.save_manifest(&manifest).await?;
+            emit_upload_progress(
+                app,
+                UploadProgress {
+                    upload_id: manifest.upload_id.clone(),
+                    uploaded_bytes: manifest.uploaded_bytes(),
+                    total_bytes: manifest.total_size,
+                    completed_parts: manifest.completed_parts.len() as u32,
+                    total_parts,
+                },
+            );
+        }
+
+        let parts: Vec<_> = manifest
+            .completed_parts
+            .iter()
+            .map(|&part| {
+                let range = part_range(part, manifest.chunk_size, manifest.total_size);
+                serde_json::json!({ "part": part, "sha256": sha256_hex(&audio[range]) })
+            })
+            .collect();
+
+        let res = api
+            .post(&format!("/uploads/{}/complete", manifest.upload_id))
+            .header("Authorization", format!("Bearer {}", token))
+            .idempotency_key(&manifest.idempotency_key)
+            .json(&serde_json::json!({ "parts": parts, "sha256": manifest.sha256 }))
+            .send()
+            .await?;
+
+        if !res.status().is_success() {
+            return Err(AppError::from_response(res).await);
+        }
+
+        let response: CompleteResponse = res.json().await?;
+        println!("✅ Chunked upload {} completed as memo {}", manifest.upload_id, response.memo_id);
+        self.discard(&manifest.fingerprint).await;
+        Ok(response.memo_id)
+    }
+}
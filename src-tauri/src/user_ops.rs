@@ -1,65 +1,58 @@
-use reqwest::Client;
+use crate::api_client::ApiClient;
+use crate::error::{AppError, AppResult};
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-use std::error::Error;
-
-const API_BASE_URL: &str = "https://smartmemo-backend-rust.onrender.com/api";
 
 // Structs for API communication
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct SignupPayload {
     pub username: String,
     pub email: String,
     pub password: String,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct SignupResponse {
     pub message: String,
     pub user_id: String,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct LoginPayload {
     pub email: String,
     pub password: String,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct LoginResponse {
     pub message: String,
     pub token: String,
 }
 
 /// Signs up a new user by calling the backend API.
-pub async fn signup(payload: SignupPayload) -> Result<SignupResponse, Box<dyn Error>> {
-    let client = Client::new();
-    let res = client
-        .post(&format!("{}/signup", API_BASE_URL))
+pub async fn signup(api: &ApiClient, payload: SignupPayload) -> AppResult<SignupResponse> {
+    let res = api
+        .post("/signup")
         .json(&payload)
         .send()
         .await?;
 
     if !res.status().is_success() {
-        let error_body = res.text().await.unwrap_or_else(|_| "Unknown signup error".to_string());
-        return Err(format!("API Error: {}", error_body).into());
+        return Err(AppError::from_response(res).await);
     }
 
     Ok(res.json().await?)
 }
 
 /// Logs in a user by calling the backend API.
-pub async fn login(payload: LoginPayload) -> Result<LoginResponse, Box<dyn Error>> {
-    let client = Client::new();
-    let res = client
-        .post(&format!("{}/login", API_BASE_URL))
+pub async fn login(api: &ApiClient, payload: LoginPayload) -> AppResult<LoginResponse> {
+    let res = api
+        .post("/login")
         .json(&payload)
         .send()
         .await?;
 
     if !res.status().is_success() {
-        let error_body = res.text().await.unwrap_or_else(|_| "Unknown login error".to_string());
-        return Err(format!("API Error: {}", error_body).into());
+        return Err(AppError::from_response(res).await);
     }
 
     Ok(res.json().await?)
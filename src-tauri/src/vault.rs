@@ -0,0 +1,448 @@
+use crate::audio_cache::AudioCache;
+use crate::audio_store::AudioStore;
+use crate::db::{self, DATABASE_FILE, ENCRYPTED_DATABASE_FILE};
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::events::{emit_memo_updated, emit_vault_status};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, Runtime};
+use zeroize::Zeroizing;
+
+/// Passphrase metadata inside the app data directory; its presence means
+/// the library is encrypted.
+pub(crate) const VAULT_FILE: &str = "vault.json";
+/// Encrypted files start with this, followed by the nonce and the ciphertext.
+const SEALED_MAGIC: &[u8] = b"SMVAULT1";
+const NONCE_LEN: usize = 12;
+const MIN_PASSPHRASE_LEN: usize = 8;
+
+/// Random key protecting the library. The first half is the SQLCipher key
+/// of the database, the second the AES-256-GCM key of audio files. It is
+/// stored wrapped by a key derived from the passphrase, so changing the
+/// passphrase does not re-encrypt anything.
+struct DataKey(Zeroizing<[u8; 64]>);
+
+impl DataKey {
+    fn random() -> Self {
+        let mut bytes = Zeroizing::new([0u8; 64]);
+        OsRng.fill_bytes(&mut bytes[..]);
+        Self(bytes)
+    }
+
+    fn database_key(&self) -> Zeroizing<String> {
+        Zeroizing::new(self.0[..32].iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.0[32..]))
+    }
+}
+
+pub fn is_sealed(data: &[u8]) -> bool {
+    data.starts_with(SEALED_MAGIC)
+}
+
+pub(crate) fn seal_with(cipher: &Aes256Gcm, data: &[u8]) -> AppResult<Vec<u8>> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, data)
+        .map_err(|_| AppError::internal("Encryption failed"))?;
+    let mut sealed = Vec::with_capacity(SEALED_MAGIC.len() + NONCE_LEN + ciphertext.len());
+    sealed.extend_from_slice(SEALED_MAGIC);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+pub(crate) fn open_with(cipher: &Aes256Gcm, sealed: &[u8]) -> AppResult<Vec<u8>> {
+    let body = match sealed.strip_prefix(SEALED_MAGIC) {
+        Some(body) if body.len() >= NONCE_LEN => body,
+        _ => return Err(AppError::new(ErrorKind::Decode, "Encrypted data is truncated")),
+    };
+    let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| AppError::new(ErrorKind::Decode, "Encrypted data could not be decrypted"))
+}
+
+/// Contents of `vault.json`.
+#[derive(Serialize, Deserialize)]
+struct VaultFile {
+    version: u32,
+    /// Argon2id cost parameters for deriving the key that wraps the data key.
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    salt: String,
+    wrapped_key: String,
+}
+
+impl VaultFile {
+    fn wrap(key: &DataKey, passphrase: &str) -> AppResult<Self> {
+        if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+            return Err(AppError::validation(format!(
+                "The passphrase must be at least {} characters long",
+                MIN_PASSPHRASE_LEN
+            )));
+        }
+        let params = Params::default();
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let wrapping = derive(passphrase, &salt, params.clone())?;
+        Ok(Self {
+            version: 1,
+            memory_kib: params.m_cost(),
+            iterations: params.t_cost(),
+            parallelism: params.p_cost(),
+            salt: BASE64.encode(salt),
+            wrapped_key: BASE64.encode(seal_with(&wrapping, &key.0[..])?),
+        })
+    }
+
+    fn unwrap(&self, passphrase: &str) -> AppResult<DataKey> {
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, None)
+            .map_err(|e| AppError::internal(format!("Invalid key derivation parameters: {}", e)))?;
+        let decode = |value: &str| {
+            BASE64
+                .decode(value)
+                .map_err(|e| AppError::new(ErrorKind::Decode, format!("Corrupt {}: {}", VAULT_FILE, e)))
+        };
+        let wrapping = derive(passphrase, &decode(&self.salt)?, params)?;
+        let bytes = Zeroizing::new(
+            open_with(&wrapping, &decode(&self.wrapped_key)?)
+                .map_err(|_| AppError::new(ErrorKind::Unauthorized, "Wrong passphrase"))?,
+        );
+        let mut key = Zeroizing::new([0u8; 64]);
+        if bytes.len() != key.len() {
+            return Err(AppError::new(ErrorKind::Decode, format!("Corrupt {}", VAULT_FILE)));
+        }
+        key.copy_from_slice(&bytes);
+        Ok(DataKey(key))
+    }
+
+    fn load(path: &Path) -> AppResult<Self> {
+        match std::fs::read(path) {
+            Ok(raw) => Ok(serde_json::from_slice(&raw)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(AppError::validation("The memo library has no passphrase"))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, path: &Path) -> AppResult<()> {
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+fn derive(passphrase: &str, salt: &[u8], params: Params) -> AppResult<Aes256Gcm> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key[..])
+        .map_err(|e| AppError::internal(format!("Key derivation failed: {}", e)))?;
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key[..])))
+}
+
+enum KeyState {
+    /// No passphrase; nothing is encrypted.
+    Plain,
+    Locked,
+    Unlocked(DataKey),
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VaultStatus {
+    /// Whether the library is protected by a passphrase.
+    pub encrypted: bool,
+    /// Whether the passphrase is needed before memos can be read.
+    pub locked: bool,
+}
+
+struct Inner {
+    data_dir: PathBuf,
+    state: RwLock<KeyState>,
+    /// When the key or database file last changed; older connections are stale.
+    changed_at: Mutex<Instant>,
+    /// Serializes passphrase operations.
+    busy: tokio::sync::Mutex<()>,
+}
+
+/// Encryption at rest for the local library.
+///
+/// Once a passphrase is set the database is rewritten as a SQLCipher
+/// database (`smartmemo.enc.db`) and recordings in the [`AudioStore`] and
+/// [`AudioCache`] are sealed with AES-256-GCM. The key only lives in memory
+/// while the vault is unlocked; locking drops it and every pooled
+/// connection, after which database reads fail with `ErrorKind::Locked`.
+///
+/// Recordings of uploads still in progress are not covered.
+#[derive(Clone)]
+pub struct Vault {
+    inner: Arc<Inner>,
+}
+
+impl Vault {
+    /// Reads the vault state of `data_dir`. An encrypted library starts out
+    /// locked.
+    pub fn load(data_dir: PathBuf) -> AppResult<Self> {
+        let encrypted = data_dir.join(VAULT_FILE).exists();
+        let vault = Self {
+            inner: Arc::new(Inner {
+                state: RwLock::new(if encrypted { KeyState::Locked } else { KeyState::Plain }),
+                data_dir,
+                changed_at: Mutex::new(Instant::now()),
+                busy: tokio::sync::Mutex::new(()),
+            }),
+        };
+        if !encrypted && vault.plain_path().exists() {
+            // Left over from removing the passphrase; its key is gone.
+            db::remove_database_files(&vault.encrypted_path());
+        }
+        Ok(vault)
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        &self.inner.data_dir
+    }
+
+    fn vault_path(&self) -> PathBuf {
+        self.inner.data_dir.join(VAULT_FILE)
+    }
+
+    fn plain_path(&self) -> PathBuf {
+        self.inner.data_dir.join(DATABASE_FILE)
+    }
+
+    fn encrypted_path(&self) -> PathBuf {
+        self.inner.data_dir.join(ENCRYPTED_DATABASE_FILE)
+    }
+
+    /// The database file currently in use.
+    pub fn database_path(&self) -> PathBuf {
+        if self.status().encrypted {
+            self.encrypted_path()
+        } else {
+            self.plain_path()
+        }
+    }
+
+    pub fn status(&self) -> VaultStatus {
+        match &*self.inner.state.read().unwrap() {
+            KeyState::Plain => VaultStatus {
+                encrypted: false,
+                locked: false,
+            },
+            KeyState::Locked => VaultStatus {
+                encrypted: true,
+                locked: true,
+            },
+            KeyState::Unlocked(_) => VaultStatus {
+                encrypted: true,
+                locked: false,
+            },
+        }
+    }
+
+    /// Connection settings for the database in its current state.
+    pub fn connect_options(&self) -> SqliteConnectOptions {
+        match &*self.inner.state.read().unwrap() {
+            KeyState::Plain => db::connect_options(&self.plain_path(), None),
+            // Without the key connecting fails, which surfaces as `Locked`.
+            KeyState::Locked => db::connect_options(&self.encrypted_path(), None).create_if_missing(false),
+            KeyState::Unlocked(key) => {
+                db::connect_options(&self.encrypted_path(), Some(&key.database_key())).create_if_missing(false)
+            }
+        }
+    }
+
+    /// Whether a pooled connection of the given age predates the last key
+    /// or file change.
+    pub fn is_stale(&self, age: Duration) -> bool {
+        age > self.inner.changed_at.lock().unwrap().elapsed()
+    }
+
+    fn switch(&self, pool: &SqlitePool, state: KeyState) {
+        *self.inner.state.write().unwrap() = state;
+        pool.set_connect_options(self.connect_options());
+        *self.inner.changed_at.lock().unwrap() = Instant::now();
+    }
+
+    /// Waits until every connection checked out of `pool` is back. After a
+    /// switch to a state new connections cannot write in, nothing writes
+    /// to the database once this returns; returned connections are stale
+    /// and never handed out again.
+    async fn drain(pool: &SqlitePool) {
+        while pool.size() as usize > pool.num_idle() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    /// Encrypts `data` if the library has a passphrase.
+    pub fn seal(&self, data: &[u8]) -> AppResult<Vec<u8>> {
+        match &*self.inner.state.read().unwrap() {
+            KeyState::Plain => Ok(data.to_vec()),
+            KeyState::Locked => Err(AppError::locked()),
+            KeyState::Unlocked(key) => seal_with(&key.cipher(), data),
+        }
+    }
+
+    /// Decrypts data written by [`Vault::seal`]; anything else is returned
+    /// as is.
+    pub fn open(&self, data: Vec<u8>) -> AppResult<Vec<u8>> {
+        if !is_sealed(&data) {
+            return Ok(data);
+        }
+        match &*self.inner.state.read().unwrap() {
+            KeyState::Unlocked(key) => open_with(&key.cipher(), &data),
+            _ => Err(AppError::locked()),
+        }
+    }
+
+    /// Encrypts the library with a new passphrase.
+    pub async fn set_passphrase<R: Runtime>(&self, app: &AppHandle<R>, passphrase: &str) -> AppResult<()> {
+        let _busy = self.inner.busy.lock().await;
+        if self.status().encrypted {
+            return Err(AppError::new(
+                ErrorKind::Conflict,
+                "The memo library already has a passphrase",
+            ));
+        }
+        let key = DataKey::random();
+        // Saved first so that after a crash the next unlock finishes the job.
+        VaultFile::wrap(&key, passphrase)?.save(&self.vault_path())?;
+
+        let pool = app.state::<SqlitePool>();
+        let database_key = key.database_key();
+        let cipher = key.cipher();
+        // Switch first: new connections fail until the encrypted file exists
+        // instead of writing to the plaintext one while it is copied.
+        self.switch(&pool, KeyState::Unlocked(key));
+        Self::drain(&pool).await;
+        if let Err(e) = db::export(&self.plain_path(), None, &self.encrypted_path(), Some(&database_key)).await {
+            // The plaintext database is untouched, so keep using it.
+            self.switch(&pool, KeyState::Plain);
+            let _ = std::fs::remove_file(self.vault_path());
+            println!("❌ Encrypting the memo library failed: {}", e.message);
+            return Err(e);
+        }
+        self.remove_plaintext();
+
+        let sealed = app
+            .state::<AudioStore>()
+            .convert_files(|data| if is_sealed(data) { Ok(None) } else { seal_with(&cipher, data).map(Some) })
+            .await?;
+        app.state::<AudioCache>().clear().await?;
+        println!("🔐 Memo library encrypted ({} recording(s))", sealed);
+        emit_vault_status(app, self.status());
+        Ok(())
+    }
+
+    /// Re-wraps the data key with a new passphrase; nothing is re-encrypted.
+    pub async fn change_passphrase<R: Runtime>(&self, app: &AppHandle<R>, current: &str, new: &str) -> AppResult<()> {
+        let _busy = self.inner.busy.lock().await;
+        let key = VaultFile::load(&self.vault_path())?.unwrap(current)?;
+        VaultFile::wrap(&key, new)?.save(&self.vault_path())?;
+        println!("🔐 Memo library passphrase changed");
+        emit_vault_status(app, self.status());
+        Ok(())
+    }
+
+    /// Decrypts the library and forgets the passphrase.
+    pub async fn remove_passphrase<R: Runtime>(&self, app: &AppHandle<R>, passphrase: &str) -> AppResult<()> {
+        let _busy = self.inner.busy.lock().await;
+        let key = VaultFile::load(&self.vault_path())?.unwrap(passphrase)?;
+        if !self.encrypted_path().exists() {
+            return Err(AppError::validation("Unlock the memo library before removing its passphrase"));
+        }
+
+        let pool = app.state::<SqlitePool>();
+        let database_key = key.database_key();
+        let cipher = key.cipher();
+        // Nothing may write to the encrypted database while it is copied.
+        let was_locked = self.status().locked;
+        self.switch(&pool, KeyState::Locked);
+        Self::drain(&pool).await;
+        if let Err(e) = db::export(&self.encrypted_path(), Some(&database_key), &self.plain_path(), None).await {
+            self.switch(&pool, if was_locked { KeyState::Locked } else { KeyState::Unlocked(key) });
+            return Err(e);
+        }
+        self.switch(&pool, KeyState::Plain);
+        let opened = app
+            .state::<AudioStore>()
+            .convert_files(|data| if is_sealed(data) { open_with(&cipher, data).map(Some) } else { Ok(None) })
+            .await?;
+        app.state::<AudioCache>().clear().await?;
+        std::fs::remove_file(self.vault_path())?;
+        db::remove_database_files(&self.encrypted_path());
+        println!("🔓 Memo library decrypted ({} recording(s))", opened);
+        emit_vault_status(app, self.status());
+        Ok(())
+    }
+
+    /// Forgets the key until the library is unlocked again.
+    pub async fn lock<R: Runtime>(&self, app: &AppHandle<R>) -> AppResult<()> {
+        let _busy = self.inner.busy.lock().await;
+        if !self.status().encrypted {
+            return Err(AppError::validation("The memo library has no passphrase"));
+        }
+        self.switch(&app.state::<SqlitePool>(), KeyState::Locked);
+        println!("🔒 Memo library locked");
+        emit_vault_status(app, self.status());
+        emit_memo_updated(app);
+        Ok(())
+    }
+
+    pub async fn unlock<R: Runtime>(&self, app: &AppHandle<R>, passphrase: &str) -> AppResult<()> {
+        let _busy = self.inner.busy.lock().await;
+        let status = self.status();
+        if !status.encrypted {
+            return Err(AppError::validation("The memo library has no passphrase"));
+        }
+        if !status.locked {
+            return Ok(());
+        }
+        let key = VaultFile::load(&self.vault_path())?.unwrap(passphrase)?;
+        let database_key = key.database_key();
+        let cipher = key.cipher();
+        if !self.encrypted_path().exists() {
+            // Setting the passphrase was interrupted before the copy finished.
+            db::export(&self.plain_path(), None, &self.encrypted_path(), Some(&database_key)).await?;
+        }
+        let pool = app.state::<SqlitePool>();
+        self.switch(&pool, KeyState::Unlocked(key));
+        self.remove_plaintext();
+        db::migrate(&pool).await?;
+
+        // Finish encrypting recordings if that was interrupted, and move
+        // any recordings still inside the database into the store.
+        let audio = app.state::<AudioStore>();
+        audio
+            .convert_files(|data| if is_sealed(data) { Ok(None) } else { seal_with(&cipher, data).map(Some) })
+            .await?;
+        audio.adopt_inline(&pool).await?;
+        println!("🔓 Memo library unlocked");
+        emit_vault_status(app, self.status());
+        emit_memo_updated(app);
+        Ok(())
+    }
+
+    fn remove_plaintext(&self) {
+        if !db::remove_database_files(&self.plain_path()) {
+            // Still open somewhere (Windows); retried on the next unlock.
+            println!("⚠️ Could not remove the plaintext database yet");
+        }
+    }
+}
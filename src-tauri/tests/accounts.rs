@@ -0,0 +1,151 @@
+//! Several accounts on one device, each with its own profile directories.
+
+use app_lib::accounts::AccountRegistry;
+use app_lib::config::CONFIG_FILE_NAME;
+use app_lib::error::ErrorKind;
+use app_lib::session::{SessionInfo, SessionState};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+struct Harness {
+    dir: TempDir,
+}
+
+impl Harness {
+    fn new() -> Self {
+        Self { dir: TempDir::new().unwrap() }
+    }
+
+    fn data(&self) -> PathBuf {
+        self.dir.path().join("data")
+    }
+
+    fn cache(&self) -> PathBuf {
+        self.dir.path().join("cache")
+    }
+
+    fn config(&self) -> PathBuf {
+        self.dir.path().join("config")
+    }
+
+    fn load(&self) -> AccountRegistry {
+        AccountRegistry::load(self.data(), self.cache(), Some(self.config())).unwrap()
+    }
+}
+
+fn token(user_id: &str, email: &str) -> String {
+    let claims = serde_json::json!({"sub": user_id, "email": email});
+    format!("e30.{}.signature", URL_SAFE_NO_PAD.encode(claims.to_string()))
+}
+
+fn info(user_id: &str, email: &str) -> SessionInfo {
+    SessionInfo::from_token(&token(user_id, email), email)
+}
+
+fn write(path: &Path, contents: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, contents).unwrap();
+}
+
+fn entries(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn an_existing_library_moves_into_the_profile_of_its_signed_in_account() {
+    let h = Harness::new();
+    write(&h.data().join("smartmemo.db"), "library");
+    write(&h.data().join("audio/ab/abcd"), "recording");
+    write(&h.cache().join("audio/memo-1.audio"), "cached");
+    SessionState::load(h.data()).unwrap().start(token("user-1", "ada@example.com"), "ada@example.com").unwrap();
+
+    let accounts = h.load();
+    let id = AccountRegistry::account_id(&info("user-1", "ada@example.com"));
+    assert_eq!(accounts.active_id(), Some(id.clone()));
+    let profile = accounts.active_profile();
+    assert_eq!(std::fs::read_to_string(profile.data_dir.join("smartmemo.db")).unwrap(), "library");
+    assert!(profile.data_dir.join("audio/ab/abcd").exists());
+    assert!(profile.cache_dir.join("audio/memo-1.audio").exists());
+    assert_eq!(entries(&h.data()), ["accounts", "accounts.json"]);
+    assert_eq!(entries(&h.cache()), ["accounts"]);
+
+    // The session moved along, so the account is still signed in.
+    let listed = accounts.list();
+    assert_eq!(listed.len(), 1);
+    assert!(listed[0].active && listed[0].signed_in);
+    assert_eq!(SessionState::load(profile.data_dir).unwrap().current().unwrap().email, "ada@example.com");
+}
+
+#[test]
+fn moving_the_library_leaves_other_files_alone_and_finishes_on_a_later_start() {
+    let h = Harness::new();
+    write(&h.data().join("smartmemo.db"), "library");
+    write(&h.data().join("smartmemo.db-wal"), "log");
+    write(&h.data().join("EBWebView/Local State"), "webview");
+    h.load();
+    assert_eq!(entries(&h.data()), ["EBWebView", "accounts", "accounts.json"]);
+
+    // A recording whose move failed last time goes where the library went.
+    write(&h.data().join("audio/ab/abcd"), "recording");
+    let accounts = h.load();
+    let legacy = h.data().join("accounts/legacy");
+    assert_eq!(entries(&legacy), ["audio", "smartmemo.db", "smartmemo.db-wal"]);
+    let ada = accounts.remember(&info("user-1", "ada@example.com")).unwrap();
+
+    write(&h.data().join("vault.json"), "{}");
+    let accounts = h.load();
+    let profile = accounts.profile(Some(&ada));
+    assert_eq!(entries(&profile.data_dir), ["audio", "smartmemo.db", "smartmemo.db-wal", "vault.json"]);
+    assert!(!legacy.exists());
+    assert_eq!(entries(&h.data()), ["EBWebView", "accounts", "accounts.json"]);
+}
+
+#[test]
+fn the_first_account_to_log_in_takes_over_a_signed_out_library() {
+    let h = Harness::new();
+    write(&h.data().join("smartmemo.db"), "library");
+
+    let accounts = h.load();
+    assert_eq!(accounts.active_id(), None);
+    // Nobody's memos are open while signed out.
+    assert!(!accounts.active_profile().data_dir.join("smartmemo.db").exists());
+
+    let ada = accounts.remember(&info("user-1", "ada@example.com")).unwrap();
+    let grace = accounts.remember(&info("user-2", "grace@example.com")).unwrap();
+    assert_ne!(ada, grace);
+    assert!(accounts.profile(Some(&ada)).data_dir.join("smartmemo.db").exists());
+    assert!(!accounts.profile(Some(&grace)).data_dir.exists());
+
+    accounts.set_active(&grace).unwrap();
+    let accounts = h.load();
+    assert_eq!(accounts.active_id(), Some(grace.clone()));
+    let listed: Vec<(String, bool)> = accounts.list().into_iter().map(|a| (a.account.email, a.active)).collect();
+    assert_eq!(listed, [("grace@example.com".to_string(), true), ("ada@example.com".to_string(), false)]);
+
+    let err = accounts.set_active("nobody").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::NotFound);
+}
+
+#[test]
+fn settings_are_per_account_with_shared_defaults() {
+    let h = Harness::new();
+    write(&h.config().join(CONFIG_FILE_NAME), "{}");
+    let accounts = h.load();
+    let ada = accounts.remember(&info("user-1", "ada@example.com")).unwrap();
+    let grace = accounts.remember(&info("user-2", "grace@example.com")).unwrap();
+
+    let ada_profile = accounts.profile(Some(&ada));
+    write(&ada_profile.own_settings_file().unwrap(), "{}");
+
+    assert_eq!(ada_profile.settings_file().unwrap(), ada_profile.own_settings_file().unwrap());
+    let grace_profile = accounts.profile(Some(&grace));
+    assert_eq!(grace_profile.settings_file().unwrap(), h.config().join(CONFIG_FILE_NAME));
+    assert_ne!(grace_profile.own_settings_file().unwrap(), ada_profile.own_settings_file().unwrap());
+}
@@ -0,0 +1,573 @@
+//! Exercises the backend-facing operations against the in-process mock
+//! backend in `support/mock_backend.rs`.
+
+mod support;
+
+use app_lib::api_client::ApiClient;
+use app_lib::api_key_ops;
+use app_lib::audio_cache::AudioCache;
+use app_lib::error::ErrorKind;
+use app_lib::gemini;
+use app_lib::idempotency::IdempotencyStore;
+use app_lib::memo_ops::{self, MemoQuery, MemoSortField, SortDirection};
+use app_lib::upload::{UploadConfig, UploadManager};
+use app_lib::user_ops::{self, LoginPayload, SignupPayload};
+use std::sync::{Arc, Mutex};
+use support::mock_backend::MockBackend;
+use tauri::test::{mock_app, MockRuntime};
+use tauri::{App, Listener};
+use tempfile::TempDir;
+
+/// WebM magic followed by filler, so MIME sniffing sees a real recording.
+fn recording(len: usize, seed: u8) -> Vec<u8> {
+    let mut audio = vec![0x1A, 0x45, 0xDF, 0xA3];
+    audio.extend((0..len.saturating_sub(4)).map(|i| (i as u8).wrapping_mul(31).wrapping_add(seed)));
+    audio
+}
+
+struct Harness {
+    backend: MockBackend,
+    api: ApiClient,
+    app: App<MockRuntime>,
+    keys: IdempotencyStore,
+    uploads: UploadManager,
+    cache: AudioCache,
+    token: String,
+    _dir: TempDir,
+}
+
+impl Harness {
+    async fn start() -> Self {
+        Self::with_upload_config(UploadConfig::default()).await
+    }
+
+    async fn with_upload_config(config: UploadConfig) -> Self {
+        let backend = MockBackend::start().await;
+        let dir = TempDir::new().unwrap();
+        let token = backend.register("ada@example.com", "hunter22");
+        Self {
+            api: backend.client(),
+            app: mock_app(),
+            keys: IdempotencyStore::load(dir.path().join("data")).unwrap(),
+            uploads: UploadManager::new(dir.path().join("data"), config).unwrap(),
+            cache: AudioCache::new(dir.path().join("cache")).unwrap(),
+            backend,
+            token,
+            _dir: dir,
+        }
+    }
+
+    fn events(&self, name: &str) -> Arc<Mutex<Vec<String>>> {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        self.app
+            .handle()
+            .listen(name, move |event| sink.lock().unwrap().push(event.payload().to_string()));
+        seen
+    }
+
+    async fn save(&self, audio: Vec<u8>, tags: &[&str]) -> memo_ops::VoiceMemo {
+        memo_ops::save_audio(
+            self.app.handle(),
+            &self.api,
+            &self.keys,
+            &self.uploads,
+            &self.token,
+            audio,
+            "00:05".to_string(),
+            tags.iter().map(|t| t.to_string()).collect(),
+        )
+        .await
+        .unwrap()
+    }
+}
+
+#[tokio::test]
+async fn signup_then_login_returns_a_token() {
+    let backend = MockBackend::start().await;
+    let api = backend.client();
+
+    let created = user_ops::signup(
+        &api,
+        SignupPayload {
+            username: "grace".into(),
+            email: "grace@example.com".into(),
+            password: "cobol".into(),
+        },
+    )
+    .await
+    .unwrap();
+    assert!(!created.user_id.is_empty());
+
+    let duplicate = user_ops::signup(
+        &api,
+        SignupPayload {
+            username: "grace".into(),
+            email: "grace@example.com".into(),
+            password: "other".into(),
+        },
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(duplicate.kind, ErrorKind::Conflict);
+
+    let session = user_ops::login(
+        &api,
+        LoginPayload {
+            email: "grace@example.com".into(),
+            password: "cobol".into(),
+        },
+    )
+    .await
+    .unwrap();
+    assert!(!session.token.is_empty());
+
+    let wrong = user_ops::login(
+        &api,
+        LoginPayload {
+            email: "grace@example.com".into(),
+            password: "nope".into(),
+        },
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(wrong.kind, ErrorKind::Unauthorized);
+    assert_eq!(wrong.status, Some(401));
+}
+
+#[tokio::test]
+async fn invalid_token_is_unauthorized() {
+    let h = Harness::start().await;
+    let err = memo_ops::get_memos(&h.api, "bogus", &MemoQuery::default())
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Unauthorized);
+    assert!(!err.retryable);
+}
+
+#[tokio::test]
+async fn save_audio_round_trips_the_recording() {
+    let h = Harness::start().await;
+    let updates = h.events("memo:updated");
+    let audio = recording(64, 1);
+
+    let memo = h.save(audio.clone(), &["work"]).await;
+    assert_eq!(memo.audio_blob.as_deref(), Some(audio.as_slice()));
+    assert_eq!(memo.tags.as_deref(), Some(&["work".to_string()][..]));
+    assert_eq!(memo.duration, "00:05");
+    assert_eq!(updates.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn lost_save_response_is_retried_without_duplicating() {
+    let h = Harness::start().await;
+    h.backend.lose_next(&[503]);
+
+    h.save(recording(64, 2), &[]).await;
+    assert_eq!(h.backend.request_count("POST /api/save_memo"), 2);
+    assert_eq!(h.backend.memo_count(), 1);
+}
+
+#[tokio::test]
+async fn transient_failures_are_retried_and_reported() {
+    let h = Harness::start().await;
+    let waking = Arc::new(Mutex::new(Vec::new()));
+    let sink = waking.clone();
+    let mut api = h.backend.client();
+    api.set_waking_listener(move |status| sink.lock().unwrap().push(status.waking));
+
+    h.backend.fail_next(&[503, 502]);
+    let page = memo_ops::get_memos(&api, &h.token, &MemoQuery::default()).await.unwrap();
+    assert!(page.items.is_empty());
+    assert_eq!(h.backend.request_count("GET /api/get_memos"), 3);
+    assert_eq!(*waking.lock().unwrap(), vec![true, true, false]);
+
+    // Logins are not idempotent and get a single attempt.
+    h.backend.fail_next(&[503]);
+    let err = user_ops::login(
+        &api,
+        LoginPayload {
+            email: "ada@example.com".into(),
+            password: "hunter22".into(),
+        },
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Server);
+    assert!(err.retryable);
+    assert_eq!(h.backend.request_count("POST /api/login"), 1);
+}
+
+#[tokio::test]
+async fn an_absurd_retry_after_is_capped() {
+    let h = Harness::start().await;
+    h.backend.set_retry_after(&u64::MAX.to_string());
+    h.backend.fail_next(&[429]);
+
+    memo_ops::get_memos(&h.api, &h.token, &MemoQuery::default()).await.unwrap();
+    assert_eq!(h.backend.request_count("GET /api/get_memos"), 2);
+}
+
+#[tokio::test]
+async fn save_memo_updates_fields() {
+    let h = Harness::start().await;
+    let memo = h.save(recording(32, 3), &[]).await;
+
+    let updated = memo_ops::save_memo(
+        h.app.handle(),
+        &h.api,
+        &h.token,
+        &memo.id,
+        "Standup",
+        Some("we shipped it".into()),
+        Some("nous l'avons livré".into()),
+        Some("shipped".into()),
+        Some(vec!["work".into(), "daily".into()]),
+    )
+    .await
+    .unwrap();
+    assert_eq!(updated.title, "Standup");
+    assert_eq!(updated.transcript.as_deref(), Some("we shipped it"));
+    assert_eq!(updated.translate.as_deref(), Some("nous l'avons livré"));
+    assert_eq!(updated.summary.as_deref(), Some("shipped"));
+    assert_eq!(updated.tags, Some(vec!["work".to_string(), "daily".to_string()]));
+
+    let missing = memo_ops::save_memo(
+        h.app.handle(),
+        &h.api,
+        &h.token,
+        "memo-404",
+        "Nothing",
+        None,
+        None,
+        None,
+        None,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(missing.kind, ErrorKind::NotFound);
+}
+
+#[tokio::test]
+async fn get_memos_pages_sorts_and_filters() {
+    let h = Harness::start().await;
+    let mut ids = Vec::new();
+    for (i, title) in ["Charlie", "Alpha", "Echo", "Bravo", "Delta"].iter().enumerate() {
+        let tags: &[&str] = if i % 2 == 0 { &["even"] } else { &["odd"] };
+        let memo = h.save(recording(16, i as u8), tags).await;
+        let transcript = (i < 2).then(|| format!("transcript {}", i));
+        memo_ops::save_memo(
+            h.app.handle(),
+            &h.api,
+            &h.token,
+            &memo.id,
+            title,
+            transcript,
+            None,
+            None,
+            Some(tags.iter().map(|t| t.to_string()).collect()),
+        )
+        .await
+        .unwrap();
+        ids.push(memo.id);
+    }
+
+    // Default order is newest first; walk every page of two.
+    let mut query = MemoQuery {
+        page_size: Some(2),
+        ..MemoQuery::default()
+    };
+    let mut seen = Vec::new();
+    loop {
+        let page = memo_ops::get_memos(&h.api, &h.token, &query).await.unwrap();
+        assert!(page.items.len() <= 2);
+        assert!(page.items.iter().all(|m| m.audio_blob.is_none()));
+        seen.extend(page.items.into_iter().map(|m| m.id));
+        match page.next_cursor {
+            Some(cursor) => query.cursor = Some(cursor),
+            None => break,
+        }
+    }
+    let mut newest_first = ids.clone();
+    newest_first.reverse();
+    assert_eq!(seen, newest_first);
+
+    let by_title = memo_ops::get_memos(
+        &h.api,
+        &h.token,
+        &MemoQuery {
+            sort_by: MemoSortField::Title,
+            sort_dir: SortDirection::Asc,
+            ..MemoQuery::default()
+        },
+    )
+    .await
+    .unwrap();
+    let titles: Vec<_> = by_title.items.iter().map(|m| m.title.as_str()).collect();
+    assert_eq!(titles, ["Alpha", "Bravo", "Charlie", "Delta", "Echo"]);
+
+    let even = memo_ops::get_memos(
+        &h.api,
+        &h.token,
+        &MemoQuery {
+            tags: Some(vec!["even".into()]),
+            has_transcript: Some(true),
+            ..MemoQuery::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(even.items.len(), 1);
+    assert_eq!(even.items[0].title, "Charlie");
+
+    let second = memo_ops::get_memo(&h.api, &ids[1], &h.token).await.unwrap().unwrap();
+    let since_second = memo_ops::get_memos(
+        &h.api,
+        &h.token,
+        &MemoQuery {
+            created_from: Some(second.created_at),
+            ..MemoQuery::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(since_second.items.len(), 4);
+
+    let invalid = memo_ops::get_memos(
+        &h.api,
+        &h.token,
+        &MemoQuery {
+            page_size: Some(0),
+            ..MemoQuery::default()
+        },
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(invalid.kind, ErrorKind::Validation);
+}
+
+#[tokio::test]
+async fn list_memos_returns_summaries() {
+    let h = Harness::start().await;
+    let memo = h.save(recording(16, 4), &["idea"]).await;
+    memo_ops::save_memo(
+        h.app.handle(),
+        &h.api,
+        &h.token,
+        &memo.id,
+        "Idea",
+        Some("text".into()),
+        None,
+        Some("short".into()),
+        Some(vec!["idea".into()]),
+    )
+    .await
+    .unwrap();
+
+    let page = memo_ops::list_memos(&h.api, &h.token, &MemoQuery::default()).await.unwrap();
+    assert_eq!(page.items.len(), 1);
+    let summary = &page.items[0];
+    assert_eq!(summary.title, "Idea");
+    assert!(summary.has_transcript);
+    assert!(summary.has_summary);
+    assert!(!summary.has_translation);
+    assert!(page.next_cursor.is_none());
+}
+
+#[tokio::test]
+async fn get_memo_audio_is_cached() {
+    let h = Harness::start().await;
+    let audio = recording(128, 5);
+    let memo = h.save(audio.clone(), &[]).await;
+    let path = format!("GET /api/get_memo/{}/audio", memo.id);
+
+    let first = memo_ops::get_memo_audio(&h.api, &h.cache, &memo.id, &h.token).await.unwrap();
+    let second = memo_ops::get_memo_audio(&h.api, &h.cache, &memo.id, &h.token).await.unwrap();
+    assert_eq!(first, audio);
+    assert_eq!(second, audio);
+    assert_eq!(h.backend.request_count(&path), 1);
+
+    h.cache.remove(&memo.id).await;
+    memo_ops::get_memo_audio(&h.api, &h.cache, &memo.id, &h.token).await.unwrap();
+    assert_eq!(h.backend.request_count(&path), 2);
+}
+
+#[tokio::test]
+async fn get_memo_returns_none_when_missing() {
+    let h = Harness::start().await;
+    assert!(memo_ops::get_memo(&h.api, "memo-404", &h.token).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn delete_memo_and_delete_all() {
+    let h = Harness::start().await;
+    let first = h.save(recording(16, 6), &[]).await;
+    h.save(recording(16, 7), &[]).await;
+    h.save(recording(16, 8), &[]).await;
+
+    memo_ops::delete_memo(h.app.handle(), &h.api, &first.id, &h.token).await.unwrap();
+    assert!(memo_ops::get_memo(&h.api, &first.id, &h.token).await.unwrap().is_none());
+    let again = memo_ops::delete_memo(h.app.handle(), &h.api, &first.id, &h.token)
+        .await
+        .unwrap_err();
+    assert_eq!(again.kind, ErrorKind::NotFound);
+
+    let message = memo_ops::delete_all_memos(h.app.handle(), &h.api, &h.token).await.unwrap();
+    assert_eq!(message, "Deleted 2 memos");
+    assert_eq!(h.backend.memo_count(), 0);
+}
+
+#[tokio::test]
+async fn chunked_upload_resumes_after_interruption() {
+    let h = Harness::with_upload_config(UploadConfig {
+        chunked_threshold_bytes: 16,
+        chunk_size_bytes: 8,
+    })
+    .await;
+    let progress = h.events("upload:progress");
+    let audio = recording(40, 9);
+
+    // Part 2 keeps failing until the retry budget is spent.
+    h.backend.fail_matching("/parts/2", 503, 3);
+    let err = memo_ops::save_audio(
+        h.app.handle(),
+        &h.api,
+        &h.keys,
+        &h.uploads,
+        &h.token,
+        audio.clone(),
+        "00:40".into(),
+        vec![],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Server);
+    assert_eq!(h.uploads.pending().await.unwrap().len(), 1);
+    assert_eq!(progress.lock().unwrap().len(), 2);
+
+    // Parts reported twice are counted once.
+    h.backend.report_parts(&[0, 1]);
+    let resumed = memo_ops::resume_uploads(h.app.handle(), &h.api, &h.keys, &h.uploads, &h.token)
+        .await
+        .unwrap();
+    assert_eq!(resumed.len(), 1);
+    let last: serde_json::Value = serde_json::from_str(progress.lock().unwrap().last().unwrap()).unwrap();
+    assert_eq!(last["uploaded_bytes"], 40);
+    assert_eq!(resumed[0].audio_blob.as_deref(), Some(audio.as_slice()));
+    assert!(h.uploads.pending().await.unwrap().is_empty());
+
+    // Parts the server already had were not sent again.
+    assert_eq!(h.backend.request_count("/parts/0"), 1);
+    assert_eq!(h.backend.request_count("/parts/1"), 1);
+    assert_eq!(h.backend.request_count("/parts/4"), 1);
+    assert_eq!(h.backend.request_count("POST /api/uploads/init"), 1);
+    assert_eq!(progress.lock().unwrap().len(), 5);
+    assert_eq!(h.backend.memo_count(), 1);
+}
+
+#[tokio::test]
+async fn chunked_upload_rejects_parts_past_the_end() {
+    let h = Harness::with_upload_config(UploadConfig {
+        chunked_threshold_bytes: 16,
+        chunk_size_bytes: 8,
+    })
+    .await;
+    h.backend.report_parts(&[99]);
+    let err = memo_ops::save_audio(
+        h.app.handle(),
+        &h.api,
+        &h.keys,
+        &h.uploads,
+        &h.token,
+        recording(40, 12),
+        "00:40".into(),
+        vec![],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Decode);
+    assert_eq!(h.backend.request_count("/parts/"), 0);
+    assert_eq!(h.backend.memo_count(), 0);
+}
+
+#[tokio::test]
+async fn chunked_upload_retried_by_the_user_continues() {
+    let h = Harness::with_upload_config(UploadConfig {
+        chunked_threshold_bytes: 16,
+        chunk_size_bytes: 8,
+    })
+    .await;
+    let audio = recording(30, 10);
+
+    h.backend.fail_matching("/parts/1", 500, 1);
+    let save = || {
+        memo_ops::save_audio(
+            h.app.handle(),
+            &h.api,
+            &h.keys,
+            &h.uploads,
+            &h.token,
+            audio.clone(),
+            "00:30".into(),
+            vec!["long".into()],
+        )
+    };
+    assert_eq!(save().await.unwrap_err().kind, ErrorKind::Server);
+
+    let memo = save().await.unwrap();
+    assert_eq!(memo.audio_blob.as_deref(), Some(audio.as_slice()));
+    assert_eq!(h.backend.request_count("/parts/0"), 1);
+    assert_eq!(h.backend.memo_count(), 1);
+}
+
+#[tokio::test]
+async fn ai_text_operations() {
+    let h = Harness::start().await;
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("clip.webm");
+    std::fs::write(&path, recording(20, 11)).unwrap();
+
+    let transcript = gemini::transcribe_audio(&h.api, path.to_str().unwrap(), &h.token)
+        .await
+        .unwrap();
+    assert_eq!(transcript, "Transcript of 20 bytes");
+
+    let empty = dir.path().join("empty.webm");
+    std::fs::write(&empty, b"").unwrap();
+    let err = gemini::transcribe_audio(&h.api, empty.to_str().unwrap(), &h.token)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Validation);
+
+    let translated = gemini::translate_text(&h.api, "hello", "fr", &h.token).await.unwrap();
+    assert_eq!(translated, "[fr] hello");
+
+    let summary = gemini::summarize_text(&h.api, "one two three four five six", &h.token)
+        .await
+        .unwrap();
+    assert_eq!(summary, "Summary: one two three four five");
+
+    let name = gemini::generate_memo_name(&h.api, "quarterly planning meeting notes", &h.token)
+        .await
+        .unwrap();
+    assert_eq!(name, "quarterly planning meeting");
+}
+
+#[tokio::test]
+async fn api_keys_and_helper_state() {
+    let h = Harness::start().await;
+
+    assert_eq!(api_key_ops::get_api_key(&h.api, &h.token).await.unwrap(), None);
+    api_key_ops::save_api_key(&h.api, &h.token, "gm-123").await.unwrap();
+    assert_eq!(
+        api_key_ops::get_api_key(&h.api, &h.token).await.unwrap().as_deref(),
+        Some("gm-123")
+    );
+    api_key_ops::delete_gemini_api_key(&h.api, &h.token).await.unwrap();
+    api_key_ops::delete_elevenlabs_api_key(&h.api, &h.token).await.unwrap();
+    assert_eq!(api_key_ops::get_api_key(&h.api, &h.token).await.unwrap(), None);
+
+    assert!(!api_key_ops::get_helper_app_state(&h.api, &h.token).await.unwrap());
+    api_key_ops::update_helper_app_state(&h.api, &h.token, true).await.unwrap();
+    assert!(api_key_ops::get_helper_app_state(&h.api, &h.token).await.unwrap());
+}
@@ -0,0 +1,141 @@
+//! Content-addressed recordings behind the local memo store: deduplication,
+//! reference counts, garbage collection and adoption of inline recordings.
+
+mod support;
+
+use app_lib::audio_store::AudioStore;
+use app_lib::memo_store::MemoStore;
+use app_lib::trash;
+use chrono::Duration;
+use support::{recording, Harness};
+use tauri::Manager;
+
+impl Harness {
+    fn audio(&self) -> tauri::State<'_, AudioStore> {
+        self.app.state::<AudioStore>()
+    }
+
+    async fn refcount(&self, hash: &str) -> i64 {
+        sqlx::query_scalar("SELECT refcount FROM audio_blobs WHERE hash = ?")
+            .bind(hash)
+            .fetch_one(&self.pool)
+            .await
+            .unwrap()
+    }
+}
+
+#[tokio::test]
+async fn identical_recordings_are_stored_once() {
+    let h = Harness::start().await;
+    let audio = recording(1);
+    let hash = AudioStore::hash(&audio);
+    let first = h.store.create("unused", audio.clone(), "00:01".into(), vec![]).await.unwrap();
+    let second = h.store.create("unused", audio.clone(), "00:01".into(), vec![]).await.unwrap();
+
+    let files = h.audio_files();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].file_name().unwrap().to_str(), Some(hash.as_str()));
+    assert_eq!(h.refcount(&hash).await, 2);
+    assert_eq!(h.store.audio("unused", &second.id).await.unwrap(), audio);
+
+    // The file outlives the first memo that used it.
+    h.store.delete("unused", &first.id).await.unwrap();
+    trash::empty_trash(h.app.handle(), &h.pool).await.unwrap();
+    assert_eq!(h.refcount(&hash).await, 1);
+    let report = h.audio().collect_garbage(&h.pool, Duration::zero()).await.unwrap();
+    assert_eq!(report.removed_files, 0);
+    assert_eq!(h.store.audio("unused", &second.id).await.unwrap(), audio);
+}
+
+#[tokio::test]
+async fn garbage_collector_removes_unreferenced_recordings() {
+    let h = Harness::start().await;
+    let kept = h.store.create("unused", recording(1), "00:01".into(), vec![]).await.unwrap();
+    let dropped = h.store.create("unused", recording(2), "00:01".into(), vec![]).await.unwrap();
+    h.store.delete("unused", &dropped.id).await.unwrap();
+    trash::empty_trash(h.app.handle(), &h.pool).await.unwrap();
+
+    // Recently touched files survive the grace period.
+    let report = h.audio().collect_garbage(&h.pool, Duration::minutes(10)).await.unwrap();
+    assert_eq!(report.removed_files, 0);
+    assert_eq!(h.audio_files().len(), 2);
+
+    let report = h.audio().collect_garbage(&h.pool, Duration::zero()).await.unwrap();
+    assert_eq!(report.removed_files, 1);
+    assert_eq!(report.freed_bytes, recording(2).len() as u64);
+    assert_eq!(h.audio_files().len(), 1);
+    assert_eq!(h.store.audio("unused", &kept.id).await.unwrap(), recording(1));
+}
+
+#[tokio::test]
+async fn garbage_collector_removes_stray_files() {
+    let h = Harness::start().await;
+    let memo = h.store.create("unused", recording(1), "00:01".into(), vec![]).await.unwrap();
+    let stray = h.dir.path().join("audio").join("ab").join(AudioStore::hash(b"stray"));
+    std::fs::create_dir_all(stray.parent().unwrap()).unwrap();
+    std::fs::write(&stray, b"stray").unwrap();
+
+    let report = h.audio().collect_garbage(&h.pool, Duration::zero()).await.unwrap();
+    assert_eq!((report.removed_files, report.freed_bytes), (1, 5));
+    assert!(!stray.exists());
+    assert!(h.store.get("unused", &memo.id).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn inline_recordings_are_moved_into_files() {
+    let h = Harness::start().await;
+    let audio = recording(7);
+    sqlx::query(
+        "INSERT INTO voice_memos (id, name, date, duration, audio_blob, updated_at)
+         VALUES ('legacy', 'Old memo', '2024-05-01T10:00:00.000Z', '00:02', ?, '2024-05-01T10:00:00.000Z')",
+    )
+    .bind(&audio)
+    .execute(&h.pool)
+    .await
+    .unwrap();
+
+    assert_eq!(h.audio().adopt_inline(&h.pool).await.unwrap(), 1);
+    assert_eq!(h.audio().adopt_inline(&h.pool).await.unwrap(), 0);
+    assert_eq!(h.store.audio("unused", "legacy").await.unwrap(), audio);
+    assert_eq!(h.refcount(&AudioStore::hash(&audio)).await, 1);
+    let inline: Vec<u8> = sqlx::query_scalar("SELECT audio_blob FROM voice_memos WHERE id = 'legacy'")
+        .fetch_one(&h.pool)
+        .await
+        .unwrap();
+    assert!(inline.is_empty());
+}
+
+#[tokio::test]
+async fn damaged_files_are_written_again() {
+    let h = Harness::start().await;
+    let first = h.store.create("unused", recording(1), "00:01".into(), vec![]).await.unwrap();
+    let files = h.audio_files();
+    std::fs::write(&files[0], b"truncated").unwrap();
+
+    h.store.create("unused", recording(1), "00:01".into(), vec![]).await.unwrap();
+    assert_eq!(std::fs::read(&files[0]).unwrap(), recording(1));
+    assert_eq!(h.store.audio("unused", &first.id).await.unwrap(), recording(1));
+}
+
+#[tokio::test]
+async fn garbage_collector_waits_for_recordings_being_saved() {
+    let h = Harness::start().await;
+    let dropped = h.store.create("unused", recording(2), "00:01".into(), vec![]).await.unwrap();
+    h.store.delete("unused", &dropped.id).await.unwrap();
+    trash::empty_trash(h.app.handle(), &h.pool).await.unwrap();
+    sqlx::query("UPDATE audio_blobs SET touched_at = '2000-01-01T00:00:00.000Z'")
+        .execute(&h.pool)
+        .await
+        .unwrap();
+
+    // The same recording is saved again while the collector runs.
+    let audio = h.audio();
+    let mut tx = h.pool.begin().await.unwrap();
+    audio.put(&mut tx, &recording(2)).await.unwrap();
+    let (report, _) = tokio::join!(audio.collect_garbage(&h.pool, Duration::minutes(10)), async {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        tx.commit().await.unwrap();
+    });
+    assert_eq!(report.unwrap().removed_files, 0);
+    assert_eq!(h.audio_files().len(), 1);
+}
@@ -0,0 +1,202 @@
+//! Library backup archives: export, merge and replace imports, duplicate
+//! detection and settings.
+
+mod support;
+
+use app_lib::backup::{self, DuplicateReason, ImportMode, ImportOptions};
+use app_lib::error::ErrorKind;
+use app_lib::memo_store::{MemoStore, MemoUpdate};
+use app_lib::memo_ops::MemoQuery;
+use app_lib::storage::QuotaConfig;
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use support::Harness;
+use tauri::Listener;
+
+impl Harness {
+    async fn memo(&self, title: &str, audio: &[u8], tags: &[&str]) -> String {
+        let id = self.store.create("unused", audio.to_vec(), "00:03".into(), vec![]).await.unwrap().id;
+        let update = MemoUpdate {
+            title: title.to_string(),
+            transcript: Some(format!("transcript of {}", title)),
+            translate: None,
+            summary: Some("summary".into()),
+            tags: Some(tags.iter().map(|t| t.to_string()).collect()),
+        };
+        self.store.update("unused", &id, update).await.unwrap();
+        id
+    }
+
+    fn settings(&self) -> PathBuf {
+        self.dir.path().join("config").join("config.json")
+    }
+
+    fn archive(&self) -> PathBuf {
+        self.dir.path().join("backup.zip")
+    }
+
+    async fn titles(&self) -> Vec<String> {
+        let page = self.store.list("unused", &MemoQuery::default()).await.unwrap();
+        let mut titles: Vec<String> = page.items.into_iter().map(|m| m.title).collect();
+        titles.sort();
+        titles
+    }
+
+    async fn export(&self) {
+        backup::export_library(self.app.handle(), &self.pool, &self.archive(), &self.settings())
+            .await
+            .unwrap();
+    }
+
+    async fn import(&self, archive: &std::path::Path, options: ImportOptions) -> backup::ImportReport {
+        backup::import_library(self.app.handle(), &self.pool, archive, &options, &self.settings())
+            .await
+            .unwrap()
+    }
+}
+
+#[tokio::test]
+async fn export_and_import_round_trip_into_an_empty_library() {
+    let source = Harness::start().await;
+    let first = source.memo("Groceries", b"first recording", &["home"]).await;
+    source.memo("Standup", b"second recording", &["work", "daily"]).await;
+    std::fs::create_dir_all(source.settings().parent().unwrap()).unwrap();
+    std::fs::write(source.settings(), r#"{"trash": {"retention_days": 7}}"#).unwrap();
+
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let seen = progress.clone();
+    source.app.handle().listen("backup:progress", move |event| {
+        let payload: serde_json::Value = serde_json::from_str(event.payload()).unwrap();
+        seen.lock().unwrap().push(payload["done"].as_u64().unwrap());
+    });
+    let report = backup::export_library(source.app.handle(), &source.pool, &source.archive(), &source.settings())
+        .await
+        .unwrap();
+    assert_eq!((report.memos, report.recordings), (2, 2));
+    assert_eq!(*progress.lock().unwrap(), [1, 2]);
+
+    let target = Harness::start().await;
+    let options = ImportOptions {
+        restore_settings: true,
+        ..Default::default()
+    };
+    let report = target.import(&source.archive(), options).await;
+    assert_eq!(report.imported, 2);
+    assert!(report.settings_restored);
+    assert_eq!(target.titles().await, ["Groceries", "Standup"]);
+
+    let memo = target.store.get("unused", &first).await.unwrap().unwrap();
+    assert_eq!(memo.tags.unwrap(), ["home"]);
+    assert_eq!(memo.transcript.as_deref(), Some("transcript of Groceries"));
+    assert_eq!(memo.audio_blob.unwrap(), b"first recording");
+    let settings: serde_json::Value = serde_json::from_slice(&std::fs::read(target.settings()).unwrap()).unwrap();
+    assert_eq!(settings, json!({"trash": {"retention_days": 7}}));
+}
+
+#[tokio::test]
+async fn merging_skips_memos_already_in_the_library() {
+    let h = Harness::start().await;
+    h.memo("Groceries", b"first recording", &[]).await;
+    h.export().await;
+    h.memo("Standup", b"second recording", &[]).await;
+
+    // Same id.
+    let report = h.import(&h.archive(), ImportOptions::default()).await;
+    assert_eq!(report.imported, 0);
+    assert_eq!(report.duplicates[0].reason, DuplicateReason::Id);
+
+    // Same recording under another id, e.g. exported from another device.
+    let other = Harness::start().await;
+    other.memo("Standup copy", b"second recording", &[]).await;
+    other.memo("Call mum", b"third recording", &[]).await;
+    other.export().await;
+    let report = h.import(&other.archive(), ImportOptions::default()).await;
+    assert_eq!(report.imported, 1);
+    assert_eq!(report.duplicates.len(), 1);
+    assert_eq!(
+        (report.duplicates[0].title.as_str(), report.duplicates[0].reason),
+        ("Standup copy", DuplicateReason::Audio)
+    );
+    assert_eq!(h.titles().await, ["Call mum", "Groceries", "Standup"]);
+}
+
+#[tokio::test]
+async fn replacing_drops_the_current_library() {
+    let source = Harness::start().await;
+    source.memo("Groceries", b"first recording", &[]).await;
+    source.export().await;
+
+    let target = Harness::start().await;
+    target.memo("Standup", b"second recording", &[]).await;
+    let options = ImportOptions {
+        mode: ImportMode::Replace,
+        ..Default::default()
+    };
+    let report = target.import(&source.archive(), options).await;
+    assert_eq!((report.replaced, report.imported), (1, 1));
+    assert_eq!(target.titles().await, ["Groceries"]);
+    assert!(!report.settings_restored);
+    assert!(!target.settings().exists());
+}
+
+#[tokio::test]
+async fn replacing_deletes_synced_memos_on_the_backend_later() {
+    let source = Harness::start().await;
+    source.memo("Groceries", b"first recording", &[]).await;
+    source.export().await;
+
+    let target = Harness::start().await;
+    let synced = target.memo("Standup", b"second recording", &[]).await;
+    sqlx::query("UPDATE voice_memos SET remote_id = 'remote-1', dirty = 0 WHERE id = ?")
+        .bind(&synced)
+        .execute(&target.pool)
+        .await
+        .unwrap();
+    sqlx::query(
+        "INSERT INTO outbox (operation, memo_id, payload, created_at) VALUES ('update', 'remote-1', '{}', '2024-01-01T00:00:00Z')",
+    )
+    .execute(&target.pool)
+    .await
+    .unwrap();
+
+    let options = ImportOptions {
+        mode: ImportMode::Replace,
+        ..Default::default()
+    };
+    target.import(&source.archive(), options).await;
+    let tombstones: Vec<String> = sqlx::query_scalar("SELECT remote_id FROM sync_tombstones")
+        .fetch_all(&target.pool)
+        .await
+        .unwrap();
+    assert_eq!(tombstones, ["remote-1"]);
+    let queued: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM outbox")
+        .fetch_one(&target.pool)
+        .await
+        .unwrap();
+    assert_eq!(queued, 0);
+}
+
+#[tokio::test]
+async fn importing_respects_the_storage_quota() {
+    let source = Harness::start().await;
+    source.memo("Lecture", &vec![7; 2 * 1024 * 1024], &[]).await;
+    source.export().await;
+
+    let target = Harness::with_quota(QuotaConfig {
+        soft_limit_mb: None,
+        hard_limit_mb: Some(1),
+    })
+    .await;
+    let err = backup::import_library(
+        target.app.handle(),
+        &target.pool,
+        &source.archive(),
+        &ImportOptions::default(),
+        &target.settings(),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::QuotaExceeded);
+    assert!(target.titles().await.is_empty());
+}
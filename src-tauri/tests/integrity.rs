@@ -0,0 +1,155 @@
+//! Library integrity checks: memos against their recordings, orphans and
+//! what `repair_library` can fix.
+
+mod support;
+
+use app_lib::audio_cache::AudioCache;
+use app_lib::audio_store::AudioStore;
+use app_lib::integrity::{self, IntegrityReport, IssueKind};
+use app_lib::memo_store::MemoStore;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use support::{recording, Harness};
+use tauri::Manager;
+
+impl Harness {
+    /// Saves [`recording`] with the duration it was made for.
+    async fn memo(&self, seed: u8) -> (String, String) {
+        let audio = recording(seed);
+        let hash = AudioStore::hash(&audio);
+        let id = self.store.create("unused", audio, "00:10".into(), vec![]).await.unwrap().id;
+        (id, hash)
+    }
+
+    fn file(&self, hash: &str) -> PathBuf {
+        self.dir.path().join("audio").join(&hash[..2]).join(hash)
+    }
+
+    async fn execute(&self, sql: &str) {
+        sqlx::query(sql).execute(&self.pool).await.unwrap();
+    }
+
+    async fn verify(&self) -> IntegrityReport {
+        integrity::verify_library(self.app.handle()).await.unwrap()
+    }
+}
+
+fn kinds(report: &IntegrityReport) -> Vec<IssueKind> {
+    let mut kinds: Vec<IssueKind> = report.issues.iter().map(|i| i.kind).collect();
+    kinds.sort_by_key(|k| format!("{:?}", k));
+    kinds
+}
+
+#[tokio::test]
+async fn a_healthy_library_has_no_issues() {
+    let h = Harness::start().await;
+    h.memo(1).await;
+    h.memo(2).await;
+
+    let report = h.verify().await;
+    assert_eq!((report.memos_checked, report.recordings_checked), (2, 2));
+    assert!(report.issues.is_empty(), "{:?}", report.issues);
+}
+
+#[tokio::test]
+async fn repairs_what_it_can_and_reports_the_rest() {
+    let h = Harness::start().await;
+    let (missing, missing_hash) = h.memo(1).await;
+    let (_, damaged_hash) = h.memo(2).await;
+    let (mislabeled, mislabeled_hash) = h.memo(3).await;
+    let (too_long, _) = h.memo(4).await;
+
+    // Missing file, but the recording was cached when it was played.
+    std::fs::remove_file(h.file(&missing_hash)).unwrap();
+    h.app.state::<AudioCache>().put(&missing, &recording(1)).await.unwrap();
+    std::fs::write(h.file(&damaged_hash), recording(9)).unwrap();
+    h.execute(&format!(
+        "UPDATE voice_memos SET audio_mime = 'audio/wav', audio_size = 1 WHERE id = '{}'",
+        mislabeled
+    ))
+    .await;
+    h.execute(&format!(
+        "UPDATE audio_blobs SET refcount = 7 WHERE hash = '{}'",
+        mislabeled_hash
+    ))
+    .await;
+    h.execute(&format!("UPDATE voice_memos SET duration = '09:00:00' WHERE id = '{}'", too_long)).await;
+    // A stray file from before the last crash.
+    let stray = AudioStore::hash(b"stray");
+    std::fs::create_dir_all(h.file(&stray).parent().unwrap()).unwrap();
+    std::fs::write(h.file(&stray), b"stray").unwrap();
+    let old = SystemTime::now() - Duration::from_secs(3600);
+    std::fs::File::options().write(true).open(h.file(&stray)).unwrap().set_modified(old).unwrap();
+
+    let expected = [
+        IssueKind::ChecksumMismatch,
+        IssueKind::ImplausibleDuration,
+        IssueKind::MissingRecording,
+        IssueKind::OrphanFile,
+        IssueKind::RefcountDrift,
+        IssueKind::WrongMetadata,
+    ];
+    let report = h.verify().await;
+    assert_eq!(kinds(&report), expected);
+    assert!(report.issues.iter().all(|i| !i.repaired));
+    // Verifying changes nothing.
+    assert_eq!(kinds(&h.verify().await), expected);
+
+    let report = integrity::repair_library(h.app.handle()).await.unwrap();
+    let unrepaired: Vec<IssueKind> = report.issues.iter().filter(|i| !i.repaired).map(|i| i.kind).collect();
+    assert_eq!(unrepaired, [IssueKind::ChecksumMismatch, IssueKind::ImplausibleDuration]);
+
+    assert_eq!(kinds(&h.verify().await), [IssueKind::ChecksumMismatch, IssueKind::ImplausibleDuration]);
+    assert_eq!(h.store.audio("unused", &missing).await.unwrap(), recording(1));
+    assert!(!h.file(&stray).exists());
+}
+
+#[tokio::test]
+async fn repairs_database_leftovers() {
+    let h = Harness::start().await;
+    // A memo from before the audio store, and tags of a memo deleted while
+    // foreign keys were off.
+    h.execute(
+        "INSERT INTO voice_memos (id, name, date, duration, audio_blob, audio_mime, audio_size)
+         VALUES ('inline', 'Old memo', '2024-01-01T00:00:00.000Z', '00:10', X'1A45DFA3', 'audio/webm', 4)",
+    )
+    .await;
+    let mut conn = h.pool.acquire().await.unwrap();
+    sqlx::query("PRAGMA foreign_keys = OFF").execute(&mut *conn).await.unwrap();
+    sqlx::query("INSERT INTO memo_tags (memo_id, position, tag) VALUES ('gone', 0, 'work')")
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    sqlx::query("PRAGMA foreign_keys = ON").execute(&mut *conn).await.unwrap();
+    drop(conn);
+
+    let report = integrity::repair_library(h.app.handle()).await.unwrap();
+    let repaired: Vec<IssueKind> = report.issues.iter().filter(|i| i.repaired).map(|i| i.kind).collect();
+    assert_eq!(repaired, [IssueKind::InlineRecording, IssueKind::OrphanRows]);
+
+    let hash: Option<String> = sqlx::query_scalar("SELECT audio_hash FROM voice_memos WHERE id = 'inline'")
+        .fetch_one(&h.pool)
+        .await
+        .unwrap();
+    assert!(hash.is_some());
+    let report = h.verify().await;
+    assert!(!report.issues.iter().any(|i| i.kind == IssueKind::OrphanRows));
+}
+
+#[tokio::test]
+async fn durations_that_do_not_parse_are_implausible() {
+    let h = Harness::start().await;
+    for (seed, duration) in [(1, "18446744073709551615:00"), (2, "0:0:0:10"), (3, "+10"), (4, "1::10")] {
+        let (id, _) = h.memo(seed).await;
+        h.execute(&format!("UPDATE voice_memos SET duration = '{}' WHERE id = '{}'", duration, id)).await;
+    }
+
+    let report = h.verify().await;
+    assert_eq!(kinds(&report), [IssueKind::ImplausibleDuration; 4]);
+    // The column listings sort on rejects them too.
+    let sorted: Vec<i64> = sqlx::query_scalar("SELECT duration_secs FROM voice_memos")
+        .fetch_all(&h.pool)
+        .await
+        .unwrap();
+    assert_eq!(sorted, [0; 4]);
+}
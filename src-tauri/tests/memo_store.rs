@@ -0,0 +1,296 @@
+//! Runs the same `MemoStore` contract against the remote store (talking to
+//! the mock backend) and the local SQLite store.
+
+mod support;
+
+use app_lib::audio_cache::AudioCache;
+use app_lib::audio_store::AudioStore;
+use app_lib::db;
+use app_lib::error::ErrorKind;
+use app_lib::idempotency::IdempotencyStore;
+use app_lib::local_store::LocalMemoStore;
+use app_lib::memo_ops::{MemoQuery, MemoSortField, SortDirection};
+use app_lib::memo_store::{MemoStore, MemoUpdate, RemoteMemoStore};
+use app_lib::outbox::Outbox;
+use app_lib::session::SessionState;
+use app_lib::trash::{self, TrashConfig};
+use app_lib::upload::{UploadConfig, UploadManager};
+use app_lib::vault::Vault;
+use sqlx::SqlitePool;
+use support::mock_backend::MockBackend;
+use tauri::test::mock_app;
+use tauri::Manager;
+use tempfile::TempDir;
+
+fn update(title: &str, transcript: Option<&str>, tags: &[&str]) -> MemoUpdate {
+    MemoUpdate {
+        title: title.to_string(),
+        transcript: transcript.map(String::from),
+        translate: None,
+        summary: None,
+        tags: Some(tags.iter().map(|t| t.to_string()).collect()),
+    }
+}
+
+async fn exercise(store: &dyn MemoStore, token: &str) {
+    let audio = vec![0x1A, 0x45, 0xDF, 0xA3, 1, 2, 3, 4];
+    let created = store
+        .create(token, audio.clone(), "00:08".into(), vec!["inbox".into()])
+        .await
+        .unwrap();
+    assert_eq!(created.audio_blob.as_deref(), Some(audio.as_slice()));
+    assert_eq!(created.tags.as_deref(), Some(&["inbox".to_string()][..]));
+
+    let mut ids = vec![created.id.clone()];
+    for (i, title) in ["Bravo", "Alpha"].iter().enumerate() {
+        let memo = store
+            .create(token, vec![0x4F, 0x67, 0x67, 0x53, i as u8], "00:02".into(), vec![])
+            .await
+            .unwrap();
+        store
+            .update(token, &memo.id, update(title, Some("spoken words"), &["work", "idea"]))
+            .await
+            .unwrap();
+        ids.push(memo.id);
+    }
+    store
+        .update(token, &created.id, update("Charlie", None, &["inbox"]))
+        .await
+        .unwrap();
+
+    let fetched = store.get(token, &ids[1]).await.unwrap().unwrap();
+    assert_eq!(fetched.title, "Bravo");
+    assert_eq!(fetched.transcript.as_deref(), Some("spoken words"));
+    assert_eq!(fetched.tags, Some(vec!["work".to_string(), "idea".to_string()]));
+    assert!(store.get(token, "missing").await.unwrap().is_none());
+
+    let first = store
+        .list(
+            token,
+            &MemoQuery {
+                page_size: Some(2),
+                sort_by: MemoSortField::Title,
+                sort_dir: SortDirection::Asc,
+                ..MemoQuery::default()
+            },
+        )
+        .await
+        .unwrap();
+    let titles: Vec<_> = first.items.iter().map(|m| m.title.as_str()).collect();
+    assert_eq!(titles, ["Alpha", "Bravo"]);
+    assert!(first.items.iter().all(|m| m.audio_blob.is_none()));
+    let rest = store
+        .list(
+            token,
+            &MemoQuery {
+                cursor: first.next_cursor,
+                page_size: Some(2),
+                sort_by: MemoSortField::Title,
+                sort_dir: SortDirection::Asc,
+                ..MemoQuery::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(rest.items.len(), 1);
+    assert_eq!(rest.items[0].title, "Charlie");
+    assert!(rest.next_cursor.is_none());
+
+    let tagged = store
+        .list_summaries(
+            token,
+            &MemoQuery {
+                tags: Some(vec!["idea".into(), "work".into()]),
+                ..MemoQuery::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(tagged.items.len(), 2);
+    assert!(tagged.items.iter().all(|s| s.has_transcript && !s.has_summary));
+
+    let untranscribed = store
+        .list(
+            token,
+            &MemoQuery {
+                has_transcript: Some(false),
+                ..MemoQuery::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(untranscribed.items.len(), 1);
+    assert_eq!(untranscribed.items[0].id, created.id);
+
+    assert_eq!(store.audio(token, &created.id).await.unwrap(), audio);
+    // Every recording above was stored in one go, so nothing is left half sent.
+    assert!(store.resume_uploads(token).await.unwrap().is_empty());
+
+    store.delete(token, &ids[0]).await.unwrap();
+    assert_eq!(
+        store.delete(token, &ids[0]).await.unwrap_err().kind,
+        ErrorKind::NotFound
+    );
+    assert_eq!(
+        store.update(token, &ids[0], update("Gone", None, &[])).await.unwrap_err().kind,
+        ErrorKind::NotFound
+    );
+    assert_eq!(store.audio(token, &ids[0]).await.unwrap_err().kind, ErrorKind::NotFound);
+
+    // Both stores move memos to the trash instead of deleting them.
+    assert!(store.delete_all(token).await.unwrap().contains("2 memos"));
+    let empty = store.list(token, &MemoQuery::default()).await.unwrap();
+    assert!(empty.items.is_empty());
+}
+
+/// Durations sort by length, and a cursor still points at the right memo
+/// after the page before it changed.
+async fn exercise_paging(store: &dyn MemoStore, token: &str) {
+    for (i, duration) in ["59:00", "1:05:00", "00:30"].iter().enumerate() {
+        store
+            .create(token, vec![0x1A, 0x45, 0xDF, 0xA3, i as u8], duration.to_string(), vec![])
+            .await
+            .unwrap();
+    }
+    let by_duration = |cursor: Option<String>, sort_dir: SortDirection| MemoQuery {
+        cursor,
+        page_size: Some(1),
+        sort_by: MemoSortField::Duration,
+        sort_dir,
+        ..MemoQuery::default()
+    };
+
+    let mut seen = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = store.list(token, &by_duration(cursor, SortDirection::Desc)).await.unwrap();
+        seen.extend(page.items.into_iter().map(|m| m.duration));
+        cursor = page.next_cursor;
+        if cursor.is_none() {
+            break;
+        }
+    }
+    assert_eq!(seen, ["1:05:00", "59:00", "00:30"]);
+
+    let first = store.list(token, &by_duration(None, SortDirection::Asc)).await.unwrap();
+    assert_eq!(first.items[0].duration, "00:30");
+    store.delete(token, &first.items[0].id).await.unwrap();
+    let next = store
+        .list(token, &by_duration(first.next_cursor, SortDirection::Asc))
+        .await
+        .unwrap();
+    assert_eq!(next.items[0].duration, "59:00");
+
+    let err = store
+        .list(token, &by_duration(Some("not a cursor".into()), SortDirection::Asc))
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Validation);
+}
+
+#[tokio::test]
+async fn remote_store_contract() {
+    let backend = MockBackend::start().await;
+    let token = backend.register("ada@example.com", "hunter22");
+    let dir = TempDir::new().unwrap();
+    let app = mock_app();
+    app.manage(backend.client());
+    app.manage(IdempotencyStore::load(dir.path().to_path_buf()).unwrap());
+    app.manage(UploadManager::new(dir.path().to_path_buf(), UploadConfig::default()).unwrap());
+    app.manage(AudioCache::new(dir.path().join("cache")).unwrap());
+    app.manage(AudioStore::new(dir.path().to_path_buf()).unwrap());
+    let pool = db::open(&dir.path().join(db::DATABASE_FILE)).await.unwrap();
+    app.manage(pool.clone());
+    app.manage(Outbox::new(pool));
+
+    let store = RemoteMemoStore::new(app.handle().clone());
+    exercise(&store, &token).await;
+    exercise_paging(&store, &token).await;
+
+    // Trashed memos stay on the backend until the trash is emptied.
+    let pool = app.state::<SqlitePool>();
+    let trashed = trash::list_trash(&pool, &TrashConfig::default()).await.unwrap();
+    assert_eq!(trashed.len(), 4);
+    assert_eq!(backend.memo_count(), 6);
+    trash::restore_memo(app.handle(), &pool, &trashed[0].memo.id).await.unwrap();
+    assert!(store.get(&token, &trashed[0].memo.id).await.unwrap().is_some());
+    assert_eq!(store.list(&token, &MemoQuery::default()).await.unwrap().items.len(), 3);
+
+    let session = SessionState::load(dir.path().to_path_buf()).unwrap();
+    session.start(token.clone(), "ada@example.com").unwrap();
+    app.manage(session);
+    assert_eq!(trash::empty_trash(app.handle(), &pool).await.unwrap(), 3);
+    assert_eq!(backend.memo_count(), 3);
+}
+
+#[tokio::test]
+async fn remote_store_works_while_the_library_is_locked() {
+    let backend = MockBackend::start().await;
+    let token = backend.register("ada@example.com", "hunter22");
+    let dir = TempDir::new().unwrap();
+    let app = mock_app();
+    let vault = Vault::load(dir.path().to_path_buf()).unwrap();
+    app.manage(vault.clone());
+    app.manage(backend.client());
+    app.manage(IdempotencyStore::load(dir.path().to_path_buf()).unwrap());
+    app.manage(UploadManager::new(dir.path().to_path_buf(), UploadConfig::default()).unwrap());
+    app.manage(AudioCache::new(dir.path().join("cache")).unwrap().with_vault(vault.clone()));
+    app.manage(AudioStore::new(dir.path().to_path_buf()).unwrap().with_vault(vault.clone()));
+    let pool = db::open_with_vault(&vault).await.unwrap();
+    app.manage(pool.clone());
+    app.manage(Outbox::new(pool));
+    vault.set_passphrase(app.handle(), "correct horse battery").await.unwrap();
+    vault.lock(app.handle()).await.unwrap();
+
+    let store = RemoteMemoStore::new(app.handle().clone());
+    let memo = store
+        .create(&token, vec![0x1A, 0x45, 0xDF, 0xA3], "00:01".into(), vec![])
+        .await
+        .unwrap();
+    let updated = store.update(&token, &memo.id, update("Locked", None, &[])).await.unwrap();
+    assert_eq!(updated.title, "Locked");
+    assert!(store.get(&token, &memo.id).await.unwrap().is_some());
+    assert_eq!(store.list(&token, &MemoQuery::default()).await.unwrap().items.len(), 1);
+    // Trashing needs the local database.
+    let err = store.delete(&token, &memo.id).await.unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Locked);
+}
+
+#[tokio::test]
+async fn local_store_contract() {
+    let dir = TempDir::new().unwrap();
+    let app = mock_app();
+    app.manage(AudioStore::new(dir.path().to_path_buf()).unwrap());
+    let pool = db::open(&dir.path().join(db::DATABASE_FILE)).await.unwrap();
+    let store = LocalMemoStore::new(app.handle().clone(), pool);
+    exercise(&store, "unused").await;
+    exercise_paging(&store, "unused").await;
+
+    let err = store
+        .create("unused", Vec::new(), "00:00".into(), vec![])
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Validation);
+}
+
+#[tokio::test]
+async fn local_store_survives_reopen() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join(db::DATABASE_FILE);
+    let app = mock_app();
+    app.manage(AudioStore::new(dir.path().to_path_buf()).unwrap());
+
+    let store = LocalMemoStore::new(app.handle().clone(), db::open(&path).await.unwrap());
+    let memo = store
+        .create("unused", vec![b'O', b'g', b'g', b'S', 9], "00:01".into(), vec!["kept".into()])
+        .await
+        .unwrap();
+    drop(store);
+
+    // Migrations that already ran are skipped on the second open.
+    let store = LocalMemoStore::new(app.handle().clone(), db::open(&path).await.unwrap());
+    let reloaded = store.get("unused", &memo.id).await.unwrap().unwrap();
+    assert_eq!(reloaded.title, memo.title);
+    assert_eq!(reloaded.tags, Some(vec!["kept".to_string()]));
+    assert_eq!(reloaded.audio_blob, memo.audio_blob);
+}
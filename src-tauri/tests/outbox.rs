@@ -0,0 +1,237 @@
+//! Queues remote memo writes while the mock backend is unreachable and
+//! replays them once it is back.
+
+mod support;
+
+use app_lib::audio_cache::AudioCache;
+use app_lib::audio_store::AudioStore;
+use app_lib::db;
+use app_lib::error::ErrorKind;
+use app_lib::idempotency::IdempotencyStore;
+use app_lib::memo_ops::{MemoQuery, VoiceMemo};
+use app_lib::memo_store::{MemoStore, MemoUpdate, RemoteMemoStore};
+use app_lib::outbox::{is_pending_id, OperationKind, Outbox};
+use app_lib::session::SessionState;
+use app_lib::trash::{self, TrashConfig};
+use app_lib::upload::{UploadConfig, UploadManager};
+use sqlx::SqlitePool;
+use support::mock_backend::MockBackend;
+use tauri::test::{mock_app, MockRuntime};
+use tauri::{App, Manager};
+use tempfile::TempDir;
+
+struct Harness {
+    backend: MockBackend,
+    app: App<MockRuntime>,
+    store: RemoteMemoStore<MockRuntime>,
+    token: String,
+    dir: TempDir,
+}
+
+impl Harness {
+    async fn start() -> Self {
+        let backend = MockBackend::start().await;
+        let token = backend.register("ada@example.com", "hunter22");
+        let dir = TempDir::new().unwrap();
+        let app = mock_app();
+        app.manage(backend.client());
+        app.manage(IdempotencyStore::load(dir.path().to_path_buf()).unwrap());
+        app.manage(UploadManager::new(dir.path().to_path_buf(), UploadConfig::default()).unwrap());
+        app.manage(AudioCache::new(dir.path().join("cache")).unwrap());
+        app.manage(AudioStore::new(dir.path().to_path_buf()).unwrap());
+        let pool = db::open(&dir.path().join(db::DATABASE_FILE)).await.unwrap();
+        app.manage(pool.clone());
+        app.manage(Outbox::new(pool));
+        let store = RemoteMemoStore::new(app.handle().clone());
+        Self {
+            backend,
+            app,
+            store,
+            token,
+            dir,
+        }
+    }
+
+    fn outbox(&self) -> tauri::State<'_, Outbox> {
+        self.app.state::<Outbox>()
+    }
+
+    async fn create(&self, seed: u8) -> VoiceMemo {
+        self.store
+            .create(&self.token, vec![0x1A, 0x45, 0xDF, 0xA3, seed], "00:04".into(), vec![])
+            .await
+            .unwrap()
+    }
+
+    async fn rename(&self, id: &str, title: &str) -> VoiceMemo {
+        let update = MemoUpdate {
+            title: title.to_string(),
+            transcript: None,
+            translate: None,
+            summary: None,
+            tags: None,
+        };
+        self.store.update(&self.token, id, update).await.unwrap()
+    }
+
+    async fn remote_titles(&self) -> Vec<String> {
+        let page = self.store.list(&self.token, &MemoQuery::default()).await.unwrap();
+        let mut titles: Vec<_> = page.items.into_iter().map(|m| m.title).collect();
+        titles.sort();
+        titles
+    }
+}
+
+#[tokio::test]
+async fn queues_writes_while_offline_and_replays_them_in_order() {
+    let h = Harness::start().await;
+    let online = h.create(1).await;
+
+    h.backend.set_offline(true);
+    let queued = h.create(2).await;
+    assert!(is_pending_id(&queued.id));
+    h.rename(&online.id, "First draft").await;
+    h.rename(&online.id, "Second draft").await;
+    h.rename(&queued.id, "Written offline").await;
+
+    let pending = h.outbox().list().await.unwrap();
+    let ops: Vec<_> = pending.iter().map(|p| (p.operation, p.title.as_deref())).collect();
+    assert_eq!(
+        ops,
+        [
+            (OperationKind::Create, None),
+            (OperationKind::Update, Some("Second draft")),
+            (OperationKind::Update, Some("Written offline")),
+        ]
+    );
+    assert_eq!(pending[0].audio_size, Some(5));
+    assert_eq!(h.backend.memo_count(), 1);
+
+    h.backend.set_offline(false);
+    let report = h.outbox().replay(h.app.handle(), &h.token).await.unwrap();
+    assert_eq!((report.replayed, report.failed, report.remaining), (3, 0, 0));
+    assert_eq!(h.remote_titles().await, ["Second draft", "Written offline"]);
+    assert_eq!(h.backend.request_count("PATCH /api/update_memo"), 2);
+}
+
+#[tokio::test]
+async fn next_write_replays_the_queue_first() {
+    let h = Harness::start().await;
+    h.backend.set_offline(true);
+    let queued = h.create(1).await;
+    h.rename(&queued.id, "Queued").await;
+
+    h.backend.set_offline(false);
+    let memo = h.create(2).await;
+    assert!(!is_pending_id(&memo.id));
+    assert!(h.outbox().list().await.unwrap().is_empty());
+    assert_eq!(h.backend.memo_count(), 2);
+    assert!(h.remote_titles().await.contains(&"Queued".to_string()));
+}
+
+#[tokio::test]
+async fn deleting_drops_queued_writes() {
+    let h = Harness::start().await;
+    let online = h.create(1).await;
+
+    h.backend.set_offline(true);
+    let queued = h.create(2).await;
+    h.rename(&queued.id, "Never uploaded").await;
+    h.store.delete(&h.token, &queued.id).await.unwrap();
+    h.rename(&online.id, "Doomed edit").await;
+    h.store.delete(&h.token, &online.id).await.unwrap();
+
+    let pending = h.outbox().list().await.unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!((pending[0].operation, pending[0].memo_id.as_str()), (OperationKind::Delete, online.id.as_str()));
+
+    h.backend.set_offline(false);
+    h.outbox().replay(h.app.handle(), &h.token).await.unwrap();
+    assert!(h.remote_titles().await.is_empty());
+    assert_eq!(h.backend.request_count("POST /api/save_memo"), 1);
+
+    // The backend copy stays until the trash is emptied.
+    assert_eq!(h.backend.memo_count(), 1);
+    let session = SessionState::load(h.dir.path().to_path_buf()).unwrap();
+    session.start(h.token.clone(), "ada@example.com").unwrap();
+    h.app.manage(session);
+    trash::empty_trash(h.app.handle(), &h.app.state::<SqlitePool>()).await.unwrap();
+    assert_eq!(h.backend.memo_count(), 0);
+}
+
+#[tokio::test]
+async fn rejected_operations_wait_for_retry_or_discard() {
+    let h = Harness::start().await;
+    let kept = h.create(1).await;
+    let gone = h.create(2).await;
+
+    h.backend.set_offline(true);
+    h.rename(&gone.id, "Edit of a deleted memo").await;
+    h.rename(&kept.id, "Kept").await;
+    h.backend.set_offline(false);
+    h.backend.fail_matching(&format!("PATCH /api/update_memo/{}", gone.id), 404, 2);
+
+    let report = h.outbox().replay(h.app.handle(), &h.token).await.unwrap();
+    assert_eq!((report.replayed, report.failed, report.remaining), (1, 1, 1));
+    let failed = &h.outbox().list().await.unwrap()[0];
+    assert!(failed.failed);
+    // Queueing the second edit already tried the first one while offline.
+    assert_eq!(failed.attempts, 2);
+    assert!(failed.last_error.is_some());
+
+    // Failed operations are not replayed on their own.
+    let report = h.outbox().replay(h.app.handle(), &h.token).await.unwrap();
+    assert_eq!((report.replayed, report.failed), (0, 0));
+
+    h.outbox().reset_failed(Some(failed.id)).await.unwrap();
+    let report = h.outbox().replay(h.app.handle(), &h.token).await.unwrap();
+    assert_eq!(report.failed, 1);
+
+    h.outbox().discard(failed.id).await.unwrap();
+    assert!(h.outbox().list().await.unwrap().is_empty());
+    assert_eq!(h.outbox().discard(failed.id).await.unwrap_err().kind, ErrorKind::NotFound);
+}
+
+#[tokio::test]
+async fn queue_survives_restart() {
+    let h = Harness::start().await;
+    h.backend.set_offline(true);
+    h.create(1).await;
+
+    let reopened = Outbox::new(db::open(&h.dir.path().join(db::DATABASE_FILE)).await.unwrap());
+    let pending = reopened.list().await.unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].operation, OperationKind::Create);
+}
+
+#[tokio::test]
+async fn queues_writes_while_the_backend_keeps_answering_unavailable() {
+    let h = Harness::start().await;
+    h.backend.fail_next(&[503, 502, 503]);
+    let queued = h.create(1).await;
+    assert!(is_pending_id(&queued.id));
+    assert_eq!(h.backend.memo_count(), 0);
+
+    let report = h.outbox().replay(h.app.handle(), &h.token).await.unwrap();
+    assert_eq!((report.replayed, report.failed), (1, 0));
+    assert_eq!(h.backend.memo_count(), 1);
+}
+
+#[tokio::test]
+async fn deleting_everything_is_not_queued() {
+    let h = Harness::start().await;
+    h.create(1).await;
+    h.create(2).await;
+
+    // Which memos to delete is only known once they are listed, so while
+    // offline nothing is moved to the trash or queued.
+    h.backend.set_offline(true);
+    let err = h.store.delete_all(&h.token).await.unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Network);
+    assert!(h.outbox().list().await.unwrap().is_empty());
+    let trashed = trash::list_trash(&h.app.state::<SqlitePool>(), &TrashConfig::default()).await.unwrap();
+    assert!(trashed.is_empty());
+
+    h.backend.set_offline(false);
+    assert_eq!(h.remote_titles().await.len(), 2);
+}
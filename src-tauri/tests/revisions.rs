@@ -0,0 +1,141 @@
+//! Revision history of memo edits: recording, diffs and reverts.
+
+mod support;
+
+use app_lib::error::ErrorKind;
+use app_lib::memo_store::{MemoStore, MemoUpdate, StorageBackend};
+use app_lib::revisions::{self, diff_text, ChangeKind, MemoField, RevisionSource, TextChange};
+use support::{recording, Harness};
+
+impl Harness {
+    async fn memo(&self) -> String {
+        self.store.create("unused", recording(1), "00:10".into(), vec![]).await.unwrap().id
+    }
+
+    async fn save(&self, id: &str, title: &str, summary: Option<&str>, tags: &[&str]) {
+        let update = MemoUpdate {
+            title: title.to_string(),
+            transcript: Some("the meeting starts at noon".into()),
+            translate: None,
+            summary: summary.map(String::from),
+            tags: Some(tags.iter().map(|t| t.to_string()).collect()),
+        };
+        self.store.update("unused", id, update).await.unwrap();
+    }
+}
+
+fn change(kind: ChangeKind, text: &str) -> TextChange {
+    TextChange {
+        kind,
+        text: text.to_string(),
+    }
+}
+
+#[test]
+fn diffs_text_word_by_word() {
+    assert_eq!(
+        diff_text("call the office today", "call the bank today"),
+        [
+            change(ChangeKind::Equal, "call the "),
+            change(ChangeKind::Delete, "office"),
+            change(ChangeKind::Insert, "bank"),
+            change(ChangeKind::Equal, " today"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn every_save_records_the_changed_fields() {
+    let h = Harness::start().await;
+    let id = h.memo().await;
+    h.save(&id, "Standup", None, &["work"]).await;
+    h.save(&id, "Standup", Some("Short sync"), &["work"]).await;
+    // Saving the same text again adds nothing.
+    h.save(&id, "Standup", Some("Short sync"), &["work"]).await;
+
+    let history = revisions::list_revisions(&h.pool, &id).await.unwrap();
+    let sources: Vec<_> = history.iter().map(|r| r.source).collect();
+    assert_eq!(sources, [RevisionSource::Edit, RevisionSource::Edit, RevisionSource::Created]);
+    assert_eq!(history[0].changed_fields, [MemoField::Summary]);
+    assert_eq!(
+        history[1].changed_fields,
+        [MemoField::Title, MemoField::Transcript, MemoField::Tags]
+    );
+    assert_eq!(history[0].summary.as_deref(), Some("Short sync"));
+    assert_eq!(history[1].tags, ["work"]);
+}
+
+#[tokio::test]
+async fn diffs_two_revisions_field_by_field() {
+    let h = Harness::start().await;
+    let id = h.memo().await;
+    h.save(&id, "Weekly standup", None, &["work"]).await;
+    h.save(&id, "Weekly planning", Some("Roadmap"), &["work", "planning"]).await;
+    let history = revisions::list_revisions(&h.pool, &id).await.unwrap();
+
+    let diff = revisions::diff_revisions(&h.pool, &id, history[1].id, history[0].id).await.unwrap();
+    let fields: Vec<_> = diff.fields.iter().map(|f| f.field).collect();
+    assert_eq!(fields, [MemoField::Title, MemoField::Summary, MemoField::Tags]);
+    assert_eq!(
+        diff.fields[0].changes,
+        [
+            change(ChangeKind::Equal, "Weekly "),
+            change(ChangeKind::Delete, "standup"),
+            change(ChangeKind::Insert, "planning"),
+        ]
+    );
+    assert_eq!(diff.fields[1].changes, [change(ChangeKind::Insert, "Roadmap")]);
+
+    let other = h.memo().await;
+    let err = revisions::diff_revisions(&h.pool, &other, history[1].id, history[0].id)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::NotFound);
+}
+
+#[tokio::test]
+async fn reverting_restores_fields_as_a_new_revision() {
+    let h = Harness::start().await;
+    let id = h.memo().await;
+    h.save(&id, "Budget", Some("Spend less"), &["money"]).await;
+    let good = revisions::list_revisions(&h.pool, &id).await.unwrap()[0].id;
+    h.save(&id, "Budget", Some("A bad AI summary"), &[]).await;
+
+    let reverted = revisions::revert_to_revision(h.app.handle(), &h.pool, &id, good).await.unwrap();
+    assert_eq!(reverted.source, RevisionSource::Revert);
+    assert_eq!(reverted.changed_fields, [MemoField::Summary, MemoField::Tags]);
+
+    let memo = h.store.get("unused", &id).await.unwrap().unwrap();
+    assert_eq!(memo.summary.as_deref(), Some("Spend less"));
+    assert_eq!(memo.tags.unwrap(), ["money"]);
+    let dirty: bool = sqlx::query_scalar("SELECT dirty FROM voice_memos WHERE id = ?")
+        .bind(&id)
+        .fetch_one(&h.pool)
+        .await
+        .unwrap();
+    assert!(dirty);
+    assert_eq!(revisions::list_revisions(&h.pool, &id).await.unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn keeps_the_original_text_of_memos_without_history() {
+    let h = Harness::start().await;
+    let id = h.memo().await;
+    h.save(&id, "Old title", None, &[]).await;
+    // Memos saved before revisions were recorded have none.
+    sqlx::query("DELETE FROM memo_revisions").execute(&h.pool).await.unwrap();
+
+    h.save(&id, "New title", None, &[]).await;
+    let history = revisions::list_revisions(&h.pool, &id).await.unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!((history[1].source, history[1].title.as_str()), (RevisionSource::Original, "Old title"));
+    assert_eq!(history[0].changed_fields, [MemoField::Title]);
+}
+
+#[test]
+fn revision_history_is_only_kept_for_local_memos() {
+    assert!(StorageBackend::Local.require_local("Revision history").is_ok());
+    let err = StorageBackend::Remote.require_local("Revision history").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Validation);
+    assert!(err.message.starts_with("Revision history"));
+}
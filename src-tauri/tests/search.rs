@@ -0,0 +1,118 @@
+//! Full-text search over memos in the local database.
+
+mod support;
+
+use app_lib::error::ErrorKind;
+use app_lib::memo_store::{MemoStore, MemoUpdate};
+use app_lib::search::{search_memos, SearchQuery, SearchResults};
+use support::Harness;
+
+impl Harness {
+    async fn memo(&self, title: &str, transcript: &str, summary: Option<&str>) -> String {
+        let audio = [vec![0x1A, 0x45, 0xDF, 0xA3], title.as_bytes().to_vec()].concat();
+        let memo = self.store.create("unused", audio, "00:10".into(), vec![]).await.unwrap();
+        let update = MemoUpdate {
+            title: title.to_string(),
+            transcript: Some(transcript.to_string()),
+            translate: None,
+            summary: summary.map(String::from),
+            tags: None,
+        };
+        self.store.update("unused", &memo.id, update).await.unwrap();
+        memo.id
+    }
+
+    async fn search(&self, text: &str) -> SearchResults {
+        let query = SearchQuery {
+            text: text.to_string(),
+            ..SearchQuery::default()
+        };
+        search_memos(&self.pool, &query).await.unwrap()
+    }
+
+    async fn titles(&self, text: &str) -> Vec<String> {
+        self.search(text).await.hits.into_iter().map(|h| h.memo.title).collect()
+    }
+}
+
+#[tokio::test]
+async fn ranks_title_matches_first_and_highlights() {
+    let h = Harness::start().await;
+    h.memo("Grocery list", "We talked about the budget for the garden", None).await;
+    h.memo("Garden plans", "Tomatoes along the fence", None).await;
+    h.memo("Standup", "Nothing about plants", Some("Team sync")).await;
+
+    let results = h.search("garden").await;
+    assert_eq!(results.total, 2);
+    let titles: Vec<_> = results.hits.iter().map(|hit| hit.memo.title.as_str()).collect();
+    assert_eq!(titles, ["Garden plans", "Grocery list"]);
+    assert!(results.hits[0].score > results.hits[1].score);
+    assert_eq!(results.hits[0].title_html, "<mark>Garden</mark> plans");
+    assert!(results.hits[1].snippet_html.contains("<mark>garden</mark>"));
+
+    assert_eq!(h.titles("team").await, ["Standup"]);
+}
+
+#[tokio::test]
+async fn supports_prefix_and_phrase_queries() {
+    let h = Harness::start().await;
+    h.memo("Call notes", "the quarterly report is late", None).await;
+    h.memo("Ideas", "report the late quarterly numbers", None).await;
+
+    assert_eq!(h.search("quart*").await.total, 2);
+    assert_eq!(h.search("quart").await.total, 0);
+    assert_eq!(h.titles("\"quarterly report\"").await, ["Call notes"]);
+    assert_eq!(h.titles("late \"quarterly numbers\"").await, ["Ideas"]);
+    // Operators and stray punctuation are searched as plain words.
+    assert_eq!(h.search("NOT (report)").await.total, 0);
+    assert_eq!(h.search("report -late").await.total, 2);
+    assert_eq!(h.search("?!").await.total, 0);
+}
+
+#[tokio::test]
+async fn index_follows_edits_and_deletes() {
+    let h = Harness::start().await;
+    let id = h.memo("Draft", "first version mentions apples", None).await;
+    assert_eq!(h.search("apples").await.total, 1);
+
+    h.store
+        .update(
+            "unused",
+            &id,
+            MemoUpdate {
+                title: "Draft".into(),
+                transcript: Some("second version mentions pears".into()),
+                translate: Some("deuxième version avec des poires".into()),
+                summary: None,
+                tags: None,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(h.search("apples").await.total, 0);
+    assert_eq!(h.search("pears").await.total, 1);
+    // Diacritics are folded.
+    assert_eq!(h.search("deuxieme").await.total, 1);
+
+    h.store.delete("unused", &id).await.unwrap();
+    assert_eq!(h.search("pears").await.total, 0);
+}
+
+#[tokio::test]
+async fn escapes_html_and_pages_results() {
+    let h = Harness::start().await;
+    for i in 0..3 {
+        h.memo(&format!("<b>Meeting</b> {}", i), "meeting notes", None).await;
+    }
+    let query = SearchQuery {
+        text: "meeting".into(),
+        limit: Some(2),
+        offset: Some(2),
+    };
+    let page = search_memos(&h.pool, &query).await.unwrap();
+    assert_eq!((page.total, page.hits.len()), (3, 1));
+    assert!(page.hits[0].title_html.starts_with("&lt;b&gt;<mark>Meeting</mark>&lt;/b&gt;"));
+
+    let empty = SearchQuery::default();
+    assert_eq!(search_memos(&h.pool, &empty).await.unwrap_err().kind, ErrorKind::Validation);
+}
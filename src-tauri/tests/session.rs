@@ -0,0 +1,251 @@
+//! The login session kept on the Rust side instead of in the webview, and
+//! what happens when its token expires.
+
+mod support;
+
+use app_lib::api_key_ops;
+use app_lib::error::ErrorKind;
+use app_lib::memo_ops::{self, MemoQuery};
+use app_lib::session::{self, ExpiryReason, SessionState};
+use app_lib::user_ops::{self, LoginPayload};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use keyring::credential::{Credential, CredentialApi, CredentialBuilderApi};
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use support::mock_backend::MockBackend;
+use tauri::test::{mock_app, MockRuntime};
+use tauri::{App, Listener, Manager};
+use tempfile::TempDir;
+
+/// An unsigned token with the claims the backend sends.
+fn token(claims: serde_json::Value) -> String {
+    let part = |v: &serde_json::Value| URL_SAFE_NO_PAD.encode(v.to_string());
+    format!("{}.{}.signature", part(&serde_json::json!({"alg": "HS256"})), part(&claims))
+}
+
+#[test]
+fn requires_a_login_before_handing_out_a_token() {
+    let dir = TempDir::new().unwrap();
+    let session = SessionState::load(dir.path().to_path_buf()).unwrap();
+
+    assert_eq!(session.token().unwrap_err().kind, ErrorKind::Unauthorized);
+    assert!(session.current().is_none());
+}
+
+#[test]
+fn keeps_the_session_across_restarts_without_storing_the_token_in_clear() {
+    let dir = TempDir::new().unwrap();
+    let jwt = token(serde_json::json!({"sub": "user-1", "username": "ada", "exp": 4102444800u64}));
+    let info = SessionState::load(dir.path().to_path_buf())
+        .unwrap()
+        .start(jwt.clone(), "ada@example.com")
+        .unwrap();
+    assert_eq!(info.user_id.as_deref(), Some("user-1"));
+    assert_eq!(info.username.as_deref(), Some("ada"));
+    assert_eq!(info.email, "ada@example.com");
+
+    let on_disk = std::fs::read(dir.path().join("session.bin")).unwrap();
+    assert!(!String::from_utf8_lossy(&on_disk).contains(&jwt));
+
+    let restored = SessionState::load(dir.path().to_path_buf()).unwrap();
+    assert_eq!(restored.token().unwrap(), jwt);
+    assert_eq!(restored.current(), Some(info));
+}
+
+#[test]
+fn logging_out_forgets_the_session() {
+    let dir = TempDir::new().unwrap();
+    let session = SessionState::load(dir.path().to_path_buf()).unwrap();
+    session.start("opaque-token".into(), "ada@example.com").unwrap();
+    session.end().unwrap();
+    assert!(session.token().is_err());
+
+    let restored = SessionState::load(dir.path().to_path_buf()).unwrap();
+    assert!(restored.current().is_none());
+
+    // A damaged session file means logging in again, not failing to start.
+    std::fs::write(dir.path().join("session.bin"), b"garbage").unwrap();
+    assert!(SessionState::load(dir.path().to_path_buf()).unwrap().current().is_none());
+}
+
+/// Secrets of the in-memory credential store, by service and account.
+type Secrets = Arc<Mutex<HashMap<(String, String), Vec<u8>>>>;
+
+/// A credential store that, unlike `keyring::mock`, finds what an earlier
+/// entry for the same account saved.
+struct MemoryStore(Secrets);
+
+struct MemoryCredential {
+    secrets: Secrets,
+    id: (String, String),
+}
+
+impl CredentialApi for MemoryCredential {
+    fn set_secret(&self, secret: &[u8]) -> keyring::Result<()> {
+        self.secrets.lock().unwrap().insert(self.id.clone(), secret.to_vec());
+        Ok(())
+    }
+
+    fn get_secret(&self) -> keyring::Result<Vec<u8>> {
+        self.secrets.lock().unwrap().get(&self.id).cloned().ok_or(keyring::Error::NoEntry)
+    }
+
+    fn delete_credential(&self) -> keyring::Result<()> {
+        self.secrets.lock().unwrap().remove(&self.id).map(|_| ()).ok_or(keyring::Error::NoEntry)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl CredentialBuilderApi for MemoryStore {
+    fn build(&self, _: Option<&str>, service: &str, user: &str) -> keyring::Result<Box<Credential>> {
+        Ok(Box::new(MemoryCredential {
+            secrets: self.0.clone(),
+            id: (service.to_string(), user.to_string()),
+        }))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Keeps session keys in memory for the rest of the test binary.
+fn use_memory_keyring() -> Secrets {
+    static SECRETS: OnceLock<Secrets> = OnceLock::new();
+    SECRETS
+        .get_or_init(|| {
+            let secrets = Secrets::default();
+            keyring::set_default_credential_builder(Box::new(MemoryStore(secrets.clone())));
+            secrets
+        })
+        .clone()
+}
+
+#[test]
+fn the_session_survives_moving_the_profile() {
+    let secrets = use_memory_keyring();
+    let dir = TempDir::new().unwrap();
+    let (before, after) = (dir.path().join("data"), dir.path().join("accounts").join("user-1"));
+    SessionState::load(before.clone())
+        .unwrap()
+        .start("opaque-token".into(), "ada@example.com")
+        .unwrap();
+    // The key went to the credential store instead of the data directory.
+    assert!(!before.join("session.key").exists());
+    let keys = secrets.lock().unwrap().len();
+
+    std::fs::create_dir_all(after.parent().unwrap()).unwrap();
+    std::fs::rename(&before, &after).unwrap();
+    let restored = SessionState::load(after).unwrap();
+    assert_eq!(restored.token().unwrap(), "opaque-token");
+    assert_eq!(secrets.lock().unwrap().len(), keys);
+}
+
+struct Harness {
+    app: App<MockRuntime>,
+    _dir: TempDir,
+}
+
+impl Harness {
+    fn start() -> Self {
+        let dir = TempDir::new().unwrap();
+        let app = mock_app();
+        app.manage(SessionState::load(dir.path().to_path_buf()).unwrap());
+        Self { app, _dir: dir }
+    }
+
+    fn session(&self) -> tauri::State<'_, SessionState> {
+        self.app.state::<SessionState>()
+    }
+
+    fn events(&self, name: &str) -> Arc<Mutex<Vec<serde_json::Value>>> {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        self.app.handle().listen(name, move |event| {
+            sink.lock().unwrap().push(serde_json::from_str(event.payload()).unwrap());
+        });
+        seen
+    }
+}
+
+#[test]
+fn warns_before_the_token_expires_and_ends_the_session_after() {
+    let h = Harness::start();
+    // `exp` has whole seconds.
+    let now = DateTime::from_timestamp(Utc::now().timestamp(), 0).unwrap();
+    let exp = now + Duration::minutes(10);
+    let info = h
+        .session()
+        .start(token(serde_json::json!({"exp": exp.timestamp()})), "ada@example.com")
+        .unwrap();
+    assert!(info.expires_at.is_some());
+    let expiring = h.events("auth:expiring");
+    let expired = h.events("auth:expired");
+    let changed = h.events("session:changed");
+
+    session::check_expiry(h.app.handle(), now);
+    assert!(expiring.lock().unwrap().is_empty());
+
+    session::check_expiry(h.app.handle(), now + Duration::minutes(6));
+    session::check_expiry(h.app.handle(), now + Duration::minutes(7));
+    assert_eq!(expiring.lock().unwrap().len(), 1);
+    assert_eq!(expiring.lock().unwrap()[0]["seconds_left"], 240);
+    assert!(h.session().token().is_ok());
+
+    session::check_expiry(h.app.handle(), now + Duration::minutes(10));
+    assert_eq!(expired.lock().unwrap()[0]["reason"], "expired");
+    assert_eq!(*changed.lock().unwrap(), [serde_json::Value::Null]);
+    assert!(h.session().current().is_none());
+}
+
+#[test]
+fn an_expired_token_is_not_handed_out() {
+    let h = Harness::start();
+    let exp = Utc::now() - Duration::seconds(1);
+    h.session()
+        .start(token(serde_json::json!({"exp": exp.timestamp()})), "ada@example.com")
+        .unwrap();
+
+    let err = h.session().token().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Unauthorized);
+    assert!(err.message.contains("expired"));
+}
+
+#[tokio::test]
+async fn a_401_from_any_module_ends_the_session_it_belongs_to() {
+    let h = Harness::start();
+    let backend = MockBackend::start().await;
+    let mut api = backend.client();
+    let handle = h.app.handle().clone();
+    api.set_unauthorized_listener(move |token| session::expire_session(&handle, token, ExpiryReason::Rejected));
+    let expired = h.events("auth:expired");
+
+    // A wrong password is a 401 too, but no session token was involved.
+    let payload = LoginPayload {
+        email: "ada@example.com".into(),
+        password: "wrong".into(),
+    };
+    user_ops::login(&api, payload).await.unwrap_err();
+
+    // A request made with an older token does not end the new session.
+    let old = backend.register("ada@example.com", "hunter22");
+    let current = backend.register("ada@example.com", "hunter22");
+    h.session().start(current.clone(), "ada@example.com").unwrap();
+    backend.revoke(&old);
+    api_key_ops::get_api_key(&api, &old).await.unwrap_err();
+    assert!(expired.lock().unwrap().is_empty());
+    assert!(h.session().current().is_some());
+
+    backend.revoke(&current);
+    let err = memo_ops::get_memos(&api, &current, &MemoQuery::default()).await.unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Unauthorized);
+    assert_eq!(expired.lock().unwrap().len(), 1);
+    assert_eq!(expired.lock().unwrap()[0]["reason"], "rejected");
+    assert_eq!(h.session().token().unwrap_err().message, "Not logged in");
+}
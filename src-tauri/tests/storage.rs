@@ -0,0 +1,83 @@
+//! Storage usage reporting and the recording quota.
+
+mod support;
+
+use app_lib::audio_cache::AudioCache;
+use app_lib::error::ErrorKind;
+use app_lib::memo_store::{MemoStore, MemoUpdate};
+use app_lib::storage::{self, QuotaConfig, QuotaState};
+use std::sync::{Arc, Mutex};
+use support::Harness;
+use tauri::{Listener, Manager};
+
+impl Harness {
+    /// Saves a recording the way `save_audio_command` does.
+    async fn record(&self, audio: Vec<u8>) -> Result<String, ErrorKind> {
+        storage::check_quota(self.app.handle(), audio.len() as u64)
+            .await
+            .map_err(|e| e.kind)?;
+        Ok(self.store.create("unused", audio, "00:10".into(), vec![]).await.unwrap().id)
+    }
+}
+
+#[tokio::test]
+async fn reports_audio_text_and_cache_usage() {
+    let h = Harness::start().await;
+    let small = h.record(vec![1; 100]).await.unwrap();
+    let large = h.record(vec![2; 300]).await.unwrap();
+    // Same recording as `large`, stored once.
+    let copy = h.record(vec![2; 300]).await.unwrap();
+    let update = MemoUpdate {
+        title: "Notes".into(),
+        transcript: Some("héllo".into()),
+        translate: None,
+        summary: Some("hi".into()),
+        tags: None,
+    };
+    h.store.update("unused", &small, update).await.unwrap();
+    h.store.delete("unused", &copy).await.unwrap();
+    h.app.state::<AudioCache>().put("remote-memo", &[0; 50]).await.unwrap();
+
+    let stats = storage::storage_stats(h.app.handle()).await.unwrap();
+    assert_eq!(stats.memo_count, 3);
+    assert_eq!(stats.audio_bytes, 700);
+    assert_eq!(stats.stored_audio_bytes, 400);
+    assert_eq!(stats.trash_bytes, 300);
+    assert_eq!(stats.transcript_bytes, 8);
+    assert_eq!(stats.cache_bytes, 50);
+    assert_eq!(stats.pending_upload_bytes, 0);
+    assert!(stats.database_bytes > 0);
+
+    assert_eq!(stats.oldest[0].id, small);
+    assert_eq!(stats.largest[0].audio_bytes, 300);
+    assert_eq!(stats.largest.last().unwrap().id, small);
+    assert!(stats.memos.iter().any(|m| m.id == large && !m.in_trash));
+    assert_eq!((stats.quota.used_bytes, stats.quota.state), (400, QuotaState::Ok));
+}
+
+#[tokio::test]
+async fn warns_above_the_soft_limit_and_refuses_at_the_hard_limit() {
+    let h = Harness::with_quota(QuotaConfig {
+        soft_limit_mb: Some(1),
+        hard_limit_mb: Some(2),
+    })
+    .await;
+    let warnings = Arc::new(Mutex::new(Vec::new()));
+    let sink = warnings.clone();
+    h.app.handle().listen("storage:quota", move |event| {
+        let payload: serde_json::Value = serde_json::from_str(event.payload()).unwrap();
+        sink.lock().unwrap().push(payload["state"].as_str().unwrap().to_string());
+    });
+
+    const KB: usize = 1024;
+    h.record(vec![1; 700 * KB]).await.unwrap();
+    assert!(warnings.lock().unwrap().is_empty());
+    h.record(vec![2; 700 * KB]).await.unwrap();
+    assert_eq!(*warnings.lock().unwrap(), ["warning"]);
+
+    assert_eq!(h.record(vec![3; 700 * KB]).await.unwrap_err(), ErrorKind::QuotaExceeded);
+    assert_eq!(*warnings.lock().unwrap(), ["warning", "full"]);
+    let stats = storage::storage_stats(h.app.handle()).await.unwrap();
+    assert_eq!(stats.memo_count, 2);
+    assert_eq!(stats.quota.state, QuotaState::Warning);
+}
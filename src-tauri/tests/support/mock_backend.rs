@@ -0,0 +1,907 @@
+//! In-process stand-in for the SmartMemo backend.
+//!
+//! Implements the same HTTP contract the app talks to (auth, memos with
+//! multipart uploads, idempotency keys, paging, chunked uploads, AI text
+//! endpoints, API keys and helper status) on a random local port, plus hooks
+//! for injecting failures.
+
+use axum::body::Bytes;
+use axum::extract::{Multipart, Path, Query, Request, State};
+use axum::http::header::RETRY_AFTER;
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, patch, post, put};
+use axum::{Json, Router};
+use base64::Engine;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+
+use app_lib::api_client::ApiClient;
+use app_lib::config::ApiConfig;
+use app_lib::memo_ops::{self, MemoCursor, SortValue};
+use app_lib::retry::RetryPolicy;
+
+const DEFAULT_PAGE_SIZE: usize = 50;
+
+#[derive(Clone)]
+struct StoredMemo {
+    id: String,
+    user_id: String,
+    title: String,
+    transcript: Option<String>,
+    translate: Option<String>,
+    summary: Option<String>,
+    tags: Vec<String>,
+    duration: String,
+    created_at: String,
+    updated_at: String,
+    audio: Vec<u8>,
+}
+
+impl StoredMemo {
+    fn to_json(&self, include_audio: bool) -> Value {
+        json!({
+            "id": self.id,
+            "title": self.title,
+            "transcript": self.transcript,
+            "translate": self.translate,
+            "summary": self.summary,
+            "tags": self.tags,
+            "duration": self.duration,
+            "created_at": self.created_at,
+            "updated_at": self.updated_at,
+            "audio_blob": if include_audio {
+                Some(base64::engine::general_purpose::STANDARD.encode(&self.audio))
+            } else {
+                None
+            },
+        })
+    }
+
+    fn to_summary_json(&self) -> Value {
+        let present = |t: &Option<String>| t.as_deref().is_some_and(|t| !t.is_empty());
+        json!({
+            "id": self.id,
+            "title": self.title,
+            "tags": self.tags,
+            "duration": self.duration,
+            "created_at": self.created_at,
+            "has_transcript": present(&self.transcript),
+            "has_summary": present(&self.summary),
+            "has_translation": present(&self.translate),
+        })
+    }
+}
+
+struct User {
+    user_id: String,
+    password: String,
+}
+
+#[derive(Default)]
+struct ApiKeys {
+    gemini: Option<String>,
+    elevenlabs: Option<String>,
+}
+
+struct UploadSession {
+    user_id: String,
+    total_size: usize,
+    sha256: String,
+    metadata: Value,
+    parts: BTreeMap<u32, Vec<u8>>,
+    memo_id: Option<String>,
+}
+
+#[derive(Default)]
+struct Backend {
+    users: HashMap<String, User>,
+    tokens: HashMap<String, String>,
+    memos: Vec<StoredMemo>,
+    idempotency: HashMap<(String, String), String>,
+    upload_keys: HashMap<(String, String), String>,
+    uploads: HashMap<String, UploadSession>,
+    api_keys: HashMap<String, ApiKeys>,
+    helper: HashMap<String, bool>,
+    /// Statuses returned instead of handling the next requests.
+    fail_next: VecDeque<u16>,
+    /// `(pattern, status, remaining)`: fails requests whose line contains `pattern`.
+    fail_matching: Vec<(String, u16, usize)>,
+    /// Statuses returned after handling the next requests, simulating a
+    /// response lost on the way back.
+    lose_next: VecDeque<u16>,
+    /// `Retry-After` header sent with injected failures.
+    retry_after: Option<String>,
+    /// Part indices every upload claims to have on top of the real ones.
+    phantom_parts: Vec<u32>,
+    requests: Vec<String>,
+    next_id: u64,
+    clock: i64,
+}
+
+impl Backend {
+    fn received_parts(&self, session: &UploadSession) -> Vec<u32> {
+        session.parts.keys().chain(&self.phantom_parts).copied().collect()
+    }
+
+    fn next_id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{}-{}", prefix, self.next_id)
+    }
+
+    /// Deterministic, strictly increasing creation timestamps.
+    fn now(&mut self) -> String {
+        self.clock += 1;
+        let base = chrono::DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z").unwrap();
+        (base + chrono::Duration::minutes(self.clock)).to_rfc3339()
+    }
+
+    fn create_memo(&mut self, user_id: &str, metadata: &Value, audio: Vec<u8>) -> String {
+        let id = self.next_id("memo");
+        let created_at = self.now();
+        self.memos.push(StoredMemo {
+            id: id.clone(),
+            user_id: user_id.to_string(),
+            title: metadata["title"].as_str().unwrap_or("Untitled").to_string(),
+            transcript: None,
+            translate: None,
+            summary: None,
+            tags: metadata["tags"]
+                .as_array()
+                .map(|tags| tags.iter().filter_map(|t| t.as_str().map(String::from)).collect())
+                .unwrap_or_default(),
+            duration: metadata["duration"].as_str().unwrap_or("00:00").to_string(),
+            updated_at: created_at.clone(),
+            created_at,
+            audio,
+        });
+        id
+    }
+}
+
+type Shared = Arc<Mutex<Backend>>;
+
+fn error(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+struct Unauthorized(&'static str);
+
+impl IntoResponse for Unauthorized {
+    fn into_response(self) -> Response {
+        error(StatusCode::UNAUTHORIZED, self.0)
+    }
+}
+
+fn authenticate(state: &Shared, headers: &HeaderMap) -> Result<String, Unauthorized> {
+    let token = headers
+        .get("Authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or(Unauthorized("Missing token"))?;
+    state
+        .lock()
+        .unwrap()
+        .tokens
+        .get(token)
+        .cloned()
+        .ok_or(Unauthorized("Invalid or expired token"))
+}
+
+fn idempotency_key(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("Idempotency-Key")
+        .and_then(|v| v.to_str().ok())
+        .map(String::from)
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+async fn faults(State(state): State<Shared>, req: Request, next: Next) -> Response {
+    let injected = {
+        let mut backend = state.lock().unwrap();
+        let line = format!("{} {}", req.method(), req.uri().path());
+        let matching = backend
+            .fail_matching
+            .iter_mut()
+            .find(|(pattern, _, remaining)| *remaining > 0 && line.contains(pattern.as_str()))
+            .map(|(_, status, remaining)| {
+                *remaining -= 1;
+                *status
+            });
+        backend.requests.push(line);
+        matching.or_else(|| backend.fail_next.pop_front())
+    };
+    if let Some(status) = injected {
+        let mut response = error(StatusCode::from_u16(status).unwrap(), "injected failure");
+        if let Some(value) = state.lock().unwrap().retry_after.clone() {
+            response.headers_mut().insert(RETRY_AFTER, value.parse().unwrap());
+        }
+        return response;
+    }
+
+    let response = next.run(req).await;
+    let lost = state.lock().unwrap().lose_next.pop_front();
+    match lost {
+        Some(status) => error(StatusCode::from_u16(status).unwrap(), "injected lost response"),
+        None => response,
+    }
+}
+
+// --- Auth ---
+
+#[derive(Deserialize)]
+struct SignupBody {
+    username: String,
+    email: String,
+    password: String,
+}
+
+async fn signup(State(state): State<Shared>, Json(body): Json<SignupBody>) -> Response {
+    let mut backend = state.lock().unwrap();
+    if body.username.is_empty() || !body.email.contains('@') {
+        return error(StatusCode::BAD_REQUEST, "Invalid signup details");
+    }
+    if backend.users.contains_key(&body.email) {
+        return error(StatusCode::CONFLICT, "Email already registered");
+    }
+    let user_id = backend.next_id("user");
+    backend.users.insert(
+        body.email,
+        User {
+            user_id: user_id.clone(),
+            password: body.password,
+        },
+    );
+    Json(json!({ "message": "User created", "user_id": user_id })).into_response()
+}
+
+#[derive(Deserialize)]
+struct LoginBody {
+    email: String,
+    password: String,
+}
+
+async fn login(State(state): State<Shared>, Json(body): Json<LoginBody>) -> Response {
+    let mut backend = state.lock().unwrap();
+    let user_id = match backend.users.get(&body.email) {
+        Some(user) if user.password == body.password => user.user_id.clone(),
+        _ => return error(StatusCode::UNAUTHORIZED, "Invalid email or password"),
+    };
+    let token = backend.next_id("token");
+    backend.tokens.insert(token.clone(), user_id);
+    Json(json!({ "message": "Login successful", "token": token })).into_response()
+}
+
+// --- Memos ---
+
+async fn save_memo(State(state): State<Shared>, headers: HeaderMap, mut multipart: Multipart) -> Response {
+    let user_id = match authenticate(&state, &headers) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.into_response(),
+    };
+
+    let mut metadata = None;
+    let mut audio = None;
+    while let Ok(Some(field)) = multipart.next_field().await {
+        let name = field.name().unwrap_or_default().to_string();
+        let data = match field.bytes().await {
+            Ok(data) => data,
+            Err(_) => return error(StatusCode::BAD_REQUEST, "Malformed multipart body"),
+        };
+        match name.as_str() {
+            "metadata" => metadata = serde_json::from_slice::<Value>(&data).ok(),
+            "audio" => audio = Some(data.to_vec()),
+            _ => {}
+        }
+    }
+    let (Some(metadata), Some(audio)) = (metadata, audio) else {
+        return error(StatusCode::BAD_REQUEST, "Expected metadata and audio parts");
+    };
+    if audio.is_empty() {
+        return error(StatusCode::UNPROCESSABLE_ENTITY, "Audio is empty");
+    }
+
+    let mut backend = state.lock().unwrap();
+    if let Some(key) = idempotency_key(&headers) {
+        if let Some(memo_id) = backend.idempotency.get(&(user_id.clone(), key.clone())) {
+            return Json(json!({ "message": "Memo already saved", "memo_id": memo_id })).into_response();
+        }
+        let memo_id = backend.create_memo(&user_id, &metadata, audio);
+        backend.idempotency.insert((user_id, key), memo_id.clone());
+        return Json(json!({ "message": "Memo saved", "memo_id": memo_id })).into_response();
+    }
+    let memo_id = backend.create_memo(&user_id, &metadata, audio);
+    Json(json!({ "message": "Memo saved", "memo_id": memo_id })).into_response()
+}
+
+#[derive(Deserialize)]
+struct UpdateBody {
+    title: String,
+    transcript: Option<String>,
+    translate: Option<String>,
+    summary: Option<String>,
+    tags: Option<Vec<String>>,
+}
+
+async fn update_memo(
+    State(state): State<Shared>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(body): Json<UpdateBody>,
+) -> Response {
+    let user_id = match authenticate(&state, &headers) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.into_response(),
+    };
+    let mut backend = state.lock().unwrap();
+    let now = backend.now();
+    let Some(memo) = backend
+        .memos
+        .iter_mut()
+        .find(|m| m.id == id && m.user_id == user_id)
+    else {
+        return error(StatusCode::NOT_FOUND, "Memo not found");
+    };
+    memo.updated_at = now;
+    memo.title = body.title;
+    memo.transcript = body.transcript;
+    memo.translate = body.translate;
+    memo.summary = body.summary;
+    memo.tags = body.tags.unwrap_or_default();
+    Json(json!({ "message": "Memo updated", "memo_id": id })).into_response()
+}
+
+async fn get_memos(
+    State(state): State<Shared>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let user_id = match authenticate(&state, &headers) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.into_response(),
+    };
+    let backend = state.lock().unwrap();
+
+    let tags: Vec<&str> = params
+        .get("tags")
+        .map(|t| t.split(',').filter(|t| !t.is_empty()).collect())
+        .unwrap_or_default();
+    let parse = |v: &String| chrono::DateTime::parse_from_rfc3339(v).ok();
+    let from = params.get("from").and_then(parse);
+    let to = params.get("to").and_then(parse);
+    let has_transcript = params.get("has_transcript").map(|v| v == "true");
+
+    let mut memos: Vec<&StoredMemo> = backend
+        .memos
+        .iter()
+        .filter(|m| m.user_id == user_id)
+        .filter(|m| tags.iter().all(|t| m.tags.iter().any(|mt| mt == t)))
+        .filter(|m| {
+            let created = chrono::DateTime::parse_from_rfc3339(&m.created_at).unwrap();
+            from.map_or(true, |f| created >= f) && to.map_or(true, |t| created <= t)
+        })
+        .filter(|m| {
+            has_transcript.map_or(true, |want| {
+                m.transcript.as_deref().is_some_and(|t| !t.is_empty()) == want
+            })
+        })
+        .collect();
+
+    let sort_value = |m: &StoredMemo| -> SortValue {
+        match params.get("sort").map(String::as_str).unwrap_or("created_at") {
+            "title" => SortValue::Text(m.title.clone()),
+            "duration" => SortValue::Number(memo_ops::duration_secs(&m.duration).unwrap_or(0) as i64),
+            _ => SortValue::Text(m.created_at.clone()),
+        }
+    };
+    let key = |m: &StoredMemo| (sort_value(m), m.id.clone());
+    let desc = params.get("order").map(String::as_str).unwrap_or("desc") == "desc";
+    memos.sort_by_key(|m| key(m));
+    if desc {
+        memos.reverse();
+    }
+
+    if let Some(cursor) = params.get("cursor") {
+        let Ok(cursor) = MemoCursor::decode(cursor) else {
+            return error(StatusCode::BAD_REQUEST, "Invalid cursor");
+        };
+        let after = (cursor.value, cursor.id);
+        memos.retain(|m| if desc { key(m) < after } else { key(m) > after });
+    }
+    let limit: usize = params
+        .get("limit")
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(DEFAULT_PAGE_SIZE);
+    let page: Vec<&StoredMemo> = memos.iter().take(limit).copied().collect();
+    let next_cursor = (page.len() < memos.len())
+        .then(|| page.last())
+        .flatten()
+        .map(|last| {
+            let (value, id) = key(last);
+            MemoCursor { value, id }.encode()
+        });
+
+    let summary = params.get("view").map(String::as_str) == Some("summary");
+    let include_audio = params.get("include_audio").map(String::as_str) != Some("false");
+    let items: Vec<Value> = page
+        .iter()
+        .map(|m| if summary { m.to_summary_json() } else { m.to_json(include_audio) })
+        .collect();
+    Json(json!({ "items": items, "next_cursor": next_cursor })).into_response()
+}
+
+async fn get_memo(State(state): State<Shared>, headers: HeaderMap, Path(id): Path<String>) -> Response {
+    let user_id = match authenticate(&state, &headers) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.into_response(),
+    };
+    let backend = state.lock().unwrap();
+    match backend.memos.iter().find(|m| m.id == id && m.user_id == user_id) {
+        Some(memo) => Json(memo.to_json(true)).into_response(),
+        None => error(StatusCode::NOT_FOUND, "Memo not found"),
+    }
+}
+
+async fn get_memo_audio(State(state): State<Shared>, headers: HeaderMap, Path(id): Path<String>) -> Response {
+    let user_id = match authenticate(&state, &headers) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.into_response(),
+    };
+    let backend = state.lock().unwrap();
+    match backend.memos.iter().find(|m| m.id == id && m.user_id == user_id) {
+        Some(memo) => (
+            [("Content-Type", "application/octet-stream")],
+            memo.audio.clone(),
+        )
+            .into_response(),
+        None => error(StatusCode::NOT_FOUND, "Memo not found"),
+    }
+}
+
+async fn delete_memo(State(state): State<Shared>, headers: HeaderMap, Path(id): Path<String>) -> Response {
+    let user_id = match authenticate(&state, &headers) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.into_response(),
+    };
+    let mut backend = state.lock().unwrap();
+    let before = backend.memos.len();
+    backend.memos.retain(|m| !(m.id == id && m.user_id == user_id));
+    if backend.memos.len() == before {
+        return error(StatusCode::NOT_FOUND, "Memo not found");
+    }
+    Json(json!({ "message": "Memo deleted", "memo_id": id })).into_response()
+}
+
+async fn delete_all_memos(State(state): State<Shared>, headers: HeaderMap) -> Response {
+    let user_id = match authenticate(&state, &headers) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.into_response(),
+    };
+    let mut backend = state.lock().unwrap();
+    let before = backend.memos.len();
+    backend.memos.retain(|m| m.user_id != user_id);
+    let deleted = before - backend.memos.len();
+    Json(json!({ "message": format!("Deleted {} memos", deleted), "memo_id": "" })).into_response()
+}
+
+// --- Chunked uploads ---
+
+#[derive(Deserialize)]
+struct InitBody {
+    total_size: usize,
+    chunk_size: usize,
+    sha256: String,
+    metadata: Value,
+}
+
+async fn upload_init(State(state): State<Shared>, headers: HeaderMap, Json(body): Json<InitBody>) -> Response {
+    let user_id = match authenticate(&state, &headers) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.into_response(),
+    };
+    let mut backend = state.lock().unwrap();
+    let key = idempotency_key(&headers);
+    if let Some(upload_id) = key
+        .as_ref()
+        .and_then(|k| backend.upload_keys.get(&(user_id.clone(), k.clone())))
+        .cloned()
+    {
+        if let Some(session) = backend.uploads.get(&upload_id) {
+            let parts = backend.received_parts(session);
+            return Json(json!({ "upload_id": upload_id, "chunk_size": body.chunk_size, "received_parts": parts }))
+                .into_response();
+        }
+    }
+
+    let upload_id = backend.next_id("upload");
+    backend.uploads.insert(
+        upload_id.clone(),
+        UploadSession {
+            user_id: user_id.clone(),
+            total_size: body.total_size,
+            sha256: body.sha256,
+            metadata: body.metadata,
+            parts: BTreeMap::new(),
+            memo_id: None,
+        },
+    );
+    if let Some(key) = key {
+        backend.upload_keys.insert((user_id, key), upload_id.clone());
+    }
+    Json(json!({ "upload_id": upload_id, "chunk_size": body.chunk_size, "received_parts": [] })).into_response()
+}
+
+async fn upload_status(State(state): State<Shared>, headers: HeaderMap, Path(id): Path<String>) -> Response {
+    let user_id = match authenticate(&state, &headers) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.into_response(),
+    };
+    let backend = state.lock().unwrap();
+    match backend.uploads.get(&id).filter(|s| s.user_id == user_id) {
+        Some(session) => {
+            let parts = backend.received_parts(session);
+            Json(json!({ "upload_id": id, "received_parts": parts })).into_response()
+        }
+        None => error(StatusCode::NOT_FOUND, "Upload not found"),
+    }
+}
+
+async fn upload_part(
+    State(state): State<Shared>,
+    headers: HeaderMap,
+    Path((id, part)): Path<(String, u32)>,
+    body: Bytes,
+) -> Response {
+    let user_id = match authenticate(&state, &headers) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.into_response(),
+    };
+    let checksum = headers.get("X-Chunk-Sha256").and_then(|v| v.to_str().ok());
+    if checksum != Some(sha256_hex(&body).as_str()) {
+        return error(StatusCode::BAD_REQUEST, "Chunk checksum mismatch");
+    }
+    let mut backend = state.lock().unwrap();
+    match backend.uploads.get_mut(&id).filter(|s| s.user_id == user_id) {
+        Some(session) => {
+            session.parts.insert(part, body.to_vec());
+            Json(json!({ "part": part, "sha256": sha256_hex(&body) })).into_response()
+        }
+        None => error(StatusCode::NOT_FOUND, "Upload not found"),
+    }
+}
+
+async fn upload_complete(State(state): State<Shared>, headers: HeaderMap, Path(id): Path<String>) -> Response {
+    let user_id = match authenticate(&state, &headers) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.into_response(),
+    };
+    let mut backend = state.lock().unwrap();
+    let Some(session) = backend.uploads.get(&id).filter(|s| s.user_id == user_id) else {
+        return error(StatusCode::NOT_FOUND, "Upload not found");
+    };
+    if let Some(memo_id) = &session.memo_id {
+        return Json(json!({ "message": "Upload already completed", "memo_id": memo_id })).into_response();
+    }
+    let audio: Vec<u8> = session.parts.values().flatten().copied().collect();
+    if audio.len() != session.total_size || sha256_hex(&audio) != session.sha256 {
+        return error(StatusCode::UNPROCESSABLE_ENTITY, "Assembled upload does not match its checksum");
+    }
+    let metadata = session.metadata.clone();
+    let memo_id = backend.create_memo(&user_id, &metadata, audio);
+    if let Some(session) = backend.uploads.get_mut(&id) {
+        session.memo_id = Some(memo_id.clone());
+        session.parts.clear();
+    }
+    Json(json!({ "message": "Upload completed", "memo_id": memo_id })).into_response()
+}
+
+// --- AI text endpoints ---
+
+async fn transcribe(State(state): State<Shared>, headers: HeaderMap, body: Bytes) -> Response {
+    if let Err(e) = authenticate(&state, &headers) {
+        return e.into_response();
+    }
+    if headers.get("Content-Type").and_then(|v| v.to_str().ok()) != Some("application/octet-stream") {
+        return error(StatusCode::UNSUPPORTED_MEDIA_TYPE, "Expected raw audio bytes");
+    }
+    if body.is_empty() {
+        return error(StatusCode::BAD_REQUEST, "No audio data provided");
+    }
+    format!("Transcript of {} bytes", body.len()).into_response()
+}
+
+#[derive(Deserialize)]
+struct TranslateBody {
+    text: String,
+    lang: String,
+}
+
+async fn translate(State(state): State<Shared>, headers: HeaderMap, Json(body): Json<TranslateBody>) -> Response {
+    if let Err(e) = authenticate(&state, &headers) {
+        return e.into_response();
+    }
+    format!("[{}] {}", body.lang, body.text).into_response()
+}
+
+#[derive(Deserialize)]
+struct TextBody {
+    text: String,
+}
+
+async fn summary(State(state): State<Shared>, headers: HeaderMap, Json(body): Json<TextBody>) -> Response {
+    if let Err(e) = authenticate(&state, &headers) {
+        return e.into_response();
+    }
+    format!("Summary: {}", body.text.split_whitespace().take(5).collect::<Vec<_>>().join(" ")).into_response()
+}
+
+#[derive(Deserialize)]
+struct TranscriptBody {
+    transcript: String,
+}
+
+async fn generate_memo_name(
+    State(state): State<Shared>,
+    headers: HeaderMap,
+    Json(body): Json<TranscriptBody>,
+) -> Response {
+    if let Err(e) = authenticate(&state, &headers) {
+        return e.into_response();
+    }
+    body.transcript
+        .split_whitespace()
+        .take(3)
+        .collect::<Vec<_>>()
+        .join(" ")
+        .into_response()
+}
+
+// --- API keys and helper state ---
+
+#[derive(Deserialize)]
+struct SaveKeyBody {
+    gemini_api_key: String,
+}
+
+async fn save_api_key(State(state): State<Shared>, headers: HeaderMap, Json(body): Json<SaveKeyBody>) -> Response {
+    let user_id = match authenticate(&state, &headers) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.into_response(),
+    };
+    let mut backend = state.lock().unwrap();
+    backend.api_keys.entry(user_id).or_default().gemini = Some(body.gemini_api_key);
+    Json(json!({ "message": "API key saved" })).into_response()
+}
+
+async fn get_api_key(State(state): State<Shared>, headers: HeaderMap) -> Response {
+    let user_id = match authenticate(&state, &headers) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.into_response(),
+    };
+    let backend = state.lock().unwrap();
+    match backend.api_keys.get(&user_id) {
+        Some(keys) => Json(json!({
+            "gemini_api_key": keys.gemini,
+            "elevenlabs_api_key": keys.elevenlabs,
+            "message": "API keys retrieved",
+        }))
+        .into_response(),
+        None => error(StatusCode::NOT_FOUND, "No API keys stored"),
+    }
+}
+
+async fn delete_api_key(State(state): State<Shared>, headers: HeaderMap, Path(provider): Path<String>) -> Response {
+    let user_id = match authenticate(&state, &headers) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.into_response(),
+    };
+    let mut backend = state.lock().unwrap();
+    let keys = backend.api_keys.entry(user_id).or_default();
+    match provider.as_str() {
+        "gemini" => keys.gemini = None,
+        "elevenlabs" => keys.elevenlabs = None,
+        _ => return error(StatusCode::NOT_FOUND, "Unknown provider"),
+    }
+    Json(json!({ "message": "API key deleted" })).into_response()
+}
+
+#[derive(Deserialize)]
+struct HelperBody {
+    status: bool,
+}
+
+async fn set_helper_status(State(state): State<Shared>, headers: HeaderMap, Json(body): Json<HelperBody>) -> Response {
+    let user_id = match authenticate(&state, &headers) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.into_response(),
+    };
+    state.lock().unwrap().helper.insert(user_id, body.status);
+    Json(json!({ "message": "Helper status updated" })).into_response()
+}
+
+async fn get_helper_status(State(state): State<Shared>, headers: HeaderMap) -> Response {
+    let user_id = match authenticate(&state, &headers) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.into_response(),
+    };
+    let status = state.lock().unwrap().helper.get(&user_id).copied().unwrap_or(false);
+    Json(json!({ "status": status, "message": "Helper status retrieved" })).into_response()
+}
+
+/// A running mock backend. The server stops when the test's runtime ends.
+pub struct MockBackend {
+    pub base_url: String,
+    state: Shared,
+    offline: Arc<watch::Sender<bool>>,
+}
+
+/// Forwards connections from `front` to the server at `backend`, dropping
+/// them all while `offline` is set so clients see a dead network.
+async fn gateway(front: TcpListener, backend: std::net::SocketAddr, offline: Arc<watch::Sender<bool>>) {
+    loop {
+        let Ok((mut client, _)) = front.accept().await else {
+            return;
+        };
+        if *offline.borrow() {
+            continue;
+        }
+        let mut cut = offline.subscribe();
+        tokio::spawn(async move {
+            let Ok(mut server) = TcpStream::connect(backend).await else {
+                return;
+            };
+            tokio::select! {
+                _ = tokio::io::copy_bidirectional(&mut client, &mut server) => {}
+                _ = cut.wait_for(|offline| *offline) => {}
+            }
+        });
+    }
+}
+
+impl MockBackend {
+    pub async fn start() -> Self {
+        let state: Shared = Arc::new(Mutex::new(Backend::default()));
+        let router = Router::new()
+            .route("/api/signup", post(signup))
+            .route("/api/login", post(login))
+            .route("/api/save_memo", post(save_memo))
+            .route("/api/update_memo/:id", patch(update_memo))
+            .route("/api/get_memos", get(get_memos))
+            .route("/api/get_memo/:id", get(get_memo))
+            .route("/api/get_memo/:id/audio", get(get_memo_audio))
+            .route("/api/delete_memo/:id", delete(delete_memo))
+            .route("/api/delete_all_memos", delete(delete_all_memos))
+            .route("/api/uploads/init", post(upload_init))
+            .route("/api/uploads/:id", get(upload_status))
+            .route("/api/uploads/:id/parts/:part", put(upload_part))
+            .route("/api/uploads/:id/complete", post(upload_complete))
+            .route("/api/transcribe", post(transcribe))
+            .route("/api/translate", post(translate))
+            .route("/api/summary", post(summary))
+            .route("/api/generate_memo_name", post(generate_memo_name))
+            .route("/api/api_keys/save", post(save_api_key))
+            .route("/api/api_keys/get", get(get_api_key))
+            .route("/api/api_keys/:provider", delete(delete_api_key))
+            .route("/api/helper/status", post(set_helper_status).get(get_helper_status))
+            .layer(middleware::from_fn_with_state(state.clone(), faults))
+            .with_state(state.clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let backend = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+
+        let front = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = front.local_addr().unwrap();
+        let offline = Arc::new(watch::channel(false).0);
+        tokio::spawn(gateway(front, backend, offline.clone()));
+
+        Self {
+            base_url: format!("http://{}/api", addr),
+            state,
+            offline,
+        }
+    }
+
+    /// Drops open connections and refuses new ones until set back to `false`.
+    pub fn set_offline(&self, offline: bool) {
+        self.offline.send_replace(offline);
+    }
+
+    /// Client settings pointing at this server, with fast retries.
+    pub fn api_config(&self) -> ApiConfig {
+        ApiConfig {
+            base_url: self.base_url.clone(),
+            timeout_secs: 5,
+            retry: RetryPolicy {
+                max_attempts: 3,
+                base_delay_ms: 1,
+                max_delay_ms: 5,
+            },
+            waking_notice_ms: 1_000,
+            ..ApiConfig::default()
+        }
+    }
+
+    pub fn client(&self) -> ApiClient {
+        ApiClient::new(&self.api_config()).unwrap()
+    }
+
+    /// Creates a user directly and returns a valid token for it.
+    pub fn register(&self, email: &str, password: &str) -> String {
+        let mut backend = self.state.lock().unwrap();
+        let user_id = backend.next_id("user");
+        backend.users.insert(
+            email.to_string(),
+            User {
+                user_id: user_id.clone(),
+                password: password.to_string(),
+            },
+        );
+        let token = backend.next_id("token");
+        backend.tokens.insert(token.clone(), user_id);
+        token
+    }
+
+    /// Makes the backend reject `token` with 401, as it does once a token expires.
+    pub fn revoke(&self, token: &str) {
+        self.state.lock().unwrap().tokens.remove(token);
+    }
+
+    /// Answers the next requests with these statuses without handling them.
+    pub fn fail_next(&self, statuses: &[u16]) {
+        self.state.lock().unwrap().fail_next.extend(statuses);
+    }
+
+    /// Makes uploads report these part indices as received as well, whether
+    /// or not they exist.
+    pub fn report_parts(&self, parts: &[u32]) {
+        self.state.lock().unwrap().phantom_parts.extend(parts);
+    }
+
+    /// Sends `Retry-After: <value>` with the failures injected from now on.
+    pub fn set_retry_after(&self, value: &str) {
+        self.state.lock().unwrap().retry_after = Some(value.to_string());
+    }
+
+    /// Answers the next `times` requests whose `"METHOD /path"` contains
+    /// `pattern` with `status`.
+    pub fn fail_matching(&self, pattern: &str, status: u16, times: usize) {
+        self.state
+            .lock()
+            .unwrap()
+            .fail_matching
+            .push((pattern.to_string(), status, times));
+    }
+
+    /// Handles the next requests but replaces their responses with these
+    /// statuses, as if the response had been lost.
+    pub fn lose_next(&self, statuses: &[u16]) {
+        self.state.lock().unwrap().lose_next.extend(statuses);
+    }
+
+    /// Number of requests received whose `"METHOD /path"` contains `pattern`.
+    pub fn request_count(&self, pattern: &str) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .requests
+            .iter()
+            .filter(|r| r.contains(pattern))
+            .count()
+    }
+
+    pub fn memo_count(&self) -> usize {
+        self.state.lock().unwrap().memos.len()
+    }
+}
@@ -0,0 +1,87 @@
+// Each test binary uses a different subset of the helpers.
+#![allow(dead_code)]
+
+pub mod mock_backend;
+
+use app_lib::audio_cache::AudioCache;
+use app_lib::audio_store::AudioStore;
+use app_lib::db;
+use app_lib::local_store::LocalMemoStore;
+use app_lib::storage::QuotaConfig;
+use app_lib::upload::{UploadConfig, UploadManager};
+use app_lib::vault::Vault;
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+use tauri::test::{mock_app, MockRuntime};
+use tauri::{App, Manager};
+use tempfile::TempDir;
+
+/// A local library in a temporary data directory, with the state `run`
+/// manages for it. Test files add their own helpers in `impl Harness`.
+pub struct Harness {
+    pub app: App<MockRuntime>,
+    pub pool: SqlitePool,
+    pub vault: Vault,
+    pub store: LocalMemoStore<MockRuntime>,
+    pub dir: TempDir,
+}
+
+impl Harness {
+    pub async fn start() -> Self {
+        Self::open(TempDir::new().unwrap()).await
+    }
+
+    pub async fn with_quota(quota: QuotaConfig) -> Self {
+        Self::build(TempDir::new().unwrap(), quota).await
+    }
+
+    /// Starts the app the way `run` does, on an existing data directory.
+    pub async fn open(dir: TempDir) -> Self {
+        Self::build(dir, QuotaConfig::default()).await
+    }
+
+    async fn build(dir: TempDir, quota: QuotaConfig) -> Self {
+        let app = mock_app();
+        let vault = Vault::load(dir.path().to_path_buf()).unwrap();
+        app.manage(vault.clone());
+        app.manage(AudioCache::new(dir.path().join("cache")).unwrap().with_vault(vault.clone()));
+        app.manage(UploadManager::new(dir.path().to_path_buf(), UploadConfig::default()).unwrap());
+        app.manage(quota);
+        let pool = db::open_with_vault(&vault).await.unwrap();
+        app.manage(pool.clone());
+        app.manage(AudioStore::new(dir.path().to_path_buf()).unwrap().with_vault(vault.clone()));
+        let store = LocalMemoStore::new(app.handle().clone(), pool.clone());
+        Self {
+            app,
+            pool,
+            vault,
+            store,
+            dir,
+        }
+    }
+
+    /// Every file in the audio store.
+    pub fn audio_files(&self) -> Vec<PathBuf> {
+        fn walk(dir: &Path, found: &mut Vec<PathBuf>) {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    walk(&path, found);
+                } else {
+                    found.push(path);
+                }
+            }
+        }
+        let mut found = Vec::new();
+        walk(&self.dir.path().join("audio"), &mut found);
+        found
+    }
+}
+
+/// A ten second WebM recording at about 32 kbit/s; different seeds give
+/// different recordings.
+pub fn recording(seed: u8) -> Vec<u8> {
+    let mut audio = vec![0x1A, 0x45, 0xDF, 0xA3];
+    audio.resize(40_000, seed);
+    audio
+}
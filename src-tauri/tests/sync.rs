@@ -0,0 +1,250 @@
+//! Syncs a local database with the mock backend while "another device"
+//! edits the backend directly through `memo_ops`.
+
+mod support;
+
+use app_lib::api_client::ApiClient;
+use app_lib::audio_cache::AudioCache;
+use app_lib::audio_store::AudioStore;
+use app_lib::db;
+use app_lib::idempotency::IdempotencyStore;
+use app_lib::local_store::LocalMemoStore;
+use app_lib::memo_ops::{self, MemoQuery, VoiceMemo};
+use app_lib::memo_store::{MemoStore, MemoUpdate};
+use app_lib::sync::{ConflictPolicy, Resolution, SyncConfig, SyncEngine};
+use app_lib::trash;
+use app_lib::upload::{UploadConfig, UploadManager};
+use sqlx::SqlitePool;
+use std::sync::{Arc, Mutex};
+use support::mock_backend::MockBackend;
+use tauri::test::{mock_app, MockRuntime};
+use tauri::{App, Listener, Manager};
+use tempfile::TempDir;
+
+struct Harness {
+    backend: MockBackend,
+    app: App<MockRuntime>,
+    pool: SqlitePool,
+    store: LocalMemoStore<MockRuntime>,
+    engine: SyncEngine,
+    token: String,
+    _dir: TempDir,
+}
+
+impl Harness {
+    async fn start(policy: ConflictPolicy) -> Self {
+        let backend = MockBackend::start().await;
+        let token = backend.register("ada@example.com", "hunter22");
+        let dir = TempDir::new().unwrap();
+        let app = mock_app();
+        app.manage(backend.client());
+        app.manage(IdempotencyStore::load(dir.path().to_path_buf()).unwrap());
+        app.manage(UploadManager::new(dir.path().to_path_buf(), UploadConfig::default()).unwrap());
+        app.manage(AudioCache::new(dir.path().join("cache")).unwrap());
+        app.manage(AudioStore::new(dir.path().to_path_buf()).unwrap());
+
+        let pool = db::open(&dir.path().join(db::DATABASE_FILE)).await.unwrap();
+        let store = LocalMemoStore::new(app.handle().clone(), pool.clone());
+        let engine = SyncEngine::new(pool.clone(), SyncConfig { conflict_policy: policy });
+        Self {
+            backend,
+            app,
+            pool,
+            store,
+            engine,
+            token,
+            _dir: dir,
+        }
+    }
+
+    /// Creates a memo on the backend, as another device would.
+    async fn remote_create(&self, title: &str) -> VoiceMemo {
+        let handle = self.app.handle();
+        let api = self.app.state::<ApiClient>();
+        let memo = memo_ops::save_audio(
+            handle,
+            &api,
+            &self.app.state::<IdempotencyStore>(),
+            &self.app.state::<UploadManager>(),
+            &self.token,
+            vec![0x1A, 0x45, 0xDF, 0xA3, title.len() as u8],
+            "00:03".into(),
+            vec![],
+        )
+        .await
+        .unwrap();
+        self.remote_edit(&memo.id, title).await;
+        memo
+    }
+
+    async fn remote_edit(&self, id: &str, title: &str) {
+        let api = self.app.state::<ApiClient>();
+        memo_ops::save_memo(self.app.handle(), &api, &self.token, id, title, None, None, None, None)
+            .await
+            .unwrap();
+    }
+
+    async fn remote_titles(&self) -> Vec<String> {
+        let api = self.app.state::<ApiClient>();
+        let page = memo_ops::get_memos(&api, &self.token, &MemoQuery::default()).await.unwrap();
+        let mut titles: Vec<_> = page.items.into_iter().map(|m| m.title).collect();
+        titles.sort();
+        titles
+    }
+
+    async fn local_edit(&self, id: &str, title: &str) {
+        let update = MemoUpdate {
+            title: title.to_string(),
+            transcript: None,
+            translate: None,
+            summary: None,
+            tags: None,
+        };
+        self.store.update("unused", id, update).await.unwrap();
+    }
+
+    async fn local_titles(&self) -> Vec<String> {
+        let page = self.store.list("unused", &MemoQuery::default()).await.unwrap();
+        let mut titles: Vec<_> = page.items.into_iter().map(|m| m.title).collect();
+        titles.sort();
+        titles
+    }
+}
+
+#[tokio::test]
+async fn pulls_remote_memos_and_pushes_local_ones() {
+    let h = Harness::start(ConflictPolicy::default()).await;
+    let remote = h.remote_create("From phone").await;
+    let local = h
+        .store
+        .create("unused", vec![0x4F, 0x67, 0x67, 0x53, 1], "00:05".into(), vec!["desk".into()])
+        .await
+        .unwrap();
+    h.local_edit(&local.id, "From desktop").await;
+
+    let report = h.engine.sync(h.app.handle(), &h.token).await.unwrap();
+    assert_eq!((report.pulled, report.pushed), (1, 1));
+    assert!(report.conflicts.is_empty());
+    assert_eq!(h.local_titles().await, ["From desktop", "From phone"]);
+    assert_eq!(h.remote_titles().await, ["From desktop", "From phone"]);
+
+    // The pulled memo keeps the backend id and its recording.
+    let pulled = h.store.get("unused", &remote.id).await.unwrap().unwrap();
+    assert_eq!(pulled.audio_blob, remote.audio_blob);
+
+    // Nothing changed since, so a second run is a no-op.
+    let report = h.engine.sync(h.app.handle(), &h.token).await.unwrap();
+    assert_eq!((report.pulled, report.pushed), (0, 0));
+    assert_eq!(h.backend.memo_count(), 2);
+}
+
+#[tokio::test]
+async fn propagates_deletions_both_ways() {
+    let h = Harness::start(ConflictPolicy::default()).await;
+    let kept = h.remote_create("Kept").await;
+    let deleted_here = h.remote_create("Deleted here").await;
+    let deleted_there = h.remote_create("Deleted there").await;
+    h.engine.sync(h.app.handle(), &h.token).await.unwrap();
+
+    h.store.delete("unused", &deleted_here.id).await.unwrap();
+    let api = h.app.state::<ApiClient>();
+    memo_ops::delete_memo(h.app.handle(), &api, &deleted_there.id, &h.token)
+        .await
+        .unwrap();
+
+    let report = h.engine.sync(h.app.handle(), &h.token).await.unwrap();
+    assert_eq!((report.deleted_local, report.deleted_remote), (1, 0));
+    assert_eq!(h.local_titles().await, ["Kept"]);
+
+    // A memo in the trash stays on the backend until it is purged.
+    assert_eq!(h.remote_titles().await, ["Deleted here", "Kept"]);
+    trash::empty_trash(h.app.handle(), &h.pool).await.unwrap();
+    let report = h.engine.sync(h.app.handle(), &h.token).await.unwrap();
+    assert_eq!(report.deleted_remote, 1);
+    assert_eq!(h.remote_titles().await, ["Kept"]);
+    assert!(h.store.get("unused", &kept.id).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn edit_survives_remote_deletion() {
+    let h = Harness::start(ConflictPolicy::default()).await;
+    let memo = h.remote_create("Draft").await;
+    h.engine.sync(h.app.handle(), &h.token).await.unwrap();
+
+    h.local_edit(&memo.id, "Final").await;
+    let api = h.app.state::<ApiClient>();
+    memo_ops::delete_memo(h.app.handle(), &api, &memo.id, &h.token).await.unwrap();
+
+    let report = h.engine.sync(h.app.handle(), &h.token).await.unwrap();
+    assert_eq!(report.conflicts.len(), 1);
+    assert_eq!(report.conflicts[0].resolution, Resolution::KeptLocal);
+    assert_eq!(report.pushed, 1);
+    assert_eq!(h.remote_titles().await, ["Final"]);
+}
+
+/// Edits one synced memo on both sides and syncs with `policy`.
+async fn conflicting_edit(policy: ConflictPolicy) -> (Harness, Resolution) {
+    let h = Harness::start(policy).await;
+    let memo = h.remote_create("Original").await;
+    h.engine.sync(h.app.handle(), &h.token).await.unwrap();
+
+    h.remote_edit(&memo.id, "Remote edit").await;
+    h.local_edit(&memo.id, "Local edit").await;
+
+    let events = Arc::new(Mutex::new(0));
+    let seen = events.clone();
+    h.app.handle().listen("sync:conflict", move |_| *seen.lock().unwrap() += 1);
+
+    let report = h.engine.sync(h.app.handle(), &h.token).await.unwrap();
+    assert_eq!(report.conflicts.len(), 1);
+    assert_eq!(report.conflicts[0].memo_id, memo.id);
+    assert_eq!(report.conflicts[0].policy, policy);
+    assert_eq!(*events.lock().unwrap(), 1);
+    let resolution = report.conflicts[0].resolution;
+    (h, resolution)
+}
+
+#[tokio::test]
+async fn local_wins_overwrites_the_backend() {
+    let (h, resolution) = conflicting_edit(ConflictPolicy::LocalWins).await;
+    assert_eq!(resolution, Resolution::KeptLocal);
+    assert_eq!(h.local_titles().await, ["Local edit"]);
+    assert_eq!(h.remote_titles().await, ["Local edit"]);
+}
+
+#[tokio::test]
+async fn remote_wins_discards_the_local_edit() {
+    let (h, resolution) = conflicting_edit(ConflictPolicy::RemoteWins).await;
+    assert_eq!(resolution, Resolution::KeptRemote);
+    assert_eq!(h.local_titles().await, ["Remote edit"]);
+    assert_eq!(h.remote_titles().await, ["Remote edit"]);
+}
+
+#[tokio::test]
+async fn newest_wins_compares_edit_times() {
+    // The mock backend's clock is in the past, so the local edit is newer.
+    let (h, resolution) = conflicting_edit(ConflictPolicy::NewestWins).await;
+    assert_eq!(resolution, Resolution::KeptLocal);
+    assert_eq!(h.remote_titles().await, ["Local edit"]);
+}
+
+#[tokio::test]
+async fn keep_both_uploads_a_conflicted_copy() {
+    let (h, resolution) = conflicting_edit(ConflictPolicy::KeepBoth).await;
+    assert_eq!(resolution, Resolution::KeptBoth);
+    let expected = ["Local edit (conflicted copy)", "Remote edit"];
+    assert_eq!(h.local_titles().await, expected);
+    assert_eq!(h.remote_titles().await, expected);
+}
+
+#[tokio::test]
+async fn rejects_overlapping_runs() {
+    let h = Harness::start(ConflictPolicy::default()).await;
+    h.remote_create("Slow").await;
+    let (first, second) = tokio::join!(
+        h.engine.sync(h.app.handle(), &h.token),
+        h.engine.sync(h.app.handle(), &h.token)
+    );
+    assert!(first.is_ok());
+    assert_eq!(second.unwrap_err().kind, app_lib::error::ErrorKind::Conflict);
+}
@@ -0,0 +1,126 @@
+//! Library-wide tag operations on the local database.
+
+mod support;
+
+use app_lib::error::ErrorKind;
+use app_lib::memo_store::MemoStore;
+use app_lib::tags::{self, normalize_tags, TagCount};
+use std::sync::{Arc, Mutex};
+use support::{recording, Harness};
+use tauri::Listener;
+
+impl Harness {
+    async fn memo(&self, tags: &[&str]) -> String {
+        let audio = recording(tags.len() as u8);
+        let tags = tags.iter().map(|t| t.to_string()).collect();
+        self.store.create("unused", audio, "00:10".into(), tags).await.unwrap().id
+    }
+
+    async fn tags_of(&self, id: &str) -> Vec<String> {
+        self.store.get("unused", id).await.unwrap().unwrap().tags.unwrap()
+    }
+
+    async fn counts(&self) -> Vec<(String, u32)> {
+        tags::list_tags(&self.pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|TagCount { tag, count }| (tag, count))
+            .collect()
+    }
+
+    /// Counts `memo:updated` events from now on.
+    fn updates(&self) -> Arc<Mutex<usize>> {
+        let seen = Arc::new(Mutex::new(0));
+        let sink = seen.clone();
+        self.app.handle().listen("memo:updated", move |_| *sink.lock().unwrap() += 1);
+        seen
+    }
+}
+
+#[test]
+fn normalizes_case_whitespace_and_duplicates() {
+    let raw: Vec<String> = ["  Work ", "deep   focus", "work", "", "Ideas"].iter().map(|t| t.to_string()).collect();
+    assert_eq!(normalize_tags(&raw), ["work", "deep focus", "ideas"]);
+}
+
+#[tokio::test]
+async fn tags_are_normalized_on_save_and_counted() {
+    let h = Harness::start().await;
+    let first = h.memo(&["Work", " Ideas "]).await;
+    h.memo(&["work"]).await;
+    h.memo(&["personal", "WORK"]).await;
+
+    assert_eq!(h.tags_of(&first).await, ["work", "ideas"]);
+    assert_eq!(
+        h.counts().await,
+        [("work".to_string(), 3), ("ideas".to_string(), 1), ("personal".to_string(), 1)]
+    );
+}
+
+#[tokio::test]
+async fn rename_merge_and_delete_apply_to_every_memo() {
+    let h = Harness::start().await;
+    let a = h.memo(&["meeting", "q3"]).await;
+    let b = h.memo(&["meetings", "q3"]).await;
+    let c = h.memo(&["standup"]).await;
+    let updates = h.updates();
+
+    assert_eq!(tags::rename_tag(h.app.handle(), &h.pool, "Q3", "quarter 3").await.unwrap(), 2);
+    assert_eq!(h.tags_of(&a).await, ["meeting", "quarter 3"]);
+    assert_eq!(*updates.lock().unwrap(), 1);
+
+    let sources = vec!["meetings".to_string(), "standup".to_string()];
+    assert_eq!(tags::merge_tags(h.app.handle(), &h.pool, &sources, "meeting").await.unwrap(), 2);
+    assert_eq!(h.tags_of(&b).await, ["meeting", "quarter 3"]);
+    assert_eq!(h.tags_of(&c).await, ["meeting"]);
+    assert_eq!(*updates.lock().unwrap(), 2);
+
+    // Renaming onto a tag the memo already has keeps it once.
+    assert_eq!(tags::rename_tag(h.app.handle(), &h.pool, "quarter 3", "meeting").await.unwrap(), 2);
+    assert_eq!(h.tags_of(&a).await, ["meeting"]);
+
+    assert_eq!(tags::delete_tag(h.app.handle(), &h.pool, "MEETING").await.unwrap(), 3);
+    assert!(h.counts().await.is_empty());
+    assert_eq!(*updates.lock().unwrap(), 4);
+
+    // Nothing to change means no notification.
+    assert_eq!(tags::delete_tag(h.app.handle(), &h.pool, "meeting").await.unwrap(), 0);
+    assert_eq!(*updates.lock().unwrap(), 4);
+}
+
+#[tokio::test]
+async fn normalizes_tags_saved_before_normalization() {
+    let h = Harness::start().await;
+    let id = h.memo(&["placeholder"]).await;
+    // Tags written by an older version of the app.
+    sqlx::query("DELETE FROM memo_tags").execute(&h.pool).await.unwrap();
+    for (position, tag) in ["Work", "work ", "Deep  Focus"].iter().enumerate() {
+        sqlx::query("INSERT INTO memo_tags (memo_id, position, tag) VALUES (?, ?, ?)")
+            .bind(&id)
+            .bind(position as i64)
+            .bind(tag)
+            .execute(&h.pool)
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(tags::normalize_all_tags(h.app.handle(), &h.pool).await.unwrap(), 1);
+    assert_eq!(h.tags_of(&id).await, ["work", "deep focus"]);
+    let dirty: bool = sqlx::query_scalar("SELECT dirty FROM voice_memos WHERE id = ?")
+        .bind(&id)
+        .fetch_one(&h.pool)
+        .await
+        .unwrap();
+    assert!(dirty);
+    assert_eq!(tags::normalize_all_tags(h.app.handle(), &h.pool).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn rejects_empty_tag_names() {
+    let h = Harness::start().await;
+    h.memo(&["work"]).await;
+    let err = tags::rename_tag(h.app.handle(), &h.pool, "work", "   ").await.unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Validation);
+    assert_eq!(h.counts().await, [("work".to_string(), 1)]);
+}
@@ -0,0 +1,115 @@
+//! Soft deletion: the trash, restoring from it and purging it.
+
+mod support;
+
+use app_lib::error::ErrorKind;
+use app_lib::memo_ops::MemoQuery;
+use app_lib::memo_store::{MemoStore, MemoUpdate};
+use app_lib::search::{search_memos, SearchQuery};
+use app_lib::tags;
+use app_lib::trash::{self, TrashConfig};
+use support::Harness;
+
+impl Harness {
+    /// Creates a memo titled and tagged `name`.
+    async fn memo(&self, name: &str) -> String {
+        let audio = [vec![0x1A, 0x45, 0xDF, 0xA3], name.as_bytes().to_vec()].concat();
+        let memo = self.store.create("unused", audio, "00:01".into(), vec![]).await.unwrap();
+        let update = MemoUpdate {
+            title: name.to_string(),
+            transcript: None,
+            translate: None,
+            summary: None,
+            tags: Some(vec![name.to_string()]),
+        };
+        self.store.update("unused", &memo.id, update).await.unwrap();
+        memo.id
+    }
+
+    async fn listed(&self) -> Vec<String> {
+        let page = self.store.list("unused", &MemoQuery::default()).await.unwrap();
+        page.items.into_iter().map(|m| m.id).collect()
+    }
+
+    async fn trashed(&self) -> Vec<String> {
+        let items = trash::list_trash(&self.pool, &TrashConfig::default()).await.unwrap();
+        items.into_iter().map(|t| t.memo.id).collect()
+    }
+
+    /// Backdates when a memo was moved to the trash.
+    async fn trashed_days_ago(&self, id: &str, days: i64) {
+        let at = (chrono::Utc::now() - chrono::Duration::days(days)).to_rfc3339();
+        sqlx::query("UPDATE voice_memos SET deleted_at = ? WHERE id = ?")
+            .bind(at)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn deleted_memos_move_to_the_trash_and_can_be_restored() {
+    let h = Harness::start().await;
+    let kept = h.memo("kept").await;
+    let deleted = h.memo("deleted").await;
+
+    h.store.delete("unused", &deleted).await.unwrap();
+    assert_eq!(h.listed().await, [kept.as_str()]);
+    assert!(h.store.get("unused", &deleted).await.unwrap().is_none());
+    assert_eq!(h.trashed().await, [deleted.as_str()]);
+    let query = SearchQuery {
+        text: "deleted".into(),
+        ..SearchQuery::default()
+    };
+    assert_eq!(search_memos(&h.pool, &query).await.unwrap().total, 0);
+    let tag_names: Vec<_> = tags::list_tags(&h.pool).await.unwrap().into_iter().map(|t| t.tag).collect();
+    assert_eq!(tag_names, ["kept"]);
+
+    // Deleting twice is not possible; the memo is already gone from the list.
+    let err = h.store.delete("unused", &deleted).await.unwrap_err();
+    assert_eq!(err.kind, ErrorKind::NotFound);
+
+    trash::restore_memo(h.app.handle(), &h.pool, &deleted).await.unwrap();
+    assert_eq!(h.listed().await.len(), 2);
+    assert!(h.trashed().await.is_empty());
+    assert_eq!(search_memos(&h.pool, &query).await.unwrap().total, 1);
+    let err = trash::restore_memo(h.app.handle(), &h.pool, &kept).await.unwrap_err();
+    assert_eq!(err.kind, ErrorKind::NotFound);
+}
+
+#[tokio::test]
+async fn clearing_all_memos_moves_them_to_the_trash() {
+    let h = Harness::start().await;
+    h.memo("one").await;
+    h.memo("two").await;
+
+    assert_eq!(h.store.delete_all("unused").await.unwrap(), "Moved 2 memos to the trash");
+    assert!(h.listed().await.is_empty());
+    assert_eq!(h.trashed().await.len(), 2);
+
+    assert_eq!(trash::empty_trash(h.app.handle(), &h.pool).await.unwrap(), 2);
+    assert!(h.trashed().await.is_empty());
+    assert_eq!(trash::empty_trash(h.app.handle(), &h.pool).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn purges_memos_past_the_retention_period() {
+    let h = Harness::start().await;
+    let old = h.memo("old").await;
+    let recent = h.memo("recent").await;
+    h.store.delete("unused", &old).await.unwrap();
+    h.store.delete("unused", &recent).await.unwrap();
+    h.trashed_days_ago(&old, 31).await;
+
+    let listed = trash::list_trash(&h.pool, &TrashConfig::default()).await.unwrap();
+    assert!(listed.iter().all(|t| t.purge_at.is_some()));
+
+    let keep_forever = TrashConfig {
+        retention_days: 0,
+        ..TrashConfig::default()
+    };
+    assert_eq!(trash::purge_expired(h.app.handle(), &h.pool, &keep_forever).await.unwrap(), 0);
+    assert_eq!(trash::purge_expired(h.app.handle(), &h.pool, &TrashConfig::default()).await.unwrap(), 1);
+    assert_eq!(h.trashed().await, [recent]);
+}
@@ -0,0 +1,180 @@
+//! Passphrase encryption of the local library: the SQLCipher database and
+//! sealed recordings, locking, unlocking and removing the passphrase.
+
+mod support;
+
+use app_lib::db;
+use app_lib::error::ErrorKind;
+use app_lib::memo_store::MemoStore;
+use app_lib::vault::VaultStatus;
+use sqlx::pool::PoolConnection;
+use sqlx::Sqlite;
+use std::path::PathBuf;
+use std::time::Duration;
+use support::Harness;
+
+const AUDIO: &[u8] = b"\x1A\x45\xDF\xA3 a very secret recording";
+const PASSPHRASE: &str = "correct horse battery";
+
+impl Harness {
+    async fn memo(&self) -> String {
+        self.store.create("unused", AUDIO.to_vec(), "00:01".into(), vec![]).await.unwrap().id
+    }
+
+    fn file(&self, name: &str) -> PathBuf {
+        self.dir.path().join(name)
+    }
+
+    fn recordings(&self) -> Vec<Vec<u8>> {
+        self.audio_files().into_iter().map(|path| std::fs::read(path).unwrap()).collect()
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[tokio::test]
+async fn setting_a_passphrase_encrypts_memos_and_recordings() {
+    let h = Harness::start().await;
+    let id = h.memo().await;
+    assert!(h.recordings().iter().all(|r| contains(r, b"secret")));
+
+    h.vault.set_passphrase(h.app.handle(), PASSPHRASE).await.unwrap();
+    assert_eq!(h.vault.status(), VaultStatus { encrypted: true, locked: false });
+    assert!(!h.file(db::DATABASE_FILE).exists());
+    let database = std::fs::read(h.file(db::ENCRYPTED_DATABASE_FILE)).unwrap();
+    assert!(!database.starts_with(b"SQLite format 3"));
+    assert!(h.recordings().iter().all(|r| !contains(r, b"secret")));
+
+    // Everything stays readable while unlocked, including new memos.
+    assert_eq!(h.store.audio("unused", &id).await.unwrap(), AUDIO);
+    let other = h.memo().await;
+    assert_eq!(h.store.audio("unused", &other).await.unwrap(), AUDIO);
+    assert_eq!(h.recordings().len(), 1);
+}
+
+#[tokio::test]
+async fn writes_in_progress_are_kept_when_the_passphrase_changes() {
+    let h = Harness::start().await;
+    let id = h.memo().await;
+    // Writes on a connection checked out before the switch, made once the
+    // copy would otherwise have started.
+    let rename_later = |mut conn: PoolConnection<Sqlite>, name: &'static str| {
+        let id = id.clone();
+        async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            sqlx::query("UPDATE voice_memos SET name = ? WHERE id = ?")
+                .bind(name)
+                .bind(&id)
+                .execute(&mut *conn)
+                .await
+                .unwrap();
+        }
+    };
+
+    let conn = h.pool.acquire().await.unwrap();
+    let (set, ()) = tokio::join!(
+        h.vault.set_passphrase(h.app.handle(), PASSPHRASE),
+        rename_later(conn, "Encrypted")
+    );
+    set.unwrap();
+    assert_eq!(h.store.get("unused", &id).await.unwrap().unwrap().title, "Encrypted");
+
+    let conn = h.pool.acquire().await.unwrap();
+    let (removed, ()) = tokio::join!(
+        h.vault.remove_passphrase(h.app.handle(), PASSPHRASE),
+        rename_later(conn, "Decrypted")
+    );
+    removed.unwrap();
+    assert_eq!(h.store.get("unused", &id).await.unwrap().unwrap().title, "Decrypted");
+}
+
+#[tokio::test]
+async fn locking_blocks_access_until_unlocked() {
+    let h = Harness::start().await;
+    let id = h.memo().await;
+    h.vault.set_passphrase(h.app.handle(), PASSPHRASE).await.unwrap();
+
+    h.vault.lock(h.app.handle()).await.unwrap();
+    assert_eq!(h.store.get("unused", &id).await.unwrap_err().kind, ErrorKind::Locked);
+    assert_eq!(h.store.audio("unused", &id).await.unwrap_err().kind, ErrorKind::Locked);
+
+    let err = h.vault.unlock(h.app.handle(), "not the passphrase").await.unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Unauthorized);
+    h.vault.unlock(h.app.handle(), PASSPHRASE).await.unwrap();
+    assert!(h.store.get("unused", &id).await.unwrap().is_some());
+    assert_eq!(h.store.audio("unused", &id).await.unwrap(), AUDIO);
+}
+
+#[tokio::test]
+async fn an_encrypted_library_starts_locked() {
+    let h = Harness::start().await;
+    let id = h.memo().await;
+    h.vault.set_passphrase(h.app.handle(), PASSPHRASE).await.unwrap();
+    let Harness { app, dir, .. } = h;
+    drop(app);
+
+    let h = Harness::open(dir).await;
+    assert_eq!(h.vault.status(), VaultStatus { encrypted: true, locked: true });
+    assert_eq!(h.store.get("unused", &id).await.unwrap_err().kind, ErrorKind::Locked);
+    h.vault.unlock(h.app.handle(), PASSPHRASE).await.unwrap();
+    assert!(h.store.get("unused", &id).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn changing_the_passphrase_keeps_the_data() {
+    let h = Harness::start().await;
+    let id = h.memo().await;
+    let short = h.vault.set_passphrase(h.app.handle(), "short").await.unwrap_err();
+    assert_eq!(short.kind, ErrorKind::Validation);
+    h.vault.set_passphrase(h.app.handle(), PASSPHRASE).await.unwrap();
+
+    let err = h.vault.change_passphrase(h.app.handle(), "wrong passphrase", "another passphrase").await;
+    assert_eq!(err.unwrap_err().kind, ErrorKind::Unauthorized);
+    h.vault
+        .change_passphrase(h.app.handle(), PASSPHRASE, "another passphrase")
+        .await
+        .unwrap();
+
+    h.vault.lock(h.app.handle()).await.unwrap();
+    let old = h.vault.unlock(h.app.handle(), PASSPHRASE).await.unwrap_err();
+    assert_eq!(old.kind, ErrorKind::Unauthorized);
+    h.vault.unlock(h.app.handle(), "another passphrase").await.unwrap();
+    assert_eq!(h.store.audio("unused", &id).await.unwrap(), AUDIO);
+}
+
+#[tokio::test]
+async fn removing_the_passphrase_restores_plain_files() {
+    let h = Harness::start().await;
+    let id = h.memo().await;
+    h.vault.set_passphrase(h.app.handle(), PASSPHRASE).await.unwrap();
+
+    let err = h.vault.remove_passphrase(h.app.handle(), "wrong passphrase").await.unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Unauthorized);
+    h.vault.remove_passphrase(h.app.handle(), PASSPHRASE).await.unwrap();
+    assert_eq!(h.vault.status(), VaultStatus { encrypted: false, locked: false });
+    assert!(!h.file("vault.json").exists());
+    assert!(!h.file(db::ENCRYPTED_DATABASE_FILE).exists());
+    let database = std::fs::read(h.file(db::DATABASE_FILE)).unwrap();
+    assert!(database.starts_with(b"SQLite format 3"));
+    assert!(h.recordings().iter().all(|r| contains(r, b"secret")));
+
+    assert!(h.store.get("unused", &id).await.unwrap().is_some());
+    assert_eq!(h.store.audio("unused", &id).await.unwrap(), AUDIO);
+}
+
+#[tokio::test]
+async fn a_failed_encryption_leaves_the_library_plain() {
+    let h = Harness::start().await;
+    let id = h.memo().await;
+    // A directory in the way of the encrypted copy makes the export fail.
+    std::fs::create_dir_all(h.file(&format!("{}.partial", db::ENCRYPTED_DATABASE_FILE))).unwrap();
+
+    assert!(h.vault.set_passphrase(h.app.handle(), PASSPHRASE).await.is_err());
+    assert_eq!(h.vault.status(), VaultStatus { encrypted: false, locked: false });
+    assert!(!h.file("vault.json").exists());
+    assert!(!h.file(db::ENCRYPTED_DATABASE_FILE).exists());
+    assert!(h.store.get("unused", &id).await.unwrap().is_some());
+    assert_eq!(h.store.audio("unused", &id).await.unwrap(), AUDIO);
+}
@@ -7,10 +7,10 @@ import { Badge } from "@/components/ui/badge"
 import { Mic, Play, Clock, Tag, TrendingUp, AudioWaveform } from "lucide-react"
 import Link from "next/link"
 import { formatDistanceToNow } from "date-fns"
-import { invoke } from "@tauri-apps/api/core"
+import { fetchAllMemos, retryPendingOperations, syncMemos } from "../../lib/memo-api"
 import { useEffect, useState } from "react"
 import { toast } from "sonner"
-import { jwtDecode } from "jwt-decode"
+import { useSession } from "../../hooks/useSession"
 
 // UPDATED: This interface now matches the backend API response
 interface Memo {
@@ -23,14 +23,6 @@ interface Memo {
   audio_blob: number[];
 }
 
-// NEW: Interface for the decoded JWT payload, now including username
-interface DecodedToken {
-  email: string;
-  sub: string;
-  exp: number;
-  username?: string; // Username is optional for backward compatibility
-}
-
 // Helper to parse "MM:SS" duration string to seconds
 const parseDuration = (durationStr: string): number => {
     const parts = durationStr.split(':').map(Number);
@@ -63,37 +55,29 @@ const getGreeting = () => {
 export default function Dashboard() {
   const [memos, setMemos] = useState<Memo[]>([])
   const [loading, setLoading] = useState(true)
-  const [token, setToken] = useState<string | null>(null);
-  const [username, setUsername] = useState<string>(""); // NEW: State for username
+  const { session, loading: sessionLoading } = useSession()
+  // Prioritize username from the session, fallback to email
+  const name = session ? session.username || session.email.split('@')[0] : ""
+  // Capitalize the first letter of the username
+  const username = name.charAt(0).toUpperCase() + name.slice(1)
 
   useEffect(() => {
-    const storedToken = localStorage.getItem("jwt");
-    if (storedToken) {
-      setToken(storedToken);
-      try {
-        const decoded = jwtDecode<DecodedToken>(storedToken);
-        // UPDATED: Prioritize username from token, fallback to email
-        const name = decoded.username || decoded.email.split('@')[0];
-        // Capitalize the first letter of the username
-        const capitalizedUsername = name.charAt(0).toUpperCase() + name.slice(1);
-        setUsername(capitalizedUsername);
-      } catch (error) {
-        console.error("Failed to decode token for username:", error);
-        setUsername("User"); // Fallback username
-      }
-    } else {
+    if (sessionLoading) return;
+    if (!session) {
       setLoading(false);
       toast.error("You must be logged in to view the dashboard.");
     }
-  }, []);
+  }, [session, sessionLoading]);
 
   useEffect(() => {
     const fetchMemos = async () => {
-      if (!token) return;
+      if (!session) return;
       
       setLoading(true);
+      // Runs in the background; memo lists refresh on memo:updated
+      syncMemos().catch((error) => console.error("Sync failed:", error))
       try {
-        const memosData = await invoke<Memo[]>("get_memos_command", { token });
+        const memosData = await fetchAllMemos<Memo>();
         setMemos(memosData)
       } catch (error) {
         console.error("Failed to fetch memos:", error)
@@ -103,7 +87,17 @@ export default function Dashboard() {
       }
     }
     fetchMemos()
-  }, [token])
+  }, [session])
+
+  // Replay writes queued while offline as soon as the network is back
+  useEffect(() => {
+    if (!session) return
+    const replay = () => {
+      retryPendingOperations().catch((error) => console.error("Replaying queued changes failed:", error))
+    }
+    window.addEventListener("online", replay)
+    return () => window.removeEventListener("online", replay)
+  }, [session])
 
   const recentMemos = memos.slice(0, 3)
   const totalMemos = memos.length
@@ -129,7 +123,7 @@ export default function Dashboard() {
     )
   }
   
-  if (!token) {
+  if (!session) {
     return (
         <SidebarInset className="flex flex-col h-full">
             <header className="flex h-16 shrink-0 items-center gap-2 border-b px-4">
@@ -1,6 +1,6 @@
 "use client"
 
-import { AudioWaveform, BarChart3, Home, Mic, Settings, LogOut } from "lucide-react"
+import { AudioWaveform, BarChart3, Home, Mic, Settings, LogOut, UserPlus, Users } from "lucide-react"
 import {
   Sidebar,
   SidebarContent,
@@ -19,16 +19,11 @@ import { ThemeToggle } from "@/components/ThemeToggle"
 import Link from "next/link"
 import { usePathname, useRouter } from "next/navigation"
 import { useEffect, useState } from "react"
-import { jwtDecode } from "jwt-decode"
 import { Avatar, AvatarFallback, AvatarImage } from "@/components/ui/avatar"
 import { toast } from "sonner"
-
-interface DecodedToken {
-  email: string;
-  sub: string;
-  exp: number;
-  username?: string; // Username is optional
-}
+import { useSession } from "../../hooks/useSession"
+import { Account, listAccounts, logout, switchAccount } from "../../lib/memo-api"
+import { useMemoStore } from "../../lib/memo-store"
 
 const menuItems = [
   { title: "Dashboard", url: "/", icon: Home },
@@ -41,37 +36,38 @@ const menuItems = [
 export function AppSidebar() {
   const pathname = usePathname()
   const router = useRouter();
-  const [isLoggedIn, setIsLoggedIn] = useState(false);
-  const [userInitial, setUserInitial] = useState<string>("")
+  const { session } = useSession();
+  const isLoggedIn = session !== null;
+  const nameSource = session ? session.username || session.email.split('@')[0] : "";
+  const userInitial = nameSource.charAt(0).toUpperCase();
+  const [otherAccounts, setOtherAccounts] = useState<Account[]>([]);
 
   useEffect(() => {
-    const token = localStorage.getItem("jwt");
-    if (token) {
-      try {
-        const decoded = jwtDecode<DecodedToken>(token);
-        if (decoded.exp * 1000 > Date.now()) {
-          const nameSource = decoded.username || decoded.email.split('@')[0];
-          const initial = nameSource.charAt(0).toUpperCase();
-          setUserInitial(initial);
-          setIsLoggedIn(true);
-        } else {
-          localStorage.removeItem("jwt");
-          setIsLoggedIn(false);
-        }
-      } catch (error) {
-        console.error("Failed to decode JWT:", error);
-        setIsLoggedIn(false);
-      }
-    } else {
-      setIsLoggedIn(false);
+    listAccounts()
+      .then((accounts) => setOtherAccounts(accounts.filter((account) => !account.active)))
+      .catch((error) => console.error("Failed to list accounts:", error));
+  }, [session]);
+
+  // The app restarts into the other account's profile
+  const handleSwitchAccount = async (account: Account) => {
+    try {
+      useMemoStore.persist.clearStorage();
+      await switchAccount(account.id);
+    } catch (error) {
+      console.error("Switching account failed:", error);
+      toast.error("Failed to switch account.");
     }
-  }, [pathname]); // Re-check auth status on route change
+  };
 
-  const handleLogout = () => {
-    localStorage.removeItem("jwt");
-    setIsLoggedIn(false);
-    toast.success("You have been logged out.");
-    router.push("/auth");
+  const handleLogout = async () => {
+    try {
+      await logout();
+      toast.success("You have been logged out.");
+      router.push("/auth");
+    } catch (error) {
+      console.error("Logout failed:", error);
+      toast.error("Failed to log out.");
+    }
   };
 
   return (
@@ -133,7 +129,22 @@ export function AppSidebar() {
                     </AvatarFallback>
                   </Avatar>
                 </PopoverTrigger>
-                <PopoverContent className="w-40 p-2" side="top" align="end">
+                <PopoverContent className="w-56 p-2" side="top" align="end">
+                  {otherAccounts.map((account) => (
+                    <Button
+                      key={account.id}
+                      variant="ghost"
+                      className="w-full justify-start"
+                      onClick={() => handleSwitchAccount(account)}
+                    >
+                      <Users className="mr-2 h-4 w-4" />
+                      <span className="truncate">{account.username || account.email}</span>
+                    </Button>
+                  ))}
+                  <Button variant="ghost" className="w-full justify-start" onClick={() => router.push("/auth")}>
+                    <UserPlus className="mr-2 h-4 w-4" />
+                    Add account
+                  </Button>
                   <Button variant="ghost" className="w-full justify-start" onClick={handleLogout}>
                     <LogOut className="mr-2 h-4 w-4" />
                     Logout
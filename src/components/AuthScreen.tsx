@@ -11,17 +11,13 @@ import { toast } from "sonner";
 import { useRouter } from "next/navigation";
 import { Eye, EyeOff } from "lucide-react";
 import { cn } from "../../lib/utils"; // Import cn for conditional classes
+import { login as startSession } from "../../lib/memo-api";
 
 interface AuthProps {
   onAuthSuccess?: () => void;
 }
 
 // Define response types from the Tauri backend
-interface LoginResponse {
-    message: string;
-    token: string;
-}
-
 interface SignupResponse {
     message: string;
     user_id: string;
@@ -72,12 +68,9 @@ const Auth: React.FC<AuthProps> = ({ onAuthSuccess }) => {
 
     try {
       if (mode === "login") {
-        const data = await invoke<LoginResponse>("login_command", {
-          email: login.email,
-          password: login.password,
-        });
-        localStorage.setItem("jwt", data.token);
-        toast.success(data.message);
+        // The token stays in Rust; every window hears about it through "session:changed"
+        const session = await startSession(login.email, login.password);
+        toast.success(`Logged in as ${session.username || session.email}`);
         router.replace("/");
       } else { // Signup mode
         const data = await invoke<SignupResponse>("signup_command", {
@@ -90,7 +83,8 @@ const Auth: React.FC<AuthProps> = ({ onAuthSuccess }) => {
         setActiveTab("login");
       }
     } catch (err: any) {
-        const errorMessage = typeof err === 'string' ? err.replace("API Error:", "").trim() : "An unknown error occurred.";
+        // Commands reject with { kind, status, retryable, message, body }
+        const errorMessage = typeof err?.message === 'string' ? err.message : "An unknown error occurred.";
         setError(errorMessage);
         toast.error(errorMessage);
     } finally {
@@ -5,6 +5,7 @@ import { ArrowLeft, Loader2, Copy, Download, Languages, ListCollapse, Save, Play
 import { invoke } from "@tauri-apps/api/core"
 import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from "@/components/ui/select"
 import { toast } from "sonner"
+import { useSession } from "../../../hooks/useSession"
 
 // UPDATED: This interface now matches the backend API response
 interface VoiceMemo {
@@ -63,19 +64,16 @@ export default function TranscribeComponent({
   const audioRef = useRef<HTMLAudioElement | null>(null)
   const [title, setTitle] = useState<string>("")
   const [saveSuccess, setSaveSuccess] = useState(false)
-  const [token, setToken] = useState<string | null>(null); // NEW: State for JWT
+  // The helper window shares the session of the main window
+  const { session, loading: sessionLoading } = useSession()
 
-  // NEW: Effect to load token from localStorage on component mount
   useEffect(() => {
-    const storedToken = localStorage.getItem("jwt");
-    if (storedToken) {
-      setToken(storedToken);
-    } else {
+    if (!sessionLoading && !session) {
       setError("Authentication required. Please log in.");
-      toast.error("Authentication token not found.");
+      toast.error("You are not logged in.");
       setIsTranscribing(false); // Stop loading if not authenticated
     }
-  }, []);
+  }, [session, sessionLoading]);
 
   // Initialize audio player
   useEffect(() => {
@@ -104,19 +102,17 @@ export default function TranscribeComponent({
   }
 
   const transcribeAudio = useCallback(async (blob: Blob) => {
-    if (!token) throw new Error("Authentication token is missing.");
+    if (!session) throw new Error("Authentication required. Please log in.");
     try {
       const arrayBuffer = await blob.arrayBuffer()
       const audioData = Array.from(new Uint8Array(arrayBuffer))
       const transcription = await invoke<string>("transcribe_audio_command", {
         audioBlob: audioData,
-        token,
       })
 
       if (transcription) {
         const generatedTitle = await invoke<string>("generate_memo_name_command", {
           transcription: transcription,
-          token,
         })
         setTitle(generatedTitle)
       }
@@ -125,11 +121,11 @@ export default function TranscribeComponent({
       console.error("Transcription error:", err)
       throw new Error(typeof err === "string" ? err : "Failed to transcribe audio")
     }
-  }, [token])
+  }, [session])
 
   useEffect(() => {
     const startTranscription = async () => {
-      if (!token) return; // Wait for token to be loaded
+      if (!session) return; // Wait for the session to be loaded
 
       setIsTranscribing(true)
       setError(null)
@@ -143,10 +139,10 @@ export default function TranscribeComponent({
       }
     }
     startTranscription()
-  }, [audioBlob, transcribeAudio, token])
+  }, [audioBlob, transcribeAudio, session])
 
   const saveMemo = useCallback(async () => {
-    if (!transcriptionText || !token) {
+    if (!transcriptionText || !session) {
         toast.error("Cannot save without transcription or authentication.");
         return;
     };
@@ -156,7 +152,6 @@ export default function TranscribeComponent({
       const audioData = Array.from(new Uint8Array(arrayBuffer));
 
       const initialMemo = await invoke<VoiceMemo>("save_audio_command", {
-        token,
         audioBlob: audioData,
         duration: formatTime(recordingTime),
         tags: [],
@@ -168,7 +163,6 @@ export default function TranscribeComponent({
 
       await invoke<VoiceMemo>("save_memo_command", {
         id: initialMemo.id,
-        token,
         name: title,
         transcription: transcriptionText,
         translate: translatedText || null,
@@ -187,16 +181,15 @@ export default function TranscribeComponent({
     } finally {
       setIsSaving(false);
     }
-  }, [audioBlob, transcriptionText, translatedText, summaryText, title, recordingTime, token]);
+  }, [audioBlob, transcriptionText, translatedText, summaryText, title, recordingTime, session]);
 
   const handleTranslate = useCallback(async () => {
-    if (!transcriptionText || !token) return;
+    if (!transcriptionText || !session) return;
     setIsTranslating(true);
     try {
       const result = await invoke<string>("translate_text_command", {
         text: transcriptionText,
         targetLanguage: selectedLanguage,
-        token,
       });
       setTranslatedText(result);
       setActiveTab("translated");
@@ -205,16 +198,15 @@ export default function TranscribeComponent({
     } finally {
       setIsTranslating(false);
     }
-  }, [transcriptionText, selectedLanguage, token]);
+  }, [transcriptionText, selectedLanguage, session]);
   
   const handleSummarize = useCallback(async () => {
-    if (!transcriptionText || !token) return;
+    if (!transcriptionText || !session) return;
 
     try {
       setIsSummarizing(true)
       const result = await invoke<string>("summarize_text_command", {
         text: transcriptionText,
-        token,
       })
       setSummaryText(result)
       setActiveTab("summary")
@@ -223,7 +215,7 @@ export default function TranscribeComponent({
     } finally {
       setIsSummarizing(false)
     }
-  }, [transcriptionText, token])
+  }, [transcriptionText, session])
 
   const handleCopyText = useCallback(async () => {
     const textToCopy =
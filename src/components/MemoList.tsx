@@ -3,6 +3,8 @@
 import { useRef, useState, useEffect } from "react"
 import { Brain } from "lucide-react"
 import { invoke } from "@tauri-apps/api/core"
+import { fetchAllMemos, searchMemos } from "../../lib/memo-api"
+import { useSession } from "../../hooks/useSession"
 import { toast } from "sonner"
 import { MemoCard } from "./MemoCard"
 import { LoadingSpinner } from "./LoadingSpinner"
@@ -17,7 +19,7 @@ interface Memo {
   title: string
   created_at: string
   duration: string // The backend sends duration as a formatted string
-  audio_blob: number[]
+  audio_blob: number[] | null // Not included in listings; see loadAudio
   transcript: string | null
   translate: string | null
   summary: string | null
@@ -36,6 +38,7 @@ interface MemoFormData {
 export function MemoList() {
   const [memos, setMemos] = useState<Memo[]>([])
   const [searchTerm, setSearchTerm] = useState("")
+  const [searchHits, setSearchHits] = useState<Set<string> | null>(null)
   const [selectedTag, setSelectedTag] = useState<string>("all")
   const [sortBy, setSortBy] = useState<string>("newest")
   const [playingId, setPlayingId] = useState<string | null>(null)
@@ -54,22 +57,18 @@ export function MemoList() {
   const [targetLanguage, setTargetLanguage] = useState("Spanish")
   const [isLoading, setIsLoading] = useState(true)
   const [isRefreshing, setIsRefreshing] = useState(false)
-  const [token, setToken] = useState<string | null>(null); // NEW: State for JWT
+  const { session, loading: sessionLoading } = useSession()
 
-  // NEW: Effect to load token from localStorage on component mount
   useEffect(() => {
-    const storedToken = localStorage.getItem("jwt");
-    if (storedToken) {
-      setToken(storedToken);
-    } else {
-      setIsLoading(false); // If no token, stop loading
+    if (!sessionLoading && !session) {
+      setIsLoading(false); // If not logged in, stop loading
       toast.error("You must be logged in to view your memos.");
     }
-  }, []);
+  }, [session, sessionLoading]);
 
-  // This effect handles data fetching and event listening, now dependent on the token
+  // This effect handles data fetching and event listening, now dependent on the session
   useEffect(() => {
-    if (!token) return; // Don't proceed without a token
+    if (!session) return; // Don't proceed without a session
 
     fetchMemos()
 
@@ -90,7 +89,25 @@ export function MemoList() {
         }
       })
     }
-  }, [token]) // Re-run if the token changes (e.g., on login)
+  }, [session]) // Re-run if the session changes (e.g., on login)
+
+  // Search the local index once typing pauses; falls back to plain filtering on failure
+  useEffect(() => {
+    if (!searchTerm.trim()) {
+      setSearchHits(null)
+      return
+    }
+    const timer = setTimeout(async () => {
+      try {
+        const results = await searchMemos<{ id: string }>(searchTerm)
+        setSearchHits(new Set(results.hits.map((hit) => hit.memo.id)))
+      } catch (error) {
+        console.error("Search failed:", error)
+        setSearchHits(null)
+      }
+    }, 200)
+    return () => clearTimeout(timer)
+  }, [searchTerm, memos])
 
   // This effect handles the cleanup of ALL audio elements when the component unmounts
   useEffect(() => {
@@ -104,7 +121,7 @@ export function MemoList() {
 
   
   const fetchMemos = async (showRefreshIndicator = false) => {
-    if (!token) return; // Guard against fetching without a token
+    if (!session) return; // Guard against fetching while logged out
     if (showRefreshIndicator) {
       setIsRefreshing(true)
     } else {
@@ -112,7 +129,7 @@ export function MemoList() {
     }
 
     try {
-      const newMemos = await invoke<Memo[]>("get_memos_command", { token })
+      const newMemos = await fetchAllMemos<Memo>()
       setMemos(newMemos)
     } catch (error) {
       console.error("Failed to fetch memos:", error)
@@ -128,9 +145,10 @@ export function MemoList() {
   
   const filteredMemos = memos
     .filter((memo) => {
-      const matchesSearch =
-        memo.title.toLowerCase().includes(searchTerm.toLowerCase()) ||
-        (memo.transcript && memo.transcript.toLowerCase().includes(searchTerm.toLowerCase()))
+      const matchesSearch = searchHits
+        ? searchHits.has(memo.id)
+        : memo.title.toLowerCase().includes(searchTerm.toLowerCase()) ||
+          (memo.transcript && memo.transcript.toLowerCase().includes(searchTerm.toLowerCase()))
       const matchesTag = selectedTag === "all" || (memo.tags && memo.tags.includes(selectedTag))
       return matchesSearch && matchesTag
     })
@@ -151,6 +169,12 @@ export function MemoList() {
       }
     })
 
+  // Recordings are fetched (and cached) on demand instead of with the list
+  const loadAudio = async (memo: Memo): Promise<number[]> => {
+    if (memo.audio_blob) return memo.audio_blob
+    return await invoke<number[]>("get_memo_audio_command", { id: memo.id })
+  }
+
   const playAudio = async (memo: Memo) => {
     Object.values(audioElements).forEach((audio) => {
         audio.pause();
@@ -166,7 +190,7 @@ export function MemoList() {
     }
 
     try {
-        const audioBlob = new Blob([new Uint8Array(memo.audio_blob)], { type: "audio/wav" });
+        const audioBlob = new Blob([new Uint8Array(await loadAudio(memo))], { type: "audio/wav" });
         const audioUrl = URL.createObjectURL(audioBlob);
         const audio = new Audio(audioUrl);
 
@@ -188,9 +212,9 @@ export function MemoList() {
   }
 
   const deleteMemo = async (id: string) => {
-    if (!token) return toast.error("Authentication required.");
+    if (!session) return toast.error("Authentication required.");
     try {
-      await invoke("delete_memo_command", { id, token })
+      await invoke("delete_memo_command", { id })
       toast.success("Memo deleted successfully")
     } catch (error) {
       console.error("Failed to delete memo:", error)
@@ -215,11 +239,10 @@ export function MemoList() {
   }
 
   const saveEdit = async (id: string) => {
-    if (!token) return toast.error("Authentication required.");
+    if (!session) return toast.error("Authentication required.");
     try {
       await invoke("save_memo_command", {
         id,
-        token,
         name: editForm.title,
         transcript: editForm.transcript || null,
         translate: editForm.translate || null,
@@ -235,16 +258,14 @@ export function MemoList() {
   }
 
   const transcribeMemo = async (memo: Memo) => {
-    if (!token) return toast.error("Authentication required.");
+    if (!session) return toast.error("Authentication required.");
     setIsTranscribing(true)
     try {
       const transcript = await invoke<string>("transcribe_audio_command", {
-        audioBlob: memo.audio_blob,
-        token,
+        audioBlob: await loadAudio(memo),
       })
       await invoke("save_memo_command", {
         id: memo.id,
-        token,
         name: memo.title,
         transcript,
         translate: memo.translate,
@@ -265,17 +286,15 @@ export function MemoList() {
       toast.error("No transcript available to translate")
       return
     }
-    if (!token) return toast.error("Authentication required.");
+    if (!session) return toast.error("Authentication required.");
     setIsTranslating(true)
     try {
       const translation = await invoke<string>("translate_text_command", {
         text: memo.transcript,
         targetLanguage,
-        token,
       })
       await invoke("save_memo_command", {
         id: memo.id,
-        token,
         name: memo.title,
         transcript: memo.transcript,
         translate: translation,
@@ -296,16 +315,14 @@ export function MemoList() {
       toast.error("No transcript available to summarize")
       return
     }
-    if (!token) return toast.error("Authentication required.");
+    if (!session) return toast.error("Authentication required.");
     setIsSummarizing(true)
     try {
       const summary = await invoke<string>("summarize_text_command", {
         text: memo.transcript,
-        token,
       })
       await invoke("save_memo_command", {
         id: memo.id,
-        token,
         name: memo.title,
         transcript: memo.transcript,
         translate: memo.translate,
@@ -323,7 +340,7 @@ export function MemoList() {
 
   const downloadAudio = async (memo: Memo) => {
     try {
-      const audioBlob = new Blob([new Uint8Array(memo.audio_blob)], { type: "audio/wav" })
+      const audioBlob = new Blob([new Uint8Array(await loadAudio(memo))], { type: "audio/wav" })
       const url = URL.createObjectURL(audioBlob)
       const a = document.createElement("a")
       a.href = url
@@ -360,7 +377,7 @@ export function MemoList() {
     return <LoadingSpinner />
   }
 
-  if (!token) {
+  if (!session) {
     return (
         <div className="flex items-center justify-center h-full p-6 text-center">
             <Card className="max-w-md">
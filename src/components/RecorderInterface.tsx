@@ -30,6 +30,7 @@ import {
 import { useEffect, useRef, useState } from "react"
 import { toast } from "sonner"
 import { useAudioRecorder } from "../../hooks/useAudioRecorder"
+import { useSession } from "../../hooks/useSession"
 import { cn } from "../../lib/utils"
 import { useRouter } from "next/navigation"
 // NEW: Import the shadcn/ui Select components
@@ -78,6 +79,7 @@ export function RecorderInterface() {
   const [showPermissionModal, setShowPermissionModal] = useState(false)
   const [showMicOffModal, setShowMicOffModal] = useState(false)
   const [showLoginModal, setShowLoginModal] = useState(false)
+  const { session } = useSession()
 
   // NEW: Loading state for recording initialization
   const [isInitializingRecording, setIsInitializingRecording] = useState(false)
@@ -125,14 +127,13 @@ export function RecorderInterface() {
   }
 
   const handleStartRecording = async () => {
-    const token = localStorage.getItem("jwt")
-    if (!token) {
+    if (!session) {
       setShowLoginModal(true)
       return
     }
 
     try {
-      const API = await invoke<string | null>("get_api_key_command", { token })
+      const API = await invoke<string | null>("get_api_key_command")
       if (!API) {
         toast.error("API Key not set. Please set your Gemini API key.")
         setShowApiKeySettings(true)
@@ -181,8 +182,7 @@ export function RecorderInterface() {
   const saveAudioOnly = async () => {
     if (!recordedBlob) return
 
-    const token = localStorage.getItem("jwt")
-    if (!token) {
+    if (!session) {
       setShowLoginModal(true)
       return
     }
@@ -193,7 +193,6 @@ export function RecorderInterface() {
       const audioData = Array.from(new Uint8Array(arrayBuffer))
 
       const memo = await invoke<VoiceMemo>("save_audio_command", {
-        token,
         audioBlob: audioData,
         duration: formattedTime,
         tags: tags,
@@ -212,8 +211,7 @@ export function RecorderInterface() {
   const transcribeAudio = async () => {
     if (!recordedBlob) return
 
-    const token = localStorage.getItem("jwt")
-    if (!token) {
+    if (!session) {
       setShowLoginModal(true)
       return
     }
@@ -228,7 +226,6 @@ export function RecorderInterface() {
       if (!savedMemoId) {
         toast.info("Saving audio before transcription...")
         const memo = await invoke<VoiceMemo>("save_audio_command", {
-          token,
           audioBlob: audioData,
           duration: formattedTime,
           tags: tags,
@@ -238,7 +235,6 @@ export function RecorderInterface() {
       }
 
       const transcription = await invoke<string>("transcribe_audio_command", {
-        token,
         audioBlob: audioData,
       })
 
@@ -246,7 +242,6 @@ export function RecorderInterface() {
 
       if (transcription) {
         const generatedTitle = await invoke<string>("generate_memo_name_command", {
-          token,
           transcription: transcription,
         })
         setTitle(generatedTitle)
@@ -264,8 +259,7 @@ export function RecorderInterface() {
   const translateTranscript = async () => {
     if (!transcript) return
 
-    const token = localStorage.getItem("jwt")
-    if (!token) {
+    if (!session) {
       setShowLoginModal(true)
       return
     }
@@ -273,7 +267,6 @@ export function RecorderInterface() {
     setIsTranslating(true)
     try {
       const translatedText = await invoke<string>("translate_text_command", {
-        token,
         text: transcript,
         targetLanguage: targetLanguage,
       })
@@ -290,8 +283,7 @@ export function RecorderInterface() {
   const summarizeTranscript = async () => {
     if (!transcript) return
 
-    const token = localStorage.getItem("jwt")
-    if (!token) {
+    if (!session) {
       setShowLoginModal(true)
       return
     }
@@ -299,7 +291,6 @@ export function RecorderInterface() {
     setIsSummarizing(true)
     try {
       const summaryText = await invoke<string>("summarize_text_command", {
-        token,
         text: transcript,
       })
       setSummary(summaryText)
@@ -318,8 +309,7 @@ export function RecorderInterface() {
       return
     }
 
-    const token = localStorage.getItem("jwt")
-    if (!token) {
+    if (!session) {
       setShowLoginModal(true)
       return
     }
@@ -328,7 +318,6 @@ export function RecorderInterface() {
     try {
       await invoke<VoiceMemo>("save_memo_command", {
         id: savedMemoId,
-        token,
         name: title,
         transcription: transcript || null,
         translate: translate || null,
@@ -348,8 +337,7 @@ export function RecorderInterface() {
 
   const handleSaveApiKey = async () => {
     const input = document.getElementById("api-key") as HTMLInputElement
-    const token = localStorage.getItem("jwt")
-    if (!token) {
+    if (!session) {
       setShowLoginModal(true)
       return
     }
@@ -357,7 +345,7 @@ export function RecorderInterface() {
     if (input?.value) {
       const geminiKey = input.value
       try {
-        await invoke("save_api_key_command", { token, geminiKey })
+        await invoke("save_api_key_command", { geminiKey })
         setShowApiKeySettings(false)
         toast.success("API key saved")
       } catch (error) {
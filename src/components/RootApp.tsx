@@ -4,11 +4,15 @@ import { useState, useEffect } from "react"
 import { ThemeProvider } from "@/components/ThemeProvider"
 import { SidebarProvider } from "@/components/ui/sidebar"
 import { AppSidebar } from "@/components/AppSidebar"
-import { Toaster } from "sonner"
+import { Toaster, toast } from "sonner"
 import LoadingScreen from "@/components/LoadingScreen"
+import { listen } from "@tauri-apps/api/event"
+import { useRouter } from "next/navigation"
+import type { AuthExpired, AuthExpiring } from "../../lib/memo-api"
 
 export default function RootApp({ children }: { children: React.ReactNode }) {
   const [loading, setLoading] = useState(true)
+  const router = useRouter()
 
   useEffect(() => {
     const timer = setTimeout(() => {
@@ -17,6 +21,26 @@ export default function RootApp({ children }: { children: React.ReactNode }) {
     return () => clearTimeout(timer)
   }, [])
 
+  // Every window, the helper included, goes back to login once the session ends
+  useEffect(() => {
+    const unlistenExpiring = listen<AuthExpiring>("auth:expiring", (event) => {
+      const minutes = Math.max(1, Math.round(event.payload.seconds_left / 60))
+      toast.warning(`Your session expires in ${minutes} minute${minutes === 1 ? "" : "s"}. Save your work and log in again.`)
+    })
+    const unlistenExpired = listen<AuthExpired>("auth:expired", (event) => {
+      toast.error(
+        event.payload.reason === "expired"
+          ? "Your session has expired. Please log in again."
+          : "The server no longer accepts your session. Please log in again.",
+      )
+      router.replace("/auth")
+    })
+    return () => {
+      unlistenExpiring.then((unlisten) => unlisten())
+      unlistenExpired.then((unlisten) => unlisten())
+    }
+  }, [router])
+
   return (
     <ThemeProvider attribute="class" defaultTheme="system" enableSystem disableTransitionOnChange={false}>
       {loading ? (
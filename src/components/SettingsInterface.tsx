@@ -12,6 +12,8 @@ import { useTheme } from "next-themes"
 import { Moon, Sun, Monitor, Trash2, Download, Key } from "lucide-react"
 import { useMemoStore } from "../../lib/memo-store"
 import { invoke } from "@tauri-apps/api/core"
+import { fetchAllMemos } from "../../lib/memo-api"
+import { useSession } from "../../hooks/useSession"
 import { toast } from "sonner"
 import { ElevenLabsVoiceSettings } from "./ElevenLabsVoiceSettings"
 import Link from "next/link"
@@ -40,26 +42,23 @@ export function SettingsInterface() {
   const [autoTranscribe, setAutoTranscribe] = useState(true)
   const [geminiKey, setGeminiKey] = useState("")
   const [helperAppEnabled, setHelperAppEnabled] = useState<boolean>(false)
-  const [token, setToken] = useState<string | null>(null); // NEW: State for JWT
+  const { session, loading: sessionLoading } = useSession()
 
-  // NEW: Effect to load token from localStorage on component mount
   useEffect(() => {
-    const storedToken = localStorage.getItem("jwt");
-    if (storedToken) {
-      setToken(storedToken);
-    } else {
+    if (sessionLoading) return;
+    if (!session) {
       toast.error("You must be logged in to view settings.");
     }
     setMounted(true);
-  }, []);
+  }, [session, sessionLoading]);
 
-  // NEW: Effect to load settings only after the token is loaded
+  // Load settings only once logged in
   useEffect(() => {
     const loadSettings = async () => {
-      if (!token) return; // Don't load if there's no token
+      if (!session) return; // Don't load while logged out
       try {
-        const key = await invoke<string | null>("get_api_key_command", { token })
-        const helperAppToggle = await invoke<boolean>("get_helper_window_state_command", { token })
+        const key = await invoke<string | null>("get_api_key_command")
+        const helperAppToggle = await invoke<boolean>("get_helper_window_state_command")
         if (key) setGeminiKey(key)
         setHelperAppEnabled(helperAppToggle)
         console.log(helperAppToggle)
@@ -69,7 +68,7 @@ export function SettingsInterface() {
       }
     }
     loadSettings()
-  }, [token])
+  }, [session])
 
   const formatDate = (dateString: string | null | undefined) => {
     const date = dateString ? new Date(dateString) : new Date()
@@ -105,12 +104,12 @@ export function SettingsInterface() {
   }
 
   const getMemos = async (): Promise<VoiceMemo[]> => {
-    if (!token) {
+    if (!session) {
         toast.error("Authentication required.");
         return [];
     }
     try {
-      return await invoke("get_memos_command", { token }) as VoiceMemo[]
+      return await fetchAllMemos<VoiceMemo>()
     } catch (error) {
       console.error("Failed to get memos:", error)
       return []
@@ -140,12 +139,12 @@ export function SettingsInterface() {
   }
 
   const handleClearAllData = async () => {
-    if (!token) return toast.error("Authentication required.");
-    if (confirm("Are you sure you want to delete all memos? This action cannot be undone.")) {
+    if (!session) return toast.error("Authentication required.");
+    if (confirm("Are you sure you want to delete all memos? They stay in the trash until it is emptied.")) {
       try {
-        await invoke("clear_all_memos", { token })
+        const message = await invoke<string>("clear_all_memos")
         clearAllMemos()
-        toast.success("All memos deleted successfully")
+        toast.success(message)
       } catch (error) {
         console.error("Failed to delete memos:", error)
         toast.error("Failed to delete memos")
@@ -154,13 +153,13 @@ export function SettingsInterface() {
   }
 
   const handleSaveApiKey = async () => {
-    if (!token) return toast.error("Authentication required.");
+    if (!session) return toast.error("Authentication required.");
     if (!geminiKey.trim()) {
       toast.error("Please enter a valid API key")
       return
     }
     try {
-      await invoke("save_api_key_command", { token, geminiKey })
+      await invoke("save_api_key_command", { geminiKey })
       toast.success("Gemini API key saved successfully")
     } catch (error) {
       console.error("Failed to save API key:", error)
@@ -169,9 +168,9 @@ export function SettingsInterface() {
   }
 
   const handleDeleteApiKey = async () => {
-    if (!token) return toast.error("Authentication required.");
+    if (!session) return toast.error("Authentication required.");
     try {
-      await invoke("delete_gemini_api_key_command", { token })
+      await invoke("delete_gemini_api_key_command")
       setGeminiKey("")
       toast.success("Gemini API key deleted successfully")
     } catch (error) {
@@ -181,9 +180,9 @@ export function SettingsInterface() {
   }
 
   const toggleHelperApp = async (enabled: boolean) => {
-    if (!token) return toast.error("Authentication required.");
+    if (!session) return toast.error("Authentication required.");
     try {
-      await invoke("toggle_helper_window_command", { enabled, token })
+      await invoke("toggle_helper_window_command", { enabled })
       setHelperAppEnabled(enabled)
       toast.success(`Helper window ${enabled ? "enabled" : "disabled"}`)
     } catch (error) {
@@ -196,7 +195,7 @@ export function SettingsInterface() {
     return null
   }
 
-  if (!token) {
+  if (!session) {
     return (
         <div className="flex items-center justify-center h-full p-6 text-center">
             <Card className="max-w-md">
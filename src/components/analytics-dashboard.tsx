@@ -3,7 +3,8 @@
 import React, { useEffect, useState } from 'react';
 import { Card, CardContent, CardHeader, CardTitle } from '@/components/ui/card';
 import { motion, Variants } from 'framer-motion';
-import { invoke } from '@tauri-apps/api/core';
+import { fetchAllMemos } from '../../lib/memo-api';
+import { useSession } from '../../hooks/useSession';
 import { 
   BarChart, 
   Bar, 
@@ -92,27 +93,23 @@ export const AnalyticsDashboard = () => {
   const [tagData, setTagData] = useState<TagDataItem[]>([]);
   const [weeklyData, setWeeklyData] = useState<WeeklyDataItem[]>([]);
   const [loading, setLoading] = useState(true);
-  const [token, setToken] = useState<string | null>(null); // NEW: State for JWT
+  const { session, loading: sessionLoading } = useSession();
 
-  // NEW: Effect to load token from localStorage on component mount
   useEffect(() => {
-    const storedToken = localStorage.getItem("jwt");
-    if (storedToken) {
-      setToken(storedToken);
-    } else {
-      setLoading(false); // If no token, stop loading
+    if (!sessionLoading && !session) {
+      setLoading(false); // If not logged in, stop loading
       toast.error("You must be logged in to view analytics.");
     }
-  }, []);
+  }, [session, sessionLoading]);
 
-  // NEW: Effect to fetch data only after the token is loaded
+  // Fetch data only once logged in
   useEffect(() => {
     const fetchData = async () => {
-      if (!token) return; // Don't fetch if there's no token
+      if (!session) return; // Don't fetch while logged out
 
       setLoading(true);
       try {
-        const memos = await invoke<Memo[]>("get_memos_command", { token });
+        const memos = await fetchAllMemos<Memo>();
         
         const totalMemos = memos.length;
         const activeMemos = memos.filter(memo => 
@@ -167,7 +164,7 @@ export const AnalyticsDashboard = () => {
     };
 
     fetchData();
-  }, [token]); // This effect now depends on the token
+  }, [session]); // This effect depends on the session
 
   const processWeeklyData = (memos: Memo[]): WeeklyDataItem[] => {
     const days = ['Mon', 'Tue', 'Wed', 'Thu', 'Fri', 'Sat', 'Sun'];
@@ -230,7 +227,7 @@ export const AnalyticsDashboard = () => {
     );
   }
   
-  if (!token) {
+  if (!session) {
     return (
         <div className="flex items-center justify-center h-full p-6 text-center">
             <Card className="max-w-md">